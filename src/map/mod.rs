@@ -553,7 +553,7 @@ impl Map {
 
                                     let vertex_count = LittleEndian::read_u32(&part[0x58 + 0x0..]) as usize;
                                     let vertex_offset = LittleEndian::read_u32(&part[0x58 + 0xC..]) as usize;
-                                    let vertex_size = vertex_count * 0x44;
+                                    let vertex_size = vertex_count * model_vertex_size(LittleEndian::read_u32(&part[0x54..]));
                                     let vertex_end = vertex_offset + vertex_size;
                                     if vertex_end > vertices.len() {
                                         return Err("invalid model vertex offset/size");
@@ -588,7 +588,7 @@ impl Map {
 
                                         let vertex_count = LittleEndian::read_u32(&part[0x58 + 0x0..]) as usize;
                                         let vertex_offset = LittleEndian::read_u32(&part[0x58 + 0xC..]) as usize;
-                                        let vertex_size = vertex_count * 0x44;
+                                        let vertex_size = vertex_count * model_vertex_size(LittleEndian::read_u32(&part[0x54..]));
                                         let vertex_end = vertex_offset + vertex_size;
 
                                         let asset_data_len = asset_data_vec.len() as u32;
@@ -713,8 +713,8 @@ impl Map {
         sbsp_data.reserve_exact(padded_sbsp_length);
         resource_data.reserve_exact(padded_resource_data_length);
 
-        // Tag ID, File offset
-        let mut sbsps : Vec<(usize, usize)> = Vec::new();
+        // Tag ID, File offset, Size, Memory address
+        let mut sbsps : Vec<(usize, usize, usize, u32)> = Vec::new();
         sbsps.reserve_exact(sbsp_count);
 
         match self.tag_array.principal_tag() {
@@ -879,6 +879,8 @@ impl Map {
                         }
                     }
                 },
+                // mode (Xbox models) tags have to be converted with set_model_xbox first.
+                0x6D6F6465 => return Err("mode tags cannot be written to a PC map"),
                 // Get models...
                 0x6D6F6432 => {
                     let asset_data = match tag.asset_data.as_mut() {
@@ -933,9 +935,14 @@ impl Map {
                                 return Err("invalid model index offset/size");
                             }
 
+                            // PC maps can only use uncompressed vertices.
+                            if LittleEndian::read_u32(&part[0x54..]) == MODEL_VERTEX_TYPE_COMPRESSED {
+                                return Err("compressed model vertices cannot be written to a PC map");
+                            }
+
                             let vertex_count = LittleEndian::read_u32(&part[0x58 + 0x0..]) as usize;
                             let vertex_offset = LittleEndian::read_u32(&part[0x58 + 0xC..]) as usize;
-                            let vertex_size = vertex_count * 0x44;
+                            let vertex_size = vertex_count * MODEL_VERTEX_SIZE;
                             let vertex_end = vertex_offset + vertex_size;
                             if vertex_end > asset_data.len() {
                                 return Err("invalid model vertex offset/size");
@@ -957,7 +964,7 @@ impl Map {
                 },
                 // Get sbsp tags...
                 0x73627370 => {
-                    sbsps.push((tag_index, sbsp_data.len()));
+                    sbsps.push((tag_index, sbsp_data.len(), tag.data.as_ref().unwrap().len(), *tag.memory_address.as_ref().unwrap()));
                     sbsp_data.append(&mut tag.data.as_mut().unwrap());
                     tag.data = None;
                 },
@@ -1053,6 +1060,8 @@ impl Map {
                         for b in &sbsps {
                             if b.0 == tag_index {
                                 LittleEndian::write_u32(&mut sbsp[0x0..], b.1 as u32 + 0x800);
+                                LittleEndian::write_u32(&mut sbsp[0x4..], b.2 as u32);
+                                LittleEndian::write_u32(&mut sbsp[0x8..], b.3);
                                 found = true;
                             }
                         }
@@ -1096,6 +1105,107 @@ impl Map {
 
         Ok(new_cache_file)
     }
+
+    /// Get the tag indices of the structure BSPs of the principal scenario tag, in order.
+    pub fn bsps(&self) -> Result<Vec<usize>,&'static str> {
+        let scenario = self.p_scenario_tag()?;
+        let data = scenario.data.as_ref().unwrap();
+        let mut bsps = Vec::new();
+        for offset in scenario.reflexive_elements(0x5A4, 32)? {
+            let tag_id = LittleEndian::read_u32(&data[offset + 0x1C..]);
+            match self.tag_array.tags().get(tag_id as usize & 0xFFFF) {
+                Some(n) if tag_id != 0xFFFFFFFF && n.tag_class.0 == 0x73627370 => bsps.push(tag_id as usize & 0xFFFF),
+                _ => return Err("scenario tag sbsp dependency is broken")
+            }
+        }
+        Ok(bsps)
+    }
+
+    /// Add an sbsp tag to the end of the principal scenario tag's structure BSPs, returning the
+    /// index of the new BSP.
+    ///
+    /// The file offset and size of the BSP are filled in by `as_cache_file`.
+    pub fn add_bsp(&mut self, sbsp_tag : usize) -> Result<usize,&'static str> {
+        let memory_address = match self.tag_array.tags().get(sbsp_tag) {
+            Some(n) if n.tag_class.0 == 0x73627370 && n.data.is_some() => *n.memory_address.as_ref().unwrap(),
+            Some(_) => return Err("tag is not an sbsp tag with data"),
+            None => return Err("tag index out of bounds")
+        };
+        let mut bsps = self.bsps()?;
+        if bsps.contains(&sbsp_tag) {
+            return Err("sbsp tag is already in the scenario tag");
+        }
+        bsps.push(sbsp_tag);
+        let scenario = self.tag_array.principal_tag().unwrap();
+        let mut entries = self.p_bsp_entries()?;
+        let mut entry = [0u8 ; 32];
+        LittleEndian::write_u32(&mut entry[0x8..], memory_address);
+        LittleEndian::write_u32(&mut entry[0x10..], 0x73627370);
+        LittleEndian::write_u32(&mut entry[0x1C..], tag_index_to_tag_id(sbsp_tag));
+        entries.extend_from_slice(&entry);
+        self.tag_array.tags_mut()[scenario].replace_reflexive(0x5A4, 32, &entries);
+        Ok(bsps.len() - 1)
+    }
+
+    /// Remove a structure BSP from the principal scenario tag. Later BSPs are moved down by one
+    /// index, and the BSP indices of player starting locations in later BSPs are moved down to
+    /// match. BSP indices used elsewhere, such as in scripts, are not changed.
+    ///
+    /// Returns `Err` without changing the map if a player starting location is in the BSP.
+    ///
+    /// An sbsp tag that is not referenced by the scenario tag cannot be loaded from a cache file,
+    /// so the sbsp tag is also removed from the tag array and returned.
+    pub fn remove_bsp(&mut self, bsp : usize) -> Result<Tag,&'static str> {
+        let bsps = self.bsps()?;
+        let sbsp_tag = match bsps.get(bsp) {
+            Some(n) => *n,
+            None => return Err("bsp index out of bounds")
+        };
+        let mut locations = self.p_scenario_tag()?.player_starting_locations()?;
+        if locations.iter().any(|l| l.bsp_index as usize == bsp) {
+            return Err("a player starting location is in the bsp");
+        }
+        let scenario = self.tag_array.principal_tag().unwrap();
+        if locations.iter().any(|l| l.bsp_index as usize > bsp) {
+            for l in locations.iter_mut().filter(|l| l.bsp_index as usize > bsp) {
+                l.bsp_index -= 1;
+            }
+            self.tag_array.tags_mut()[scenario].set_player_starting_locations(&locations)?;
+        }
+        let mut entries = self.p_bsp_entries()?;
+        entries.drain(bsp * 32 .. (bsp + 1) * 32);
+        self.tag_array.tags_mut()[scenario].replace_reflexive(0x5A4, 32, &entries);
+        Ok(self.tag_array.remove(sbsp_tag))
+    }
+
+    /// Add an object tag to the palette of an object type in the principal scenario tag,
+    /// returning its palette index. If the tag is not in this map, it is imported recursively
+    /// from the origin tag array, if one is given.
+    pub fn add_object_palette_entry(&mut self, object_type : ScenarioObjectType, tag_path : &str, origin_tag_array : Option<&TagArray>) -> Result<usize,&'static str> {
+        self.p_scenario_tag()?;
+        let tag_index = self.tag_array.find_or_insert_recursive(tag_path, object_type.tag_class(), origin_tag_array)?;
+        let scenario = self.tag_array.principal_tag().unwrap();
+        self.tag_array.add_object_palette_entry(scenario, object_type, tag_index)
+    }
+
+    // Get the principal scenario tag, checking that it has a structure BSP reflexive.
+    fn p_scenario_tag(&self) -> Result<&Tag,&'static str> {
+        let scenario = match self.tag_array.principal_tag() {
+            Some(n) => &self.tag_array.tags()[n],
+            None => return Err("map has no scenario tag")
+        };
+        match scenario.data.as_ref() {
+            Some(n) if scenario.tag_class.0 == 0x73636E72 && n.len() >= 0x5A4 + 0xC => Ok(scenario),
+            _ => Err("invalid scenario tag")
+        }
+    }
+
+    // Get a copy of the principal scenario tag's structure BSP entries.
+    fn p_bsp_entries(&self) -> Result<Vec<u8>,&'static str> {
+        let scenario = self.p_scenario_tag()?;
+        let data = scenario.data.as_ref().unwrap();
+        Ok(scenario.reflexive_elements(0x5A4, 32)?.into_iter().flat_map(|o| data[o .. o + 32].to_owned()).collect())
+    }
 }
 
 // Convenience for reading a tag reflexive.
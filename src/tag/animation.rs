@@ -0,0 +1,395 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, ModelLOD, ANTR};
+use super::gltf::{json_float, json_floats, json_string, normalized_quaternion};
+use super::super::string_from_slice;
+
+/// Size of a node in an antr tag.
+pub const ANIMATION_NODE_SIZE : usize = 0x40;
+
+/// Size of an animation in an antr tag.
+pub const ANIMATION_SIZE : usize = 0xB4;
+
+/// Frames per second of animations.
+pub const ANIMATION_FRAME_RATE : f32 = 30.0;
+
+#[derive(Clone)]
+/// A node in an antr tag.
+pub struct AnimationNode {
+    pub name : String,
+    pub next_sibling : Option<usize>,
+    pub first_child : Option<usize>,
+    pub parent : Option<usize>
+}
+
+#[derive(Clone)]
+/// An animation in an antr tag.
+pub struct Animation {
+    pub name : String,
+    pub animation_type : u16,
+    pub frame_count : usize,
+
+    /// This is the size of each frame of frame data in bytes.
+    pub frame_size : usize,
+
+    /// This is the type of root movement stored in the frame info, from 0 (none) to 3 (dx, dy,
+    /// dz, and dyaw).
+    pub frame_info_type : u16,
+    pub node_count : usize,
+    pub loop_frame : usize,
+    pub next_animation : Option<usize>,
+
+    /// If true, the frame data is compressed.
+    pub compressed : bool
+}
+
+#[derive(Clone,Copy,PartialEq)]
+/// The transform of a node on one frame, relative to its parent.
+pub struct AnimationNodeFrame {
+    /// This is the rotation quaternion (i, j, k, w).
+    pub rotation : [f32 ; 4],
+    pub translation : [f32 ; 3],
+    pub scale : f32
+}
+
+impl Tag {
+    /// Get the nodes of this antr tag.
+    pub fn animation_nodes(&self) -> Result<Vec<AnimationNode>,&'static str> {
+        let data = self.p_animation_data()?;
+        let node_or_none = |index : i16| if index < 0 { None } else { Some(index as usize) };
+        let mut nodes = Vec::new();
+        for offset in self.reflexive_elements(0x68, ANIMATION_NODE_SIZE)? {
+            let node = &data[offset .. offset + ANIMATION_NODE_SIZE];
+            nodes.push(AnimationNode {
+                name : string_from_slice(&node[0..0x20])?,
+                next_sibling : node_or_none(LittleEndian::read_i16(&node[0x20..])),
+                first_child : node_or_none(LittleEndian::read_i16(&node[0x22..])),
+                parent : node_or_none(LittleEndian::read_i16(&node[0x24..]))
+            });
+        }
+        Ok(nodes)
+    }
+
+    /// Get the animations of this antr tag.
+    pub fn animations(&self) -> Result<Vec<Animation>,&'static str> {
+        let data = self.p_animation_data()?;
+        let mut animations = Vec::new();
+        for offset in self.reflexive_elements(0x74, ANIMATION_SIZE)? {
+            let animation = &data[offset .. offset + ANIMATION_SIZE];
+            let next_animation = LittleEndian::read_i16(&animation[0x38..]);
+            animations.push(Animation {
+                name : string_from_slice(&animation[0..0x20])?,
+                animation_type : LittleEndian::read_u16(&animation[0x20..]),
+                frame_count : LittleEndian::read_u16(&animation[0x22..]) as usize,
+                frame_size : LittleEndian::read_u16(&animation[0x24..]) as usize,
+                frame_info_type : LittleEndian::read_u16(&animation[0x26..]),
+                node_count : LittleEndian::read_u16(&animation[0x2C..]) as usize,
+                loop_frame : LittleEndian::read_u16(&animation[0x2E..]) as usize,
+                next_animation : if next_animation < 0 { None } else { Some(next_animation as usize) },
+                compressed : LittleEndian::read_u16(&animation[0x3A..]) & 1 != 0
+            });
+        }
+        Ok(animations)
+    }
+
+    /// Decode the frames of an animation in this antr tag. Each frame has a transform for each
+    /// node of the animation.
+    ///
+    /// Nodes that are not animated use the transform from the animation's default data.
+    /// Compressed frame data is read from the frame data at the offset stored in the animation,
+    /// and its keyframes are interpolated to get every frame.
+    pub fn animation_frames(&self, animation : usize) -> Result<Vec<Vec<AnimationNodeFrame>>,&'static str> {
+        let data = self.p_animation_data()?;
+        let offset = match self.reflexive_elements(0x74, ANIMATION_SIZE)?.get(animation) {
+            Some(n) => *n,
+            None => return Err("animation index out of bounds")
+        };
+        let info = &self.animations()?[animation];
+        if info.compressed {
+            let frame_data = match self.data_block_offset(offset + 0xA0) {
+                Some((o, s)) => &data[o .. o + s],
+                None => return Err("animation data is too small")
+            };
+            return match frame_data.get(LittleEndian::read_u32(&data[offset + 0x88..]) as usize..) {
+                Some(n) => decompress_animation(n, info.frame_count, info.node_count),
+                None => Err("invalid compressed animation data offset")
+            };
+        }
+
+        // Each node has a bit for whether its rotation, translation, and scale are animated.
+        let flags = |o : usize| (LittleEndian::read_u32(&data[offset + o..]) as u64) | (LittleEndian::read_u32(&data[offset + o + 4..]) as u64) << 32;
+        let (rotation_flags, translation_flags, scale_flags) = (flags(0x6C), flags(0x5C), flags(0x7C));
+        if info.node_count > 64 {
+            return Err("animation has too many nodes");
+        }
+        let animated_size = |animated : bool| (0..info.node_count).map(|n| {
+            let bit = |f : u64| (f >> n & 1 != 0) == animated;
+            (if bit(rotation_flags) { 8 } else { 0 }) + (if bit(translation_flags) { 12 } else { 0 }) + (if bit(scale_flags) { 4 } else { 0 })
+        }).sum::<usize>();
+        let frame_size = animated_size(true);
+        let default_size = animated_size(false);
+        if frame_size != info.frame_size {
+            return Err("animation frame size does not match its node flags");
+        }
+
+        let block = |o : usize, size : usize| -> Result<&[u8],&'static str> {
+            if size == 0 {
+                return Ok(&[]);
+            }
+            match self.data_block_offset(offset + o) {
+                Some((o, s)) if size <= s => Ok(&data[o .. o + size]),
+                _ => Err("animation data is too small")
+            }
+        };
+        let default_data = block(0x8C, default_size)?;
+        let frame_data = block(0xA0, frame_size * info.frame_count)?;
+
+        let mut frames = Vec::with_capacity(info.frame_count);
+        for frame in 0..info.frame_count {
+            let mut frame_offset = frame * frame_size;
+            let mut default_offset = 0;
+            let mut nodes = Vec::with_capacity(info.node_count);
+            for n in 0..info.node_count {
+                let mut read = |animated : bool, size : usize| -> &[u8] {
+                    if animated {
+                        frame_offset += size;
+                        &frame_data[frame_offset - size .. frame_offset]
+                    }
+                    else {
+                        default_offset += size;
+                        &default_data[default_offset - size .. default_offset]
+                    }
+                };
+                let rotation = read(rotation_flags >> n & 1 != 0, 8);
+                let rotation = [
+                    LittleEndian::read_i16(&rotation[0..]) as f32 / 32767.0,
+                    LittleEndian::read_i16(&rotation[2..]) as f32 / 32767.0,
+                    LittleEndian::read_i16(&rotation[4..]) as f32 / 32767.0,
+                    LittleEndian::read_i16(&rotation[6..]) as f32 / 32767.0
+                ];
+                let translation = read(translation_flags >> n & 1 != 0, 12);
+                let translation = [LittleEndian::read_f32(&translation[0..]), LittleEndian::read_f32(&translation[4..]), LittleEndian::read_f32(&translation[8..])];
+                let scale = LittleEndian::read_f32(read(scale_flags >> n & 1 != 0, 4));
+                nodes.push(AnimationNodeFrame {
+                    rotation : rotation,
+                    translation : translation,
+                    scale : scale
+                });
+            }
+            frames.push(nodes);
+        }
+        Ok(frames)
+    }
+
+    /// Export a mod2 tag as a glTF 2.0 file at a level of detail with the animations of this antr
+    /// tag. Animation nodes are bound to the model's nodes by name.
+    pub fn export_animations_gltf(&self, model : &Tag, lod : ModelLOD) -> Result<String,&'static str> {
+        let nodes = self.animation_nodes()?;
+        let model_nodes = model.model_nodes()?;
+        let bindings : Vec<Option<usize>> = nodes.iter().map(|n| model_nodes.iter().position(|m| m.name.eq_ignore_ascii_case(&n.name))).collect();
+        let mut gltf = model.p_model_gltf(lod)?;
+
+        for (a, animation) in self.animations()?.iter().enumerate() {
+            let frames = self.animation_frames(a)?;
+            if frames.is_empty() {
+                continue;
+            }
+            let times : Vec<f32> = (0..frames.len()).map(|f| f as f32 / ANIMATION_FRAME_RATE).collect();
+            let input = gltf.add_floats(&times, 1, "SCALAR", None, true);
+
+            let mut samplers = Vec::new();
+            let mut channels = Vec::new();
+            for n in 0..animation.node_count.min(nodes.len()) {
+                let target = match bindings[n] {
+                    Some(n) => n,
+                    None => continue
+                };
+                let rotations : Vec<f32> = frames.iter().flat_map(|f| normalized_quaternion(&f[n].rotation).to_vec()).collect();
+                let translations : Vec<f32> = frames.iter().flat_map(|f| f[n].translation.to_vec()).collect();
+                let scales : Vec<f32> = frames.iter().flat_map(|f| vec![f[n].scale ; 3]).collect();
+                for &(path, ref values, components, element_type) in &[("rotation", rotations, 4, "VEC4"), ("translation", translations, 3, "VEC3"), ("scale", scales, 3, "VEC3")] {
+                    let output = gltf.add_floats(values, components, element_type, None, false);
+                    samplers.push(format!("{{\"input\":{},\"output\":{}}}", input, output));
+                    channels.push(format!("{{\"sampler\":{},\"target\":{{\"node\":{},\"path\":\"{}\"}}}}", samplers.len() - 1, target, path));
+                }
+            }
+            if channels.is_empty() {
+                continue;
+            }
+            gltf.animations.push(format!("{{\"name\":{},\"samplers\":[{}],\"channels\":[{}]}}", json_string(&animation.name), samplers.join(","), channels.join(",")));
+        }
+        Ok(gltf.as_json())
+    }
+
+    /// Describe the nodes and animations of this antr tag as JSON, including the decoded frames of
+    /// each animation.
+    pub fn export_animations_json(&self) -> Result<String,&'static str> {
+        let nodes = self.animation_nodes()?;
+        let index_or_null = |index : Option<usize>| match index { Some(n) => n.to_string(), None => "null".to_owned() };
+        let mut json = String::from("{\"nodes\":[");
+        json += &nodes.iter().map(|n| format!("{{\"name\":{},\"parent\":{},\"first_child\":{},\"next_sibling\":{}}}", json_string(&n.name), index_or_null(n.parent), index_or_null(n.first_child), index_or_null(n.next_sibling))).collect::<Vec<String>>().join(",");
+        json += "],\"animations\":[";
+        let mut animations = Vec::new();
+        for (a, animation) in self.animations()?.iter().enumerate() {
+            let mut entry = format!("{{\"name\":{},\"type\":{},\"frame_count\":{},\"node_count\":{},\"loop_frame\":{},\"next_animation\":{},\"frame_info_type\":{},\"compressed\":{}",
+                json_string(&animation.name), animation.animation_type, animation.frame_count, animation.node_count, animation.loop_frame, index_or_null(animation.next_animation), animation.frame_info_type, animation.compressed);
+            let frames = self.animation_frames(a)?;
+            entry += ",\"frames\":[";
+            entry += &frames.iter().map(|f| format!("[{}]", f.iter().map(|n| format!("{{\"rotation\":{},\"translation\":{},\"scale\":{}}}", json_floats(&n.rotation), json_floats(&n.translation), json_float(n.scale))).collect::<Vec<String>>().join(","))).collect::<Vec<String>>().join(",");
+            entry += "]}";
+            animations.push(entry);
+        }
+        json += &animations.join(",");
+        json += "]}";
+        Ok(json)
+    }
+
+    // Get the tag data of an antr tag, checking that the header is there.
+    fn p_animation_data(&self) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != ANTR {
+            return Err("tag is not a model animations tag");
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= 0x80 => Ok(n),
+            Some(_) => Err("antr tag is too small"),
+            None => Err("antr tag has no data")
+        }
+    }
+}
+
+// Decode compressed frame data. It starts with twelve offsets for rotations, then translations,
+// then scales: the keyframe headers, the keyframe frame indices, the default values, and the
+// keyframe values. Each node has a keyframe header for each of these with the number of
+// keyframes in the lowest 12 bits and the index of the first keyframe in the upper 20 bits. A
+// node's default value is its value on the first frame, values between keyframes are linearly
+// interpolated, and the last value is held until the end of the animation.
+fn decompress_animation(data : &[u8], frame_count : usize, node_count : usize) -> Result<Vec<Vec<AnimationNodeFrame>>,&'static str> {
+    if data.len() < 0x30 {
+        return Err("compressed animation data is too small");
+    }
+    let slice = |offset : usize, size : usize| match offset.checked_add(size) {
+        Some(end) if end <= data.len() => Ok(&data[offset .. end]),
+        _ => Err("compressed animation data is too small")
+    };
+
+    let identity = AnimationNodeFrame { rotation : [0.0, 0.0, 0.0, 1.0], translation : [0.0 ; 3], scale : 1.0 };
+    let mut frames = vec![vec![identity ; node_count] ; frame_count];
+
+    // Rotations are 48-bit quaternions, translations are three floats, and scales are one float.
+    for channel in 0..3 {
+        let offset = |i : usize| LittleEndian::read_u32(&data[channel * 0x10 + i * 4..]) as usize;
+        let (headers, keyframes, defaults, values) = (offset(0), offset(1), offset(2), offset(3));
+        let value_size = [6, 12, 4][channel];
+        let read_value = |bytes : &[u8]| -> [f32 ; 4] {
+            match channel {
+                0 => decompress_quaternion(bytes),
+                1 => [LittleEndian::read_f32(&bytes[0..]), LittleEndian::read_f32(&bytes[4..]), LittleEndian::read_f32(&bytes[8..]), 0.0],
+                _ => [LittleEndian::read_f32(bytes), 0.0, 0.0, 0.0]
+            }
+        };
+
+        for n in 0..node_count {
+            let header = LittleEndian::read_u32(slice(headers + n * 4, 4)?);
+            let (keyframe_count, first_keyframe) = ((header & 0xFFF) as usize, (header >> 12) as usize);
+
+            // Frame zero uses the default value, and each keyframe gives the value on a later frame.
+            let mut points = vec![(0, read_value(slice(defaults + n * value_size, value_size)?))];
+            for k in first_keyframe .. first_keyframe + keyframe_count {
+                let frame = LittleEndian::read_u16(slice(keyframes + k * 2, 2)?) as usize;
+                if frame <= points[points.len() - 1].0 {
+                    return Err("compressed animation keyframes are out of order");
+                }
+                points.push((frame, read_value(slice(values + k * value_size, value_size)?)));
+            }
+
+            let mut point = 0;
+            for (f, frame) in frames.iter_mut().enumerate() {
+                while point + 1 < points.len() && points[point + 1].0 <= f {
+                    point += 1;
+                }
+                let value = match points.get(point + 1) {
+                    Some(next) => {
+                        let (start, mut end) = (points[point].1, next.1);
+                        let t = (f - points[point].0) as f32 / (next.0 - points[point].0) as f32;
+
+                        // Take the shorter path between rotations.
+                        if channel == 0 && (0..4).map(|i| start[i] * end[i]).sum::<f32>() < 0.0 {
+                            for e in &mut end {
+                                *e = -*e;
+                            }
+                        }
+                        let mut value = [0.0 ; 4];
+                        for i in 0..4 {
+                            value[i] = start[i] + (end[i] - start[i]) * t;
+                        }
+                        if channel == 0 { normalized_quaternion(&value) } else { value }
+                    },
+                    None => points[point].1
+                };
+                let node = &mut frame[n];
+                match channel {
+                    0 => node.rotation = value,
+                    1 => node.translation = [value[0], value[1], value[2]],
+                    _ => node.scale = value[0]
+                }
+            }
+        }
+    }
+    Ok(frames)
+}
+
+// Decode a quaternion packed into 48 bits as the 12-bit ones' complement components i, j, k, and
+// w, from the highest bits to the lowest bits.
+fn decompress_quaternion(bytes : &[u8]) -> [f32 ; 4] {
+    let packed = (LittleEndian::read_u16(&bytes[0..]) as u64) << 32 | (LittleEndian::read_u16(&bytes[2..]) as u64) << 16 | LittleEndian::read_u16(&bytes[4..]) as u64;
+    let component = |shift : u64| {
+        let value = (packed >> shift & 0xFFF) as i32;
+        (if value & 0x800 != 0 { value - 0xFFF } else { value }) as f32
+    };
+    normalized_quaternion(&[component(36), component(24), component(12), component(0)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_animation_interpolates_keyframes() {
+        // One node with a default rotation and scale, and a translation keyframe on frame 2.
+        let mut stream = vec![0u8 ; 0x60];
+        for (i, offset) in [0x30, 0x34, 0x34, 0x3A, 0x3A, 0x3E, 0x40, 0x4C, 0x58, 0x5C, 0x5C, 0x60].iter().enumerate() {
+            LittleEndian::write_u32(&mut stream[i * 4..], *offset);
+        }
+        LittleEndian::write_u16(&mut stream[0x38..], 0x7FF);
+        LittleEndian::write_u32(&mut stream[0x3A..], 1);
+        LittleEndian::write_u16(&mut stream[0x3E..], 2);
+        LittleEndian::write_f32(&mut stream[0x4C..], 2.0);
+        LittleEndian::write_f32(&mut stream[0x5C..], 1.0);
+
+        // The compressed data is placed after some uncompressed frame data.
+        let address = 0x40440000;
+        let animation = 0x80;
+        let frame_data = animation + ANIMATION_SIZE;
+        let mut data = vec![0u8 ; frame_data + 4 + stream.len()];
+        data[frame_data + 4..].copy_from_slice(&stream);
+        LittleEndian::write_u32(&mut data[0x74..], 1);
+        LittleEndian::write_u32(&mut data[0x78..], address + animation as u32);
+        LittleEndian::write_u16(&mut data[animation + 0x22..], 4);
+        LittleEndian::write_u16(&mut data[animation + 0x2C..], 1);
+        LittleEndian::write_u16(&mut data[animation + 0x3A..], 1);
+        LittleEndian::write_u32(&mut data[animation + 0x88..], 4);
+        LittleEndian::write_u32(&mut data[animation + 0xA0..], 4 + stream.len() as u32);
+        LittleEndian::write_u32(&mut data[animation + 0xAC..], address + frame_data as u32);
+        let tag = Tag::new("characters\\test\\test".to_owned(), (ANTR, 0xFFFFFFFF, 0xFFFFFFFF), Some(data), None, false, None, Some(address));
+
+        let frames = tag.animation_frames(0).unwrap();
+        assert_eq!(frames.len(), 4);
+        let x : Vec<f32> = frames.iter().map(|f| f[0].translation[0]).collect();
+        assert_eq!(x, vec![0.0, 1.0, 2.0, 2.0]);
+        for frame in &frames {
+            assert_eq!(frame[0].rotation, [0.0, 0.0, 0.0, 1.0]);
+            assert_eq!(frame[0].scale, 1.0);
+        }
+    }
+}
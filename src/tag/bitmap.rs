@@ -0,0 +1,1102 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::Tag;
+use super::png::{encode_png, decode_png};
+
+/// Size of a bitmap data entry in a bitm tag.
+pub const BITMAP_DATA_SIZE : usize = 0x30;
+
+/// Bitmap data with this flag has power-of-two dimensions.
+pub const BITMAP_FLAG_POWER_OF_TWO : u16 = 0x1;
+
+/// Bitmap data with this flag is DXT compressed.
+pub const BITMAP_FLAG_COMPRESSED : u16 = 0x2;
+
+/// Bitmap data with this flag is palettized.
+pub const BITMAP_FLAG_PALETTIZED : u16 = 0x4;
+
+/// Bitmap data with this flag is swizzled.
+pub const BITMAP_FLAG_SWIZZLED : u16 = 0x8;
+
+/// Bitmap data with this flag is stored in bitmaps.map rather than in the map file.
+pub const BITMAP_FLAG_EXTERNAL : u16 = 0x100;
+
+#[derive(PartialEq,Clone,Copy)]
+/// Bitmap types determine how the pixel data of a bitmap is arranged.
+pub enum BitmapType {
+    /// This is a regular two dimensional texture.
+    Texture2D,
+
+    /// This is a three dimensional texture made of a number of slices.
+    Texture3D,
+
+    /// This is a cube map made of six faces.
+    CubeMap,
+
+    /// This is a white texture.
+    White,
+
+    /// The type isn't known.
+    Unknown(u16)
+}
+impl BitmapType {
+    /// Convert a 16-bit unsigned integer into a BitmapType.
+    pub fn from_u16(bitmap_type : u16) -> BitmapType {
+        match bitmap_type {
+            0 => BitmapType::Texture2D,
+            1 => BitmapType::Texture3D,
+            2 => BitmapType::CubeMap,
+            3 => BitmapType::White,
+            n => BitmapType::Unknown(n)
+        }
+    }
+
+    /// Convert a BitmapType to its equivalent 16-bit integer.
+    pub fn as_u16(&self) -> u16 {
+        match *self {
+            BitmapType::Texture2D => 0,
+            BitmapType::Texture3D => 1,
+            BitmapType::CubeMap => 2,
+            BitmapType::White => 3,
+            BitmapType::Unknown(n) => n
+        }
+    }
+}
+
+#[derive(PartialEq,Clone,Copy)]
+/// Bitmap formats determine how each pixel is stored.
+pub enum BitmapFormat {
+    A8,
+    Y8,
+    AY8,
+    A8Y8,
+    R5G6B5,
+    A1R5G5B5,
+    A4R4G4B4,
+    X8R8G8B8,
+    A8R8G8B8,
+    DXT1,
+    DXT3,
+    DXT5,
+    P8,
+
+    /// The format isn't known.
+    Unknown(u16)
+}
+impl BitmapFormat {
+    /// Convert a 16-bit unsigned integer into a BitmapFormat.
+    pub fn from_u16(format : u16) -> BitmapFormat {
+        match format {
+            0 => BitmapFormat::A8,
+            1 => BitmapFormat::Y8,
+            2 => BitmapFormat::AY8,
+            3 => BitmapFormat::A8Y8,
+            6 => BitmapFormat::R5G6B5,
+            8 => BitmapFormat::A1R5G5B5,
+            9 => BitmapFormat::A4R4G4B4,
+            10 => BitmapFormat::X8R8G8B8,
+            11 => BitmapFormat::A8R8G8B8,
+            14 => BitmapFormat::DXT1,
+            15 => BitmapFormat::DXT3,
+            16 => BitmapFormat::DXT5,
+            17 => BitmapFormat::P8,
+            n => BitmapFormat::Unknown(n)
+        }
+    }
+
+    /// Convert a BitmapFormat to its equivalent 16-bit integer.
+    pub fn as_u16(&self) -> u16 {
+        match *self {
+            BitmapFormat::A8 => 0,
+            BitmapFormat::Y8 => 1,
+            BitmapFormat::AY8 => 2,
+            BitmapFormat::A8Y8 => 3,
+            BitmapFormat::R5G6B5 => 6,
+            BitmapFormat::A1R5G5B5 => 8,
+            BitmapFormat::A4R4G4B4 => 9,
+            BitmapFormat::X8R8G8B8 => 10,
+            BitmapFormat::A8R8G8B8 => 11,
+            BitmapFormat::DXT1 => 14,
+            BitmapFormat::DXT3 => 15,
+            BitmapFormat::DXT5 => 16,
+            BitmapFormat::P8 => 17,
+            BitmapFormat::Unknown(n) => n
+        }
+    }
+
+    /// Determine whether or not the format is DXT compressed.
+    pub fn is_compressed(&self) -> bool {
+        match *self {
+            BitmapFormat::DXT1 | BitmapFormat::DXT3 | BitmapFormat::DXT5 => true,
+            _ => false
+        }
+    }
+
+    /// Get the number of bytes used for a block of pixels. Compressed formats use 4x4 blocks,
+    /// while other formats use single pixels.
+    ///
+    /// Returns `None` if the format isn't known.
+    pub fn block_size(&self) -> Option<usize> {
+        match *self {
+            BitmapFormat::A8 | BitmapFormat::Y8 | BitmapFormat::AY8 | BitmapFormat::P8 => Some(1),
+            BitmapFormat::A8Y8 | BitmapFormat::R5G6B5 | BitmapFormat::A1R5G5B5 | BitmapFormat::A4R4G4B4 => Some(2),
+            BitmapFormat::X8R8G8B8 | BitmapFormat::A8R8G8B8 => Some(4),
+            BitmapFormat::DXT1 => Some(8),
+            BitmapFormat::DXT3 | BitmapFormat::DXT5 => Some(16),
+            BitmapFormat::Unknown(_) => None
+        }
+    }
+
+    /// Get the number of bytes used by an image with the given dimensions.
+    ///
+    /// Returns `None` if the format isn't known.
+    pub fn image_size(&self, width : usize, height : usize) -> Option<usize> {
+        let block_size = match self.block_size() {
+            Some(n) => n,
+            None => return None
+        };
+        if self.is_compressed() {
+            Some(((width + 3) / 4) * ((height + 3) / 4) * block_size)
+        }
+        else {
+            Some(width * height * block_size)
+        }
+    }
+}
+
+#[derive(Clone)]
+/// Bitmap data describes a single bitmap in a bitm tag.
+pub struct BitmapData {
+    /// This is the offset of the bitmap data in the tag data.
+    pub offset : usize,
+    pub width : u16,
+    pub height : u16,
+    pub depth : u16,
+    pub bitmap_type : BitmapType,
+    pub format : BitmapFormat,
+    pub flags : u16,
+
+    /// This is the number of mipmaps, not counting the full size image.
+    pub mipmap_count : u16,
+
+    /// This is the offset of the pixel data. For internalized bitmaps, this is an offset in the
+    /// tag's asset data.
+    pub pixel_data_offset : u32,
+
+    /// This is the size of the pixel data.
+    pub pixel_data_size : u32
+}
+impl BitmapData {
+    /// Get the number of faces (for cube maps) or slices (for 3D textures) in each mipmap.
+    pub fn layers(&self, mipmap : usize) -> usize {
+        match self.bitmap_type {
+            BitmapType::CubeMap => 6,
+            BitmapType::Texture3D => ((self.depth as usize) >> mipmap).max(1),
+            _ => 1
+        }
+    }
+
+    /// Get the width and height of a mipmap.
+    pub fn mipmap_dimensions(&self, mipmap : usize) -> (usize,usize) {
+        (((self.width as usize) >> mipmap).max(1), ((self.height as usize) >> mipmap).max(1))
+    }
+
+    /// Calculate the size of the pixel data, including all faces, slices, and mipmaps.
+    ///
+    /// Returns `None` if the format isn't known.
+    pub fn calculate_pixel_data_size(&self) -> Option<usize> {
+        let mut size = 0;
+        for m in 0..self.mipmap_count as usize + 1 {
+            let (width,height) = self.mipmap_dimensions(m);
+            size += match self.format.image_size(width, height) {
+                Some(n) => n,
+                None => return None
+            } * self.layers(m);
+        }
+        Some(size)
+    }
+}
+
+#[derive(Clone)]
+/// A decoded image of a single face, slice, and mipmap of a bitmap.
+pub struct BitmapImage {
+    pub width : usize,
+    pub height : usize,
+
+    /// This is the face of a cube map or the slice of a 3D texture. It is zero for 2D textures.
+    pub layer : usize,
+    pub mipmap : usize,
+
+    /// These are 32-bit RGBA pixels.
+    pub pixels : Vec<u8>
+}
+impl BitmapImage {
+    /// Encode the image as a PNG file.
+    pub fn as_png(&self) -> Vec<u8> {
+        encode_png(self.width, self.height, &self.pixels)
+    }
+}
+
+/// Decode pixels of a bitmap format into 32-bit RGBA pixels.
+///
+/// P8 bitmaps are converted using `palette` if given. Otherwise, each palette index is written
+/// as a grayscale value. This is not what the engine shows, as it uses its own P8-bump palette of
+/// normal vectors, which is not stored in any tag.
+///
+/// Returns `Err` if the data is too small or the format isn't known.
+pub fn decode_pixels(format : BitmapFormat, width : usize, height : usize, data : &[u8], palette : Option<&[[u8 ; 4] ; 256]>) -> Result<Vec<u8>,&'static str> {
+    let size = match format.image_size(width, height) {
+        Some(n) => n,
+        None => return Err("unknown bitmap format")
+    };
+    if data.len() < size {
+        return Err("bitmap pixel data is too small");
+    }
+
+    let mut pixels = Vec::with_capacity(width * height * 4);
+
+    if format.is_compressed() {
+        pixels.resize(width * height * 4, 0);
+        let block_size = format.block_size().unwrap();
+        let blocks_wide = (width + 3) / 4;
+        for by in 0..(height + 3) / 4 {
+            for bx in 0..blocks_wide {
+                let block = &data[(by * blocks_wide + bx) * block_size .. (by * blocks_wide + bx + 1) * block_size];
+                let decoded = decode_dxt_block(format, block);
+                for y in 0..4 {
+                    for x in 0..4 {
+                        let px = bx * 4 + x;
+                        let py = by * 4 + y;
+                        if px >= width || py >= height {
+                            continue;
+                        }
+                        let offset = (py * width + px) * 4;
+                        pixels[offset .. offset + 4].copy_from_slice(&decoded[y * 4 + x]);
+                    }
+                }
+            }
+        }
+        return Ok(pixels);
+    }
+
+    let expand = |value : u16, bits : u32| -> u8 {
+        let max = (1u32 << bits) - 1;
+        ((value as u32 * 255 + max / 2) / max) as u8
+    };
+
+    for i in 0..width * height {
+        let pixel : [u8 ; 4] = match format {
+            BitmapFormat::A8 => [255, 255, 255, data[i]],
+            BitmapFormat::Y8 => [data[i], data[i], data[i], 255],
+            BitmapFormat::AY8 => [data[i], data[i], data[i], data[i]],
+            BitmapFormat::P8 => match palette {
+                Some(p) => p[data[i] as usize],
+                None => [data[i], data[i], data[i], 255]
+            },
+            BitmapFormat::A8Y8 => [data[i * 2], data[i * 2], data[i * 2], data[i * 2 + 1]],
+            BitmapFormat::R5G6B5 => {
+                let v = LittleEndian::read_u16(&data[i * 2..]);
+                [expand(v >> 11, 5), expand((v >> 5) & 0x3F, 6), expand(v & 0x1F, 5), 255]
+            },
+            BitmapFormat::A1R5G5B5 => {
+                let v = LittleEndian::read_u16(&data[i * 2..]);
+                [expand((v >> 10) & 0x1F, 5), expand((v >> 5) & 0x1F, 5), expand(v & 0x1F, 5), if v & 0x8000 != 0 { 255 } else { 0 }]
+            },
+            BitmapFormat::A4R4G4B4 => {
+                let v = LittleEndian::read_u16(&data[i * 2..]);
+                [expand((v >> 8) & 0xF, 4), expand((v >> 4) & 0xF, 4), expand(v & 0xF, 4), expand(v >> 12, 4)]
+            },
+            BitmapFormat::X8R8G8B8 => [data[i * 4 + 2], data[i * 4 + 1], data[i * 4], 255],
+            BitmapFormat::A8R8G8B8 => [data[i * 4 + 2], data[i * 4 + 1], data[i * 4], data[i * 4 + 3]],
+            _ => unreachable!()
+        };
+        pixels.extend_from_slice(&pixel);
+    }
+
+    Ok(pixels)
+}
+
+// Convert an R5G6B5 color into RGBA.
+fn rgb565(color : u16) -> [u8 ; 4] {
+    let r = (color >> 11) as u32;
+    let g = ((color >> 5) & 0x3F) as u32;
+    let b = (color & 0x1F) as u32;
+    [((r * 255 + 15) / 31) as u8, ((g * 255 + 31) / 63) as u8, ((b * 255 + 15) / 31) as u8, 255]
+}
+
+// Decode a 4x4 block of DXT compressed pixels.
+fn decode_dxt_block(format : BitmapFormat, block : &[u8]) -> [[u8 ; 4] ; 16] {
+    let mut pixels = [[0u8 ; 4] ; 16];
+    let color_block = if format == BitmapFormat::DXT1 { block } else { &block[8..] };
+
+    let c0 = LittleEndian::read_u16(&color_block[0..]);
+    let c1 = LittleEndian::read_u16(&color_block[2..]);
+    let mut colors = [rgb565(c0), rgb565(c1), [0u8 ; 4], [0u8 ; 4]];
+    let mix = |a : u8, b : u8, wa : u32, wb : u32| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+    if c0 > c1 || format != BitmapFormat::DXT1 {
+        for c in 0..3 {
+            colors[2][c] = mix(colors[0][c], colors[1][c], 2, 1);
+            colors[3][c] = mix(colors[0][c], colors[1][c], 1, 2);
+        }
+        colors[2][3] = 255;
+        colors[3][3] = 255;
+    }
+    else {
+        for c in 0..3 {
+            colors[2][c] = mix(colors[0][c], colors[1][c], 1, 1);
+        }
+        colors[2][3] = 255;
+        colors[3] = [0, 0, 0, 0];
+    }
+
+    let indices = LittleEndian::read_u32(&color_block[4..]);
+    for i in 0..16 {
+        pixels[i] = colors[((indices >> (i * 2)) & 3) as usize];
+    }
+
+    match format {
+        BitmapFormat::DXT3 => {
+            let alpha = LittleEndian::read_u64(&block[0..]);
+            for i in 0..16 {
+                pixels[i][3] = (((alpha >> (i * 4)) & 0xF) * 17) as u8;
+            }
+        },
+        BitmapFormat::DXT5 => {
+            let a0 = block[0] as u32;
+            let a1 = block[1] as u32;
+            let mut alphas = [a0, a1, 0, 0, 0, 0, 0, 0];
+            if a0 > a1 {
+                for i in 1..7 {
+                    alphas[i + 1] = ((7 - i as u32) * a0 + i as u32 * a1) / 7;
+                }
+            }
+            else {
+                for i in 1..5 {
+                    alphas[i + 1] = ((5 - i as u32) * a0 + i as u32 * a1) / 5;
+                }
+                alphas[6] = 0;
+                alphas[7] = 255;
+            }
+            let indices = LittleEndian::read_u64(&block[0..]) >> 16;
+            for i in 0..16 {
+                pixels[i][3] = alphas[((indices >> (i * 3)) & 7) as usize] as u8;
+            }
+        },
+        _ => ()
+    }
+
+    pixels
+}
+
+impl Tag {
+    /// Get the bitmap data entries of a bitm tag.
+    ///
+    /// Returns `Err` if this is not a bitmap tag or if the bitmap data reflexive is invalid.
+    pub fn bitmap_data(&self) -> Result<Vec<BitmapData>,&'static str> {
+        if self.tag_class.0 != 0x6269746D {
+            return Err("tag is not a bitmap tag");
+        }
+        let data = match self.data.as_ref() {
+            Some(n) => n,
+            None => return Err("bitmap tag has no data")
+        };
+        if data.len() < 0x60 + 0xC {
+            return Err("bitmap tag is too small");
+        }
+        let count = LittleEndian::read_u32(&data[0x60..]) as usize;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let offset = match self.offset_from_memory_address(LittleEndian::read_u32(&data[0x64..])) {
+            Some(n) => n,
+            None => return Err("invalid address on bitmap reflexive")
+        };
+        if offset + count * BITMAP_DATA_SIZE > data.len() {
+            return Err("invalid address on bitmap reflexive");
+        }
+        let mut bitmaps = Vec::with_capacity(count);
+        for i in 0..count {
+            let bitmap_offset = offset + i * BITMAP_DATA_SIZE;
+            let bitmap = &data[bitmap_offset .. bitmap_offset + BITMAP_DATA_SIZE];
+            bitmaps.push(BitmapData {
+                offset : bitmap_offset,
+                width : LittleEndian::read_u16(&bitmap[0x4..]),
+                height : LittleEndian::read_u16(&bitmap[0x6..]),
+                depth : LittleEndian::read_u16(&bitmap[0x8..]),
+                bitmap_type : BitmapType::from_u16(LittleEndian::read_u16(&bitmap[0xA..])),
+                format : BitmapFormat::from_u16(LittleEndian::read_u16(&bitmap[0xC..])),
+                flags : LittleEndian::read_u16(&bitmap[0xE..]),
+                mipmap_count : LittleEndian::read_u16(&bitmap[0x14..]),
+                pixel_data_offset : LittleEndian::read_u32(&bitmap[0x18..]),
+                pixel_data_size : LittleEndian::read_u32(&bitmap[0x1C..])
+            });
+        }
+        Ok(bitmaps)
+    }
+
+    /// Get the pixel data of a bitmap in this bitm tag from its asset data.
+    ///
+    /// Returns `Err` if the bitmap is stored in bitmaps.map or if the pixel data is invalid.
+    pub fn bitmap_pixel_data(&self, bitmap : &BitmapData) -> Result<&[u8],&'static str> {
+        if bitmap.flags & BITMAP_FLAG_EXTERNAL != 0 {
+            return Err("bitmap pixel data is not stored in the map");
+        }
+        let asset_data = match self.asset_data.as_ref() {
+            Some(n) => n,
+            None => return Err("bitmap tag has no asset data")
+        };
+        let start = bitmap.pixel_data_offset as usize;
+        let end = start + bitmap.pixel_data_size as usize;
+        if end > asset_data.len() {
+            return Err("invalid data offset on bitmap");
+        }
+        Ok(&asset_data[start..end])
+    }
+
+    /// Decode every face, slice, and mipmap of a bitmap in this bitm tag into RGBA images.
+    ///
+    /// Images are ordered by mipmap, then by face or slice. P8 bitmaps are converted using
+    /// `palette` if given, or with their palette indices as grayscale if not, as described in
+    /// `decode_pixels`.
+    pub fn decode_bitmap(&self, bitmap_index : usize, palette : Option<&[[u8 ; 4] ; 256]>) -> Result<Vec<BitmapImage>,&'static str> {
+        let bitmaps = self.bitmap_data()?;
+        let bitmap = match bitmaps.get(bitmap_index) {
+            Some(n) => n,
+            None => return Err("bitmap index out of bounds")
+        };
+        let pixel_data = self.bitmap_pixel_data(bitmap)?;
+        let unswizzled;
+        let pixel_data = if bitmap.flags & BITMAP_FLAG_SWIZZLED != 0 {
+            unswizzled = swizzle_bitmap_pixel_data(bitmap, pixel_data, true)?;
+            &unswizzled[..]
+        }
+        else {
+            pixel_data
+        };
+
+        let mut images = Vec::new();
+        let mut offset = 0;
+        for m in 0..bitmap.mipmap_count as usize + 1 {
+            let (width,height) = bitmap.mipmap_dimensions(m);
+            let size = match bitmap.format.image_size(width, height) {
+                Some(n) => n,
+                None => return Err("unknown bitmap format")
+            };
+            for l in 0..bitmap.layers(m) {
+                if offset + size > pixel_data.len() {
+                    return Err("bitmap pixel data is too small");
+                }
+                images.push(BitmapImage {
+                    width : width,
+                    height : height,
+                    layer : l,
+                    mipmap : m,
+                    pixels : decode_pixels(bitmap.format, width, height, &pixel_data[offset .. offset + size], palette)?
+                });
+                offset += size;
+            }
+        }
+        Ok(images)
+    }
+
+    /// Export a bitmap in this bitm tag as a DDS file, including every face, slice, and mipmap.
+    ///
+    /// Formats that DDS does not support (P8 and AY8) are converted to A8R8G8B8. P8 bitmaps are
+    /// converted with their palette indices as grayscale.
+    pub fn bitmap_as_dds(&self, bitmap_index : usize) -> Result<Vec<u8>,&'static str> {
+        let bitmaps = self.bitmap_data()?;
+        let bitmap = match bitmaps.get(bitmap_index) {
+            Some(n) => n,
+            None => return Err("bitmap index out of bounds")
+        };
+
+        // Get each image as (mipmap, layer, data) in the bitmap's format or as A8R8G8B8.
+        let convert = bitmap.format == BitmapFormat::P8 || bitmap.format == BitmapFormat::AY8;
+        let mut images : Vec<(usize,usize,Vec<u8>)> = Vec::new();
+        if convert {
+            for image in self.decode_bitmap(bitmap_index, None)? {
+                let mut bgra = image.pixels;
+                for p in bgra.chunks_mut(4) {
+                    p.swap(0, 2);
+                }
+                images.push((image.mipmap, image.layer, bgra));
+            }
+        }
+        else {
+            let pixel_data = self.bitmap_pixel_data(bitmap)?;
+            let unswizzled;
+            let pixel_data = if bitmap.flags & BITMAP_FLAG_SWIZZLED != 0 {
+                unswizzled = swizzle_bitmap_pixel_data(bitmap, pixel_data, true)?;
+                &unswizzled[..]
+            }
+            else {
+                pixel_data
+            };
+            let mut offset = 0;
+            for m in 0..bitmap.mipmap_count as usize + 1 {
+                let (width,height) = bitmap.mipmap_dimensions(m);
+                let size = bitmap.format.image_size(width, height).unwrap();
+                for l in 0..bitmap.layers(m) {
+                    if offset + size > pixel_data.len() {
+                        return Err("bitmap pixel data is too small");
+                    }
+                    images.push((m, l, pixel_data[offset .. offset + size].to_owned()));
+                    offset += size;
+                }
+            }
+        }
+        let format = if convert { BitmapFormat::A8R8G8B8 } else { bitmap.format };
+
+        let mut header = [0u8 ; 128];
+        // "DDS "
+        LittleEndian::write_u32(&mut header[0x0..], 0x20534444);
+        LittleEndian::write_u32(&mut header[0x4..], 124);
+
+        // Caps, height, width, pixel format, mipmap count, and linear size or pitch
+        let mut flags = 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000 | if format.is_compressed() { 0x80000 } else { 0x8 };
+        if bitmap.bitmap_type == BitmapType::Texture3D {
+            flags |= 0x800000;
+        }
+        LittleEndian::write_u32(&mut header[0x8..], flags);
+        LittleEndian::write_u32(&mut header[0xC..], bitmap.height as u32);
+        LittleEndian::write_u32(&mut header[0x10..], bitmap.width as u32);
+        LittleEndian::write_u32(&mut header[0x14..], if format.is_compressed() {
+            format.image_size(bitmap.width as usize, bitmap.height as usize).unwrap()
+        }
+        else {
+            bitmap.width as usize * format.block_size().unwrap()
+        } as u32);
+        LittleEndian::write_u32(&mut header[0x18..], bitmap.depth as u32);
+        LittleEndian::write_u32(&mut header[0x1C..], bitmap.mipmap_count as u32 + 1);
+
+        // Pixel format: flags, FourCC, bit count, and the red, green, blue, and alpha masks
+        let pixel_format : (u32, u32, u32, u32, u32, u32, u32) = match format {
+            BitmapFormat::DXT1 => (0x4, 0x31545844, 0, 0, 0, 0, 0),
+            BitmapFormat::DXT3 => (0x4, 0x33545844, 0, 0, 0, 0, 0),
+            BitmapFormat::DXT5 => (0x4, 0x35545844, 0, 0, 0, 0, 0),
+            BitmapFormat::A8 => (0x2, 0, 8, 0, 0, 0, 0xFF),
+            BitmapFormat::Y8 => (0x20000, 0, 8, 0xFF, 0, 0, 0),
+            BitmapFormat::A8Y8 => (0x20001, 0, 16, 0xFF, 0, 0, 0xFF00),
+            BitmapFormat::R5G6B5 => (0x40, 0, 16, 0xF800, 0x7E0, 0x1F, 0),
+            BitmapFormat::A1R5G5B5 => (0x41, 0, 16, 0x7C00, 0x3E0, 0x1F, 0x8000),
+            BitmapFormat::A4R4G4B4 => (0x41, 0, 16, 0xF00, 0xF0, 0xF, 0xF000),
+            BitmapFormat::X8R8G8B8 => (0x40, 0, 32, 0xFF0000, 0xFF00, 0xFF, 0),
+            BitmapFormat::A8R8G8B8 => (0x41, 0, 32, 0xFF0000, 0xFF00, 0xFF, 0xFF000000),
+            _ => return Err("unknown bitmap format")
+        };
+        LittleEndian::write_u32(&mut header[0x4C..], 32);
+        LittleEndian::write_u32(&mut header[0x50..], pixel_format.0);
+        LittleEndian::write_u32(&mut header[0x54..], pixel_format.1);
+        LittleEndian::write_u32(&mut header[0x58..], pixel_format.2);
+        LittleEndian::write_u32(&mut header[0x5C..], pixel_format.3);
+        LittleEndian::write_u32(&mut header[0x60..], pixel_format.4);
+        LittleEndian::write_u32(&mut header[0x64..], pixel_format.5);
+        LittleEndian::write_u32(&mut header[0x68..], pixel_format.6);
+
+        // Caps
+        let mut caps = 0x1000;
+        if bitmap.mipmap_count > 0 {
+            caps |= 0x400000 | 0x8;
+        }
+        let mut caps2 = 0;
+        match bitmap.bitmap_type {
+            BitmapType::CubeMap => {
+                caps |= 0x8;
+                caps2 = 0x200 | 0xFC00;
+            },
+            BitmapType::Texture3D => {
+                caps |= 0x8;
+                caps2 = 0x200000;
+            },
+            _ => ()
+        }
+        LittleEndian::write_u32(&mut header[0x6C..], caps);
+        LittleEndian::write_u32(&mut header[0x70..], caps2);
+
+        // Halo stores each face within each mipmap, but DDS stores each mipmap within each face.
+        if bitmap.bitmap_type == BitmapType::CubeMap {
+            images.sort_by_key(|i| (i.1, i.0));
+        }
+
+        let mut dds = header.to_vec();
+        for image in images {
+            dds.extend_from_slice(&image.2);
+        }
+        Ok(dds)
+    }
+}
+
+/// Encode 32-bit RGBA pixels into a bitmap format.
+///
+/// Returns `Err` if there are not exactly `width * height * 4` bytes of pixels or if the format
+/// cannot be encoded. P8 bitmaps cannot be encoded.
+pub fn encode_pixels(format : BitmapFormat, width : usize, height : usize, pixels : &[u8]) -> Result<Vec<u8>,&'static str> {
+    if pixels.len() != width * height * 4 {
+        return Err("pixel data does not match the dimensions");
+    }
+
+    if format.is_compressed() {
+        let mut data = Vec::with_capacity(format.image_size(width, height).unwrap());
+        for by in 0..(height + 3) / 4 {
+            for bx in 0..(width + 3) / 4 {
+                // Pixels outside of the image repeat the nearest edge pixel.
+                let mut block = [[0u8 ; 4] ; 16];
+                for y in 0..4 {
+                    for x in 0..4 {
+                        let px = (bx * 4 + x).min(width - 1);
+                        let py = (by * 4 + y).min(height - 1);
+                        let offset = (py * width + px) * 4;
+                        block[y * 4 + x].copy_from_slice(&pixels[offset .. offset + 4]);
+                    }
+                }
+                data.extend_from_slice(&encode_dxt_block(format, &block));
+            }
+        }
+        return Ok(data);
+    }
+
+    let luminance = |p : &[u8]| ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114 + 500) / 1000) as u8;
+    let reduce = |value : u8, bits : u32| -> u16 {
+        let max = (1u32 << bits) - 1;
+        ((value as u32 * max + 127) / 255) as u16
+    };
+
+    let mut data = Vec::with_capacity(width * height * format.block_size().unwrap_or(0));
+    for p in pixels.chunks(4) {
+        let mut write_u16 = |value : u16| {
+            let mut bytes = [0u8 ; 2];
+            LittleEndian::write_u16(&mut bytes, value);
+            data.extend_from_slice(&bytes);
+        };
+        match format {
+            BitmapFormat::A8 => data.push(p[3]),
+            BitmapFormat::Y8 => data.push(luminance(p)),
+            BitmapFormat::AY8 => data.push(((p[0] as u32 + p[1] as u32 + p[2] as u32 + p[3] as u32) / 4) as u8),
+            BitmapFormat::A8Y8 => {
+                data.push(luminance(p));
+                data.push(p[3]);
+            },
+            BitmapFormat::R5G6B5 => write_u16((reduce(p[0], 5) << 11) | (reduce(p[1], 6) << 5) | reduce(p[2], 5)),
+            BitmapFormat::A1R5G5B5 => write_u16((if p[3] >= 128 { 0x8000 } else { 0 }) | (reduce(p[0], 5) << 10) | (reduce(p[1], 5) << 5) | reduce(p[2], 5)),
+            BitmapFormat::A4R4G4B4 => write_u16((reduce(p[3], 4) << 12) | (reduce(p[0], 4) << 8) | (reduce(p[1], 4) << 4) | reduce(p[2], 4)),
+            BitmapFormat::X8R8G8B8 => data.extend_from_slice(&[p[2], p[1], p[0], 255]),
+            BitmapFormat::A8R8G8B8 => data.extend_from_slice(&[p[2], p[1], p[0], p[3]]),
+            BitmapFormat::P8 => return Err("palettized bitmaps cannot be encoded"),
+            _ => return Err("unknown bitmap format")
+        }
+    }
+    Ok(data)
+}
+
+// Convert RGBA into an R5G6B5 color.
+fn to_rgb565(color : &[u8]) -> u16 {
+    (((color[0] as u16 * 31 + 127) / 255) << 11) | (((color[1] as u16 * 63 + 127) / 255) << 5) | ((color[2] as u16 * 31 + 127) / 255)
+}
+
+// Compress a 4x4 block of pixels with DXT compression.
+fn encode_dxt_block(format : BitmapFormat, block : &[[u8 ; 4] ; 16]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(16);
+
+    // Alpha comes before the color block on DXT3 and DXT5.
+    match format {
+        BitmapFormat::DXT3 => {
+            let mut alpha = 0u64;
+            for i in 0..16 {
+                alpha |= ((block[i][3] as u64 * 15 + 127) / 255) << (i * 4);
+            }
+            let mut bytes = [0u8 ; 8];
+            LittleEndian::write_u64(&mut bytes, alpha);
+            data.extend_from_slice(&bytes);
+        },
+        BitmapFormat::DXT5 => {
+            let a0 = block.iter().map(|p| p[3]).max().unwrap();
+            let a1 = block.iter().map(|p| p[3]).min().unwrap();
+            let mut indices = 0u64;
+            if a0 != a1 {
+                for i in 0..16 {
+                    // Index 0 and 1 are the endpoints, and 2-7 interpolate between them.
+                    let step = ((a0 - block[i][3]) as u32 * 7 + (a0 - a1) as u32 / 2) / (a0 - a1) as u32;
+                    let index = match step {
+                        0 => 0,
+                        7 => 1,
+                        n => n + 1
+                    };
+                    indices |= (index as u64) << (i * 3);
+                }
+            }
+            let mut bytes = [0u8 ; 8];
+            LittleEndian::write_u64(&mut bytes, indices << 16);
+            bytes[0] = a0;
+            bytes[1] = a1;
+            data.extend_from_slice(&bytes);
+        },
+        _ => ()
+    }
+
+    // Use the two pixels furthest apart along the color range as the endpoints.
+    let distance = |a : &[u8], b : &[u8]| -> i32 {
+        (0..3).map(|c| (a[c] as i32 - b[c] as i32) * (a[c] as i32 - b[c] as i32)).sum()
+    };
+    let transparent = format == BitmapFormat::DXT1 && block.iter().any(|p| p[3] < 128);
+    let opaque : Vec<&[u8 ; 4]> = block.iter().filter(|p| !transparent || p[3] >= 128).collect();
+    let mut endpoints = (0,0);
+    let mut furthest = -1;
+    for i in 0..opaque.len() {
+        for j in i..opaque.len() {
+            let d = distance(opaque[i], opaque[j]);
+            if d > furthest {
+                furthest = d;
+                endpoints = (i,j);
+            }
+        }
+    }
+    let (mut c0, mut c1) = if opaque.is_empty() {
+        (0, 0)
+    }
+    else {
+        (to_rgb565(opaque[endpoints.0]), to_rgb565(opaque[endpoints.1]))
+    };
+
+    // Four color blocks need c0 > c1, while three color blocks with transparency need c0 <= c1.
+    if (c0 < c1) != transparent && c0 != c1 {
+        let swap = c0;
+        c0 = c1;
+        c1 = swap;
+    }
+    let four_colors = c0 > c1;
+    let colors = {
+        let a = rgb565(c0);
+        let b = rgb565(c1);
+        let mix = |wa : u32, wb : u32| -> [u8 ; 4] {
+            let mut color = [255u8 ; 4];
+            for c in 0..3 {
+                color[c] = ((a[c] as u32 * wa + b[c] as u32 * wb) / (wa + wb)) as u8;
+            }
+            color
+        };
+        if four_colors {
+            [a, b, mix(2, 1), mix(1, 2)]
+        }
+        else {
+            [a, b, mix(1, 1), [0, 0, 0, 0]]
+        }
+    };
+
+    let mut indices = 0u32;
+    for i in 0..16 {
+        let index = if transparent && block[i][3] < 128 {
+            3
+        }
+        else {
+            let candidates = if four_colors { 4 } else { 3 };
+            (0..candidates).min_by_key(|c| distance(&block[i], &colors[*c])).unwrap()
+        };
+        indices |= (index as u32) << (i * 2);
+    }
+
+    let mut bytes = [0u8 ; 8];
+    LittleEndian::write_u16(&mut bytes[0..], c0);
+    LittleEndian::write_u16(&mut bytes[2..], c1);
+    LittleEndian::write_u32(&mut bytes[4..], indices);
+    data.extend_from_slice(&bytes);
+    data
+}
+
+/// Halve the width and height of 32-bit RGBA pixels by averaging each 2x2 group of pixels.
+///
+/// Dimensions of 1 are not halved.
+pub fn downsample_pixels(width : usize, height : usize, pixels : &[u8]) -> (usize,usize,Vec<u8>) {
+    let new_width = (width / 2).max(1);
+    let new_height = (height / 2).max(1);
+    let mut new_pixels = Vec::with_capacity(new_width * new_height * 4);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            for c in 0..4 {
+                let mut total = 0u32;
+                for (sx, sy) in &[(0,0), (1,0), (0,1), (1,1)] {
+                    let px = (x * 2 + sx).min(width - 1);
+                    let py = (y * 2 + sy).min(height - 1);
+                    total += pixels[(py * width + px) * 4 + c] as u32;
+                }
+                new_pixels.push(((total + 2) / 4) as u8);
+            }
+        }
+    }
+    (new_width, new_height, new_pixels)
+}
+
+impl Tag {
+    /// Replace a 2D bitmap in this bitm tag with 32-bit RGBA pixels, encoding them in a format.
+    ///
+    /// If `mipmap_count` is `None`, mipmaps are generated down to 1x1. The bitmap data entry is
+    /// updated, and the pixel data is stored in the tag's asset data so it is used when building
+    /// the cache file. Swizzled bitmaps stay swizzled unless the new format is compressed or the
+    /// new dimensions are not powers of two.
+    ///
+    /// Returns `Err` without changing the tag if the bitmap cannot be replaced.
+    pub fn replace_bitmap(&mut self, bitmap_index : usize, width : usize, height : usize, pixels : &[u8], format : BitmapFormat, mipmap_count : Option<u16>) -> Result<(),&'static str> {
+        let bitmaps = self.bitmap_data()?;
+        let bitmap = match bitmaps.get(bitmap_index) {
+            Some(n) => n.to_owned(),
+            None => return Err("bitmap index out of bounds")
+        };
+        if bitmap.bitmap_type != BitmapType::Texture2D {
+            return Err("only 2D bitmaps can be replaced");
+        }
+        if width == 0 || height == 0 || width > 0xFFFF || height > 0xFFFF {
+            return Err("invalid bitmap dimensions");
+        }
+        if pixels.len() != width * height * 4 {
+            return Err("pixel data does not match the dimensions");
+        }
+
+        let mut largest_dimension = width.max(height);
+        let mut maximum_mipmaps = 0;
+        while largest_dimension > 1 {
+            largest_dimension /= 2;
+            maximum_mipmaps += 1;
+        }
+        let mipmap_count = match mipmap_count {
+            Some(n) => (n as usize).min(maximum_mipmaps),
+            None => maximum_mipmaps
+        };
+
+        // Encode each mipmap.
+        let mut pixel_data = encode_pixels(format, width, height, pixels)?;
+        let mut mipmap = (width, height, pixels.to_owned());
+        for _ in 0..mipmap_count {
+            mipmap = downsample_pixels(mipmap.0, mipmap.1, &mipmap.2);
+            pixel_data.append(&mut encode_pixels(format, mipmap.0, mipmap.1, &mipmap.2)?);
+        }
+
+        let mut flags = bitmap.flags & !(BITMAP_FLAG_COMPRESSED | BITMAP_FLAG_PALETTIZED | BITMAP_FLAG_SWIZZLED | BITMAP_FLAG_EXTERNAL | BITMAP_FLAG_POWER_OF_TWO);
+        if format.is_compressed() {
+            flags |= BITMAP_FLAG_COMPRESSED;
+        }
+        if width.is_power_of_two() && height.is_power_of_two() {
+            flags |= BITMAP_FLAG_POWER_OF_TWO;
+
+            // Keep swizzled bitmaps swizzled if the new format allows it.
+            if bitmap.flags & BITMAP_FLAG_SWIZZLED != 0 && !format.is_compressed() {
+                flags |= BITMAP_FLAG_SWIZZLED;
+                let new_bitmap = BitmapData {
+                    width : width as u16,
+                    height : height as u16,
+                    depth : 1,
+                    format : format,
+                    mipmap_count : mipmap_count as u16,
+                    .. bitmap.to_owned()
+                };
+                pixel_data = swizzle_bitmap_pixel_data(&new_bitmap, &pixel_data, false)?;
+            }
+        }
+
+        // Rebuild the asset data with the new pixel data in place of the old pixel data.
+        let mut asset_data = Vec::new();
+        let mut offsets = Vec::with_capacity(bitmaps.len());
+        for (i,b) in bitmaps.iter().enumerate() {
+            if i == bitmap_index {
+                offsets.push(Some(asset_data.len()));
+                asset_data.extend_from_slice(&pixel_data);
+            }
+            else if b.flags & BITMAP_FLAG_EXTERNAL == 0 {
+                offsets.push(Some(asset_data.len()));
+                asset_data.extend_from_slice(self.bitmap_pixel_data(b)?);
+            }
+            else {
+                offsets.push(None);
+            }
+        }
+
+        let tag_data = self.data.as_mut().unwrap();
+        for (b,offset) in bitmaps.iter().zip(offsets) {
+            match offset {
+                Some(n) => LittleEndian::write_u32(&mut tag_data[b.offset + 0x18..], n as u32),
+                None => ()
+            }
+        }
+        let entry = &mut tag_data[bitmap.offset .. bitmap.offset + BITMAP_DATA_SIZE];
+        LittleEndian::write_u16(&mut entry[0x4..], width as u16);
+        LittleEndian::write_u16(&mut entry[0x6..], height as u16);
+        LittleEndian::write_u16(&mut entry[0x8..], 1);
+        LittleEndian::write_u16(&mut entry[0xC..], format.as_u16());
+        LittleEndian::write_u16(&mut entry[0xE..], flags);
+        LittleEndian::write_u16(&mut entry[0x14..], mipmap_count as u16);
+        LittleEndian::write_u32(&mut entry[0x1C..], pixel_data.len() as u32);
+        self.asset_data = Some(asset_data);
+
+        Ok(())
+    }
+
+    /// Swizzle or unswizzle every internal uncompressed bitmap in this bitm tag, rewriting the
+    /// tag's asset data and the swizzled flag of each bitmap. Xbox maps use swizzled bitmaps, while
+    /// PC maps do not.
+    ///
+    /// Bitmaps that are compressed, external, or not power-of-two in every dimension are left alone,
+    /// as are bitmaps that are already in the requested state.
+    ///
+    /// Returns `Err` without changing the tag if any bitmap could not be converted.
+    pub fn set_bitmaps_swizzled(&mut self, swizzled : bool) -> Result<(),&'static str> {
+        let bitmaps = self.bitmap_data()?;
+        let mut converted = Vec::new();
+        for b in &bitmaps {
+            if b.flags & BITMAP_FLAG_EXTERNAL != 0 || b.format.is_compressed() || (b.flags & BITMAP_FLAG_SWIZZLED != 0) == swizzled {
+                continue;
+            }
+            if !(b.width as usize).is_power_of_two() || !(b.height as usize).is_power_of_two() || (b.bitmap_type == BitmapType::Texture3D && !(b.depth as usize).is_power_of_two()) {
+                continue;
+            }
+            let pixel_data = self.bitmap_pixel_data(b)?;
+            converted.push((b, swizzle_bitmap_pixel_data(b, pixel_data, !swizzled)?));
+        }
+
+        for (b,pixel_data) in converted {
+            let offset = b.pixel_data_offset as usize;
+            self.asset_data.as_mut().unwrap()[offset .. offset + pixel_data.len()].copy_from_slice(&pixel_data);
+            let flags = if swizzled { b.flags | BITMAP_FLAG_SWIZZLED } else { b.flags & !BITMAP_FLAG_SWIZZLED };
+            LittleEndian::write_u16(&mut self.data.as_mut().unwrap()[b.offset + 0xE..], flags);
+        }
+
+        Ok(())
+    }
+
+    /// Replace a 2D bitmap in this bitm tag with a PNG file. See `replace_bitmap` for more
+    /// information.
+    pub fn replace_bitmap_with_png(&mut self, bitmap_index : usize, png : &[u8], format : BitmapFormat, mipmap_count : Option<u16>) -> Result<(),&'static str> {
+        let (width, height, pixels) = decode_png(png)?;
+        self.replace_bitmap(bitmap_index, width, height, &pixels, format, mipmap_count)
+    }
+}
+
+// Get the offset of a pixel in a swizzled texture. Bits of each coordinate are interleaved for as
+// long as that dimension has bits remaining.
+fn swizzled_offset(x : usize, y : usize, z : usize, width : usize, height : usize, depth : usize) -> usize {
+    let mut offset = 0;
+    let mut shift = 0;
+    let mut bit = 1;
+    while bit < width || bit < height || bit < depth {
+        let index = bit.trailing_zeros();
+        if bit < width {
+            offset |= ((x >> index) & 1) << shift;
+            shift += 1;
+        }
+        if bit < height {
+            offset |= ((y >> index) & 1) << shift;
+            shift += 1;
+        }
+        if bit < depth {
+            offset |= ((z >> index) & 1) << shift;
+            shift += 1;
+        }
+        bit <<= 1;
+    }
+    offset
+}
+
+/// Swizzle (Morton order) or unswizzle an uncompressed image or volume.
+///
+/// Xbox maps store uncompressed textures swizzled, while PC maps store them linearly. Each
+/// dimension must be a power of two.
+///
+/// This function will panic if `data` is smaller than `width * height * depth * bytes_per_pixel`.
+pub fn swizzle_pixels(width : usize, height : usize, depth : usize, bytes_per_pixel : usize, data : &[u8], unswizzle : bool) -> Vec<u8> {
+    let size = width * height * depth * bytes_per_pixel;
+    let mut output = vec![0u8 ; size];
+    for z in 0..depth {
+        for y in 0..height {
+            for x in 0..width {
+                let linear = ((z * height + y) * width + x) * bytes_per_pixel;
+                let swizzled = swizzled_offset(x, y, z, width, height, depth) * bytes_per_pixel;
+                let (from, to) = if unswizzle { (swizzled, linear) } else { (linear, swizzled) };
+                output[to .. to + bytes_per_pixel].copy_from_slice(&data[from .. from + bytes_per_pixel]);
+            }
+        }
+    }
+    output
+}
+
+/// Swizzle or unswizzle all of the pixel data of a bitmap, including every face, slice, and
+/// mipmap. Each face of a cube map is swizzled separately, while each mipmap of a 3D texture is
+/// swizzled as a volume.
+///
+/// Returns `Err` if the bitmap is compressed, does not have power-of-two dimensions, or if the
+/// data is too small.
+pub fn swizzle_bitmap_pixel_data(bitmap : &BitmapData, data : &[u8], unswizzle : bool) -> Result<Vec<u8>,&'static str> {
+    if bitmap.format.is_compressed() {
+        return Err("compressed bitmaps cannot be swizzled");
+    }
+    let bytes_per_pixel = match bitmap.format.block_size() {
+        Some(n) => n,
+        None => return Err("unknown bitmap format")
+    };
+    if !(bitmap.width as usize).is_power_of_two() || !(bitmap.height as usize).is_power_of_two() || (bitmap.bitmap_type == BitmapType::Texture3D && !(bitmap.depth as usize).is_power_of_two()) {
+        return Err("only bitmaps with power-of-two dimensions can be swizzled");
+    }
+    match bitmap.calculate_pixel_data_size() {
+        Some(n) if n <= data.len() => (),
+        _ => return Err("bitmap pixel data is too small")
+    }
+
+    let mut output = Vec::with_capacity(data.len());
+    let mut offset = 0;
+    for m in 0..bitmap.mipmap_count as usize + 1 {
+        let (width,height) = bitmap.mipmap_dimensions(m);
+        let layers = bitmap.layers(m);
+        let (images, depth) = if bitmap.bitmap_type == BitmapType::Texture3D { (1, layers) } else { (layers, 1) };
+        for _ in 0..images {
+            let size = width * height * depth * bytes_per_pixel;
+            output.append(&mut swizzle_pixels(width, height, depth, bytes_per_pixel, &data[offset .. offset + size], unswizzle));
+            offset += size;
+        }
+    }
+
+    // Anything after the pixel data is kept as-is.
+    output.extend_from_slice(&data[offset..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A DXT1 block with red and blue endpoints where each row uses the four palette entries in
+    // order.
+    const DXT1_FIXTURE : [u8 ; 8] = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0xE4, 0xE4, 0xE4];
+
+    #[test]
+    fn decode_dxt1_fixture() {
+        let pixels = decode_pixels(BitmapFormat::DXT1, 4, 4, &DXT1_FIXTURE, None).unwrap();
+        let row = [255, 0, 0, 255, 0, 0, 255, 255, 170, 0, 85, 255, 85, 0, 170, 255];
+        for y in 0..4 {
+            assert_eq!(&pixels[y * 16 .. (y + 1) * 16], &row);
+        }
+    }
+
+    #[test]
+    fn decode_dxt3_fixture() {
+        // Explicit alpha increases by one step per pixel, over a solid white DXT1 color block.
+        let block = [0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00];
+        let pixels = decode_pixels(BitmapFormat::DXT3, 4, 4, &block, None).unwrap();
+        for i in 0..16 {
+            assert_eq!(&pixels[i * 4 .. i * 4 + 4], &[255, 255, 255, i as u8 * 17]);
+        }
+    }
+
+    #[test]
+    fn encode_then_decode_uncompressed() {
+        let pixels : Vec<u8> = (0..6 * 5 * 4).map(|n| (n * 53 % 256) as u8).collect();
+        let encoded = encode_pixels(BitmapFormat::A8R8G8B8, 6, 5, &pixels).unwrap();
+        assert_eq!(decode_pixels(BitmapFormat::A8R8G8B8, 6, 5, &encoded, None).unwrap(), pixels);
+    }
+
+    #[test]
+    fn encode_then_decode_dxt() {
+        // Two colors that R5G6B5 can represent exactly, in a size that isn't a multiple of four.
+        let pixels : Vec<u8> = (0..6 * 5).flat_map(|n| if n % 3 == 0 { vec![255, 0, 0, 255] } else { vec![0, 0, 255, 255] }).collect();
+        for format in &[BitmapFormat::DXT1, BitmapFormat::DXT3, BitmapFormat::DXT5] {
+            let encoded = encode_pixels(*format, 6, 5, &pixels).unwrap();
+            assert_eq!(decode_pixels(*format, 6, 5, &encoded, None).unwrap(), pixels);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, TagArray, CollisionBSPIssue, COLLISION_BSP_SIZE, read_dependency, SBSP};
+
+/// Offset of the collision materials reflexive in an sbsp tag.
+pub const BSP_COLLISION_MATERIALS_OFFSET : usize = 0xBC;
+
+/// Offset of the collision BSP reflexive in an sbsp tag.
+pub const BSP_COLLISION_BSP_OFFSET : usize = 0xC8;
+
+/// Offset of the surfaces reflexive in an sbsp tag.
+pub const BSP_SURFACES_OFFSET : usize = 0x110;
+
+/// Offset of the lightmaps reflexive in an sbsp tag.
+pub const BSP_LIGHTMAPS_OFFSET : usize = 0x11C;
+
+/// Size of a lightmap in an sbsp tag.
+pub const BSP_LIGHTMAP_SIZE : usize = 0x20;
+
+/// Size of a lightmap material in an sbsp tag.
+pub const BSP_MATERIAL_SIZE : usize = 0x100;
+
+/// Size of an uncompressed rendered vertex in an sbsp tag.
+pub const BSP_VERTEX_SIZE : usize = 0x38;
+
+#[derive(Clone,Copy,PartialEq)]
+/// An uncompressed rendered BSP vertex.
+pub struct BSPVertex {
+    pub position : [f32 ; 3],
+    pub normal : [f32 ; 3],
+    pub binormal : [f32 ; 3],
+    pub tangent : [f32 ; 3],
+    pub texture_coords : [f32 ; 2]
+}
+impl BSPVertex {
+    /// Read a rendered BSP vertex from a slice.
+    ///
+    /// This function will panic if `vertex` is smaller than `BSP_VERTEX_SIZE`.
+    pub fn from_slice(vertex : &[u8]) -> BSPVertex {
+        let mut floats = [0f32 ; 14];
+        LittleEndian::read_f32_into(&vertex[0..BSP_VERTEX_SIZE], &mut floats);
+        BSPVertex {
+            position : [floats[0], floats[1], floats[2]],
+            normal : [floats[3], floats[4], floats[5]],
+            binormal : [floats[6], floats[7], floats[8]],
+            tangent : [floats[9], floats[10], floats[11]],
+            texture_coords : [floats[12], floats[13]]
+        }
+    }
+}
+
+#[derive(Clone)]
+/// A lightmap material of an sbsp tag with its rendered vertices and triangles.
+pub struct BSPMaterial {
+    /// This is the offset of the material in the tag data.
+    pub offset : usize,
+
+    /// This is the index of the lightmap the material belongs to.
+    pub lightmap : usize,
+
+    /// This is the tag index of the shader, if any.
+    pub shader : Option<usize>,
+    pub vertices : Vec<BSPVertex>,
+
+    /// These are triangles made of indices to the material's vertices.
+    pub triangles : Vec<[u16 ; 3]>
+}
+
+impl Tag {
+    /// Get the lightmap materials of this sbsp tag, including their rendered vertices and
+    /// triangles.
+    pub fn bsp_materials(&self) -> Result<Vec<BSPMaterial>,&'static str> {
+        let data = self.p_bsp_data()?;
+        let surfaces : Vec<[u16 ; 3]> = self.reflexive_elements(BSP_SURFACES_OFFSET, 6)?.into_iter().map(|o| {
+            [LittleEndian::read_u16(&data[o..]), LittleEndian::read_u16(&data[o + 2..]), LittleEndian::read_u16(&data[o + 4..])]
+        }).collect();
+
+        let mut materials = Vec::new();
+        for (l, lightmap_offset) in self.reflexive_elements(BSP_LIGHTMAPS_OFFSET, BSP_LIGHTMAP_SIZE)?.into_iter().enumerate() {
+            for offset in self.reflexive_elements(lightmap_offset + 0x14, BSP_MATERIAL_SIZE)? {
+                let material = &data[offset .. offset + BSP_MATERIAL_SIZE];
+                let first_surface = LittleEndian::read_i32(&material[0x14..]);
+                let surface_count = LittleEndian::read_i32(&material[0x18..]);
+                if first_surface < 0 || surface_count < 0 || (first_surface + surface_count) as usize > surfaces.len() {
+                    return Err("invalid surfaces on lightmap material");
+                }
+
+                let vertex_count = LittleEndian::read_u32(&material[0xB4..]) as usize;
+                let vertices : Vec<BSPVertex> = if vertex_count == 0 {
+                    Vec::new()
+                }
+                else {
+                    match self.data_block_offset(offset + 0xD8) {
+                        Some((o, size)) if vertex_count * BSP_VERTEX_SIZE <= size => data[o .. o + vertex_count * BSP_VERTEX_SIZE].chunks(BSP_VERTEX_SIZE).map(BSPVertex::from_slice).collect(),
+                        _ => return Err("invalid vertices on lightmap material")
+                    }
+                };
+
+                let triangles = surfaces[first_surface as usize .. (first_surface + surface_count) as usize].to_owned();
+                if triangles.iter().any(|t| t.iter().any(|i| *i as usize >= vertices.len())) {
+                    return Err("lightmap material surface uses an invalid vertex");
+                }
+                materials.push(BSPMaterial {
+                    offset : offset,
+                    lightmap : l,
+                    shader : read_dependency(material),
+                    vertices : vertices,
+                    triangles : triangles
+                });
+            }
+        }
+        Ok(materials)
+    }
+
+    /// Export the rendered geometry of this sbsp tag as a Wavefront OBJ file with one group for
+    /// each material, named after its shader.
+    pub fn export_bsp_obj(&self, tag_array : &TagArray) -> Result<String,&'static str> {
+        let materials = self.bsp_materials()?;
+        let mut obj = String::new();
+        obj += &format!("# {}\no {}\n", self.tag_path, tag_name(&self.tag_path));
+
+        let mut vertex_base = 1;
+        for (m, material) in materials.iter().enumerate() {
+            for vertex in &material.vertices {
+                obj += &format!("v {:?} {:?} {:?}\n", vertex.position[0], vertex.position[1], vertex.position[2]);
+            }
+            for vertex in &material.vertices {
+                obj += &format!("vt {:?} {:?}\n", vertex.texture_coords[0], 1.0 - vertex.texture_coords[1]);
+            }
+            for vertex in &material.vertices {
+                obj += &format!("vn {:?} {:?} {:?}\n", vertex.normal[0], vertex.normal[1], vertex.normal[2]);
+            }
+            let name = match material.shader.and_then(|s| tag_array.tags().get(s)) {
+                Some(n) => tag_name(&n.tag_path).to_owned(),
+                None => format!("material_{}", m)
+            };
+            obj += &format!("g {0}\nusemtl {0}\n", name);
+            for triangle in &material.triangles {
+                let (a, b, c) = (triangle[0] as usize + vertex_base, triangle[1] as usize + vertex_base, triangle[2] as usize + vertex_base);
+                obj += &format!("f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}\n", a, b, c);
+            }
+            vertex_base += material.vertices.len();
+        }
+        Ok(obj)
+    }
+
+    /// Analyze the collision BSPs of this sbsp tag for out-of-range indices, degenerate planes,
+    /// open edges, and known causes of phantom BSP.
+    ///
+    /// Issues are paired with the index of the collision BSP they were found in.
+    pub fn analyze_bsp_collision(&self) -> Result<Vec<(usize,CollisionBSPIssue)>,&'static str> {
+        self.p_bsp_data()?;
+        let mut issues = Vec::new();
+        for (b, offset) in self.reflexive_elements(BSP_COLLISION_BSP_OFFSET, COLLISION_BSP_SIZE)?.into_iter().enumerate() {
+            issues.extend(self.collision_bsp(offset)?.analyze().into_iter().map(|i| (b, i)));
+        }
+        Ok(issues)
+    }
+
+    /// Export the collision BSP of this sbsp tag as a Wavefront OBJ file with one group for each
+    /// collision material, named after its shader.
+    pub fn export_bsp_collision_obj(&self, tag_array : &TagArray) -> Result<String,&'static str> {
+        let data = self.p_bsp_data()?;
+        let bsp_offset = match self.reflexive_elements(BSP_COLLISION_BSP_OFFSET, COLLISION_BSP_SIZE)?.first() {
+            Some(n) => *n,
+            None => return Err("sbsp tag has no collision BSP")
+        };
+        let material_names : Vec<String> = self.reflexive_elements(BSP_COLLISION_MATERIALS_OFFSET, 0x14)?.into_iter().enumerate().map(|(m, o)| {
+            match read_dependency(&data[o..]).and_then(|t| tag_array.tags().get(t)) {
+                Some(n) => tag_name(&n.tag_path).to_owned(),
+                None => format!("material_{}", m)
+            }
+        }).collect();
+        let obj = self.collision_bsp(bsp_offset)?.as_obj(tag_name(&self.tag_path), &material_names)?;
+        Ok(format!("# {}\n{}", self.tag_path, obj))
+    }
+
+    // Get the tag data of an sbsp tag, checking that the header is there.
+    fn p_bsp_data(&self) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != SBSP {
+            return Err("tag is not a structure BSP tag");
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= BSP_LIGHTMAPS_OFFSET + 0xC => Ok(n),
+            Some(_) => Err("sbsp tag is too small"),
+            None => Err("sbsp tag has no data")
+        }
+    }
+}
+
+// Get the last component of a tag path.
+fn tag_name(tag_path : &str) -> &str {
+    tag_path.rsplit('\\').next().unwrap_or(tag_path)
+}
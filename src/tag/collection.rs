@@ -0,0 +1,131 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, TagArray, read_dependency, write_dependency, TAGC, ITMC, ITEM};
+
+/// Size of a tag reference in a tagc tag.
+pub const TAG_COLLECTION_REFERENCE_SIZE : usize = 0x10;
+
+/// Size of a permutation in an itmc tag.
+pub const ITEM_COLLECTION_PERMUTATION_SIZE : usize = 0x54;
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+/// A permutation of an itmc tag.
+pub struct ItemCollectionPermutation {
+    /// This is how likely the item is to spawn relative to the other permutations.
+    pub weight : f32,
+
+    /// This is the tag index of the item, if any.
+    pub item : Option<usize>
+}
+
+impl Tag {
+    /// Get the tag indices referenced by this tagc tag.
+    pub fn tag_collection(&self) -> Result<Vec<Option<usize>>,&'static str> {
+        let data = self.p_collection_data(TAGC)?;
+        Ok(self.reflexive_elements(0x0, TAG_COLLECTION_REFERENCE_SIZE)?.into_iter().map(|o| read_dependency(&data[o..])).collect())
+    }
+
+    /// Get the permutations of this itmc tag.
+    pub fn item_collection(&self) -> Result<Vec<ItemCollectionPermutation>,&'static str> {
+        let data = self.p_collection_data(ITMC)?;
+        Ok(self.reflexive_elements(0x0, ITEM_COLLECTION_PERMUTATION_SIZE)?.into_iter().map(|o| ItemCollectionPermutation {
+            weight : LittleEndian::read_f32(&data[o + 0x20..]),
+            item : read_dependency(&data[o + 0x24..])
+        }).collect())
+    }
+
+    // Get the tag data of a tagc or itmc tag, checking that the header is there.
+    fn p_collection_data(&self, tag_class : u32) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != tag_class {
+            return Err(if tag_class == TAGC { "tag is not a tag collection tag" } else { "tag is not an item collection tag" });
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= 0xC => Ok(n),
+            Some(_) => Err("collection tag is too small"),
+            None => Err("collection tag has no data")
+        }
+    }
+
+    // Replace the elements of the reflexive at the start of a collection tag by editing its data.
+    fn p_edit_collection<F>(&mut self, element_size : usize, edit : F) where F : FnOnce(&mut Vec<u8>) {
+        let mut elements : Vec<u8> = {
+            let data = self.data.as_ref().unwrap();
+            self.reflexive_elements(0x0, element_size).unwrap().into_iter().flat_map(|o| data[o .. o + element_size].to_owned()).collect()
+        };
+        edit(&mut elements);
+        self.replace_reflexive(0x0, element_size, &elements);
+    }
+}
+
+impl TagArray {
+    /// Add a reference to a tag to a tagc tag. Returns the index of the new reference.
+    ///
+    /// The referenced tag must already be in this tag array, such as by using
+    /// `find_or_insert_recursive`.
+    pub fn add_tag_collection_reference(&mut self, collection : usize, tag_index : usize) -> Result<usize,&'static str> {
+        let count = self.p_collection_tag(collection, TAGC)?.tag_collection()?.len();
+        let tag_class = match self.tags().get(tag_index) {
+            Some(n) => n.tag_class.0,
+            None => return Err("referenced tag does not exist")
+        };
+        let mut reference = [0u8 ; TAG_COLLECTION_REFERENCE_SIZE];
+        write_dependency(&mut reference, self.p_dependency_class(Some(tag_index), tag_class)?, Some(tag_index));
+        self.tags_mut()[collection].p_edit_collection(TAG_COLLECTION_REFERENCE_SIZE, |e| e.extend_from_slice(&reference));
+        Ok(count)
+    }
+
+    /// Remove a reference from a tagc tag. The referenced tag is not removed.
+    pub fn remove_tag_collection_reference(&mut self, collection : usize, reference : usize) -> Result<(),&'static str> {
+        if reference >= self.p_collection_tag(collection, TAGC)?.tag_collection()?.len() {
+            return Err("tag collection reference index out of bounds");
+        }
+        let offset = reference * TAG_COLLECTION_REFERENCE_SIZE;
+        self.tags_mut()[collection].p_edit_collection(TAG_COLLECTION_REFERENCE_SIZE, |e| { e.drain(offset .. offset + TAG_COLLECTION_REFERENCE_SIZE); });
+        Ok(())
+    }
+
+    /// Add a permutation to an itmc tag. Returns the index of the new permutation.
+    ///
+    /// The item must already be in this tag array, such as by using `find_or_insert_recursive`.
+    pub fn add_item_collection_permutation(&mut self, collection : usize, permutation : &ItemCollectionPermutation) -> Result<usize,&'static str> {
+        let count = self.p_collection_tag(collection, ITMC)?.item_collection()?.len();
+        let mut element = [0u8 ; ITEM_COLLECTION_PERMUTATION_SIZE];
+        LittleEndian::write_f32(&mut element[0x20..], permutation.weight);
+        write_dependency(&mut element[0x24..], self.p_dependency_class(permutation.item, ITEM)?, permutation.item);
+        self.tags_mut()[collection].p_edit_collection(ITEM_COLLECTION_PERMUTATION_SIZE, |e| e.extend_from_slice(&element));
+        Ok(count)
+    }
+
+    /// Replace the weight and item of a permutation of an itmc tag.
+    pub fn set_item_collection_permutation(&mut self, collection : usize, index : usize, permutation : &ItemCollectionPermutation) -> Result<(),&'static str> {
+        let offset = match self.p_collection_tag(collection, ITMC)?.reflexive_elements(0x0, ITEM_COLLECTION_PERMUTATION_SIZE)?.get(index) {
+            Some(n) => *n,
+            None => return Err("item collection permutation index out of bounds")
+        };
+        let mut dependency = [0u8 ; 0x10];
+        write_dependency(&mut dependency, self.p_dependency_class(permutation.item, ITEM)?, permutation.item);
+        let data = self.tags_mut()[collection].data.as_mut().unwrap();
+        LittleEndian::write_f32(&mut data[offset + 0x20..], permutation.weight);
+        data[offset + 0x24 .. offset + 0x34].copy_from_slice(&dependency);
+        Ok(())
+    }
+
+    /// Remove a permutation from an itmc tag. The item is not removed.
+    pub fn remove_item_collection_permutation(&mut self, collection : usize, index : usize) -> Result<(),&'static str> {
+        if index >= self.p_collection_tag(collection, ITMC)?.item_collection()?.len() {
+            return Err("item collection permutation index out of bounds");
+        }
+        let offset = index * ITEM_COLLECTION_PERMUTATION_SIZE;
+        self.tags_mut()[collection].p_edit_collection(ITEM_COLLECTION_PERMUTATION_SIZE, |e| { e.drain(offset .. offset + ITEM_COLLECTION_PERMUTATION_SIZE); });
+        Ok(())
+    }
+
+    // Get a tagc or itmc tag, checking that it exists and has data.
+    fn p_collection_tag(&self, collection : usize, tag_class : u32) -> Result<&Tag,&'static str> {
+        match self.tags().get(collection) {
+            Some(n) => n.p_collection_data(tag_class).map(|_| n),
+            None => Err("collection tag does not exist")
+        }
+    }
+}
@@ -0,0 +1,643 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, COLL};
+use super::gltf::{transform_matrix, transform_point};
+use super::super::string_from_slice;
+
+/// Size of a collision BSP.
+pub const COLLISION_BSP_SIZE : usize = 0x60;
+
+/// Size of a material in a coll tag.
+pub const COLLISION_MODEL_MATERIAL_SIZE : usize = 0x90;
+
+/// Size of a region in a coll tag.
+pub const COLLISION_MODEL_REGION_SIZE : usize = 0x54;
+
+/// Size of a region permutation in a coll tag.
+pub const COLLISION_MODEL_PERMUTATION_SIZE : usize = 0x20;
+
+/// Size of a pathfinding sphere in a coll tag.
+pub const COLLISION_MODEL_PATHFINDING_SPHERE_SIZE : usize = 0x20;
+
+/// Size of a node in a coll tag.
+pub const COLLISION_MODEL_NODE_SIZE : usize = 0x40;
+
+/// Maximum distance a point can be from a plane and still be considered on the plane.
+pub const COLLISION_PLANE_TOLERANCE : f32 = 0.001;
+
+/// Minimum area of a collision surface.
+pub const COLLISION_MINIMUM_SURFACE_AREA : f32 = 0.00001;
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+/// Kinds of problems found by analyzing a collision BSP.
+pub enum CollisionBSPIssueType {
+    /// An index refers to an element that does not exist.
+    InvalidIndex,
+
+    /// A plane's normal is not a unit vector.
+    DegeneratePlane,
+
+    /// An edge is missing a surface on one of its sides, leaving a hole in the collision.
+    OpenEdge,
+
+    /// A surface's edges do not form a closed loop.
+    BrokenSurface,
+
+    /// A surface has fewer than three vertices or almost no area.
+    DegenerateSurface,
+
+    /// A surface's vertices do not lie on its plane.
+    NonPlanarSurface,
+
+    /// A 2D BSP reachable from a leaf leads to a surface that is not on the reference's plane.
+    /// Collision is then tested against the wrong plane, which is a known cause of phantom BSP.
+    PhantomBSP
+}
+
+#[derive(Clone)]
+/// A problem found by analyzing a collision BSP.
+pub struct CollisionBSPIssue {
+    pub issue_type : CollisionBSPIssueType,
+
+    /// This is the index of the offending element. What it indexes depends on the type of issue.
+    pub index : usize,
+
+    /// This is where the problem is in world coordinates, if it can be located.
+    pub position : Option<[f32 ; 3]>,
+    pub description : String
+}
+
+#[derive(Clone)]
+/// A surface of a collision BSP. Surfaces are convex polygons made of edges.
+pub struct CollisionSurface {
+    pub plane : i32,
+    pub first_edge : i32,
+    pub flags : u8,
+    pub breakable_surface : i8,
+    pub material : i16
+}
+
+#[derive(Clone)]
+/// An edge of a collision BSP. Each edge is shared by the surfaces on its left and right.
+pub struct CollisionEdge {
+    pub start_vertex : i32,
+    pub end_vertex : i32,
+    pub forward_edge : i32,
+    pub reverse_edge : i32,
+    pub left_surface : i32,
+    pub right_surface : i32
+}
+
+#[derive(Clone)]
+/// A node of a collision BSP's 3D BSP tree. Negative children are leaves (with the sign bit
+/// cleared) or nothing (-1).
+pub struct CollisionBSP3DNode {
+    pub plane : i32,
+    pub back_child : i32,
+    pub front_child : i32
+}
+
+#[derive(Clone)]
+/// A leaf of a collision BSP's 3D BSP tree.
+pub struct CollisionLeaf {
+    pub flags : u16,
+    pub bsp2d_reference_count : u16,
+    pub first_bsp2d_reference : i32
+}
+
+#[derive(Clone)]
+/// A reference from a leaf to a 2D BSP tree.
+pub struct CollisionBSP2DReference {
+    pub plane : i32,
+    pub bsp2d_node : i32
+}
+
+#[derive(Clone)]
+/// A node of a 2D BSP tree. Negative children are surfaces (with the sign bit cleared).
+pub struct CollisionBSP2DNode {
+    pub plane : [f32 ; 3],
+    pub left_child : i32,
+    pub right_child : i32
+}
+
+#[derive(Clone)]
+/// A collision BSP, as used by sbsp and coll tags.
+pub struct CollisionBSP {
+    /// This is the offset of the collision BSP in the tag data.
+    pub offset : usize,
+    pub bsp3d_nodes : Vec<CollisionBSP3DNode>,
+
+    /// Planes are stored as (i, j, k, d).
+    pub planes : Vec<[f32 ; 4]>,
+    pub leaves : Vec<CollisionLeaf>,
+    pub bsp2d_references : Vec<CollisionBSP2DReference>,
+    pub bsp2d_nodes : Vec<CollisionBSP2DNode>,
+    pub surfaces : Vec<CollisionSurface>,
+    pub edges : Vec<CollisionEdge>,
+    pub vertices : Vec<[f32 ; 3]>
+}
+#[derive(Clone)]
+/// A region of a coll tag.
+pub struct CollisionModelRegion {
+    pub name : String,
+
+    /// These are the permutation names. Each node in the region has a collision BSP for each
+    /// permutation.
+    pub permutations : Vec<String>
+}
+
+#[derive(Clone)]
+/// A node of a coll tag.
+pub struct CollisionModelNode {
+    pub name : String,
+    pub region : Option<usize>,
+    pub parent : Option<usize>,
+    pub next_sibling : Option<usize>,
+    pub first_child : Option<usize>,
+
+    /// These are the node's collision BSPs, one for each permutation of its region, in node
+    /// space.
+    pub bsps : Vec<CollisionBSP>
+}
+
+#[derive(Clone,Copy)]
+/// A sphere used by AI for pathfinding around an object.
+pub struct CollisionPathfindingSphere {
+    pub node : Option<usize>,
+
+    /// This is the center in node space.
+    pub center : [f32 ; 3],
+    pub radius : f32
+}
+
+impl CollisionBSP {
+    /// Get a plane by index. If the sign bit of the index is set, the plane is flipped.
+    pub fn plane(&self, index : i32) -> Option<[f32 ; 4]> {
+        let plane = self.planes.get((index & 0x7FFFFFFF) as usize)?;
+        if index < 0 {
+            Some([-plane[0], -plane[1], -plane[2], -plane[3]])
+        }
+        else {
+            Some(*plane)
+        }
+    }
+
+    /// Analyze the collision BSP for out-of-range indices, degenerate planes, open edges, and
+    /// known causes of phantom collision.
+    pub fn analyze(&self) -> Vec<CollisionBSPIssue> {
+        let mut issues = Vec::new();
+        {
+            let mut issue = |issue_type, index, position, description : String| issues.push(CollisionBSPIssue {
+                issue_type : issue_type,
+                index : index,
+                position : position,
+                description : description
+            });
+
+            for (p, plane) in self.planes.iter().enumerate() {
+                let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+                if !plane.iter().all(|f| f.is_finite()) || (length - 1.0).abs() > 0.01 {
+                    let position = if plane.iter().all(|f| f.is_finite()) { Some([plane[0] * plane[3], plane[1] * plane[3], plane[2] * plane[3]]) } else { None };
+                    issue(CollisionBSPIssueType::DegeneratePlane, p, position, format!("plane #{} has a normal of length {}", p, length));
+                }
+            }
+
+            for (n, node) in self.bsp3d_nodes.iter().enumerate() {
+                let position = self.plane(node.plane).map(|p| [p[0] * p[3], p[1] * p[3], p[2] * p[3]]);
+                if position.is_none() {
+                    issue(CollisionBSPIssueType::InvalidIndex, n, None, format!("3D BSP node #{} references plane #{}", n, node.plane & 0x7FFFFFFF));
+                }
+                for child in &[node.back_child, node.front_child] {
+                    let valid = match *child {
+                        -1 => true,
+                        c if c < 0 => ((c & 0x7FFFFFFF) as usize) < self.leaves.len(),
+                        c => (c as usize) < self.bsp3d_nodes.len()
+                    };
+                    if !valid {
+                        issue(CollisionBSPIssueType::InvalidIndex, n, position, format!("3D BSP node #{} has invalid child 0x{:08X}", n, *child as u32));
+                    }
+                }
+            }
+
+            for (l, leaf) in self.leaves.iter().enumerate() {
+                let first = leaf.first_bsp2d_reference;
+                if leaf.bsp2d_reference_count > 0 && (first < 0 || first as usize + leaf.bsp2d_reference_count as usize > self.bsp2d_references.len()) {
+                    issue(CollisionBSPIssueType::InvalidIndex, l, None, format!("leaf #{} references 2D BSP references #{}-#{}", l, first, first as i64 + leaf.bsp2d_reference_count as i64 - 1));
+                }
+            }
+
+            for (r, reference) in self.bsp2d_references.iter().enumerate() {
+                let plane = match self.plane(reference.plane) {
+                    Some(n) => n,
+                    None => {
+                        issue(CollisionBSPIssueType::InvalidIndex, r, None, format!("2D BSP reference #{} references plane #{}", r, reference.plane & 0x7FFFFFFF));
+                        continue;
+                    }
+                };
+
+                // Find every surface reachable from the reference's 2D BSP.
+                let mut stack = vec![reference.bsp2d_node];
+                let mut visited = 0;
+                while let Some(child) = stack.pop() {
+                    visited += 1;
+                    if visited > self.bsp2d_nodes.len() + self.surfaces.len() + 1 {
+                        issue(CollisionBSPIssueType::InvalidIndex, r, None, format!("2D BSP of reference #{} contains a cycle", r));
+                        break;
+                    }
+                    if child == -1 {
+                        continue;
+                    }
+                    else if child >= 0 {
+                        match self.bsp2d_nodes.get(child as usize) {
+                            Some(n) => {
+                                stack.push(n.left_child);
+                                stack.push(n.right_child);
+                            },
+                            None => issue(CollisionBSPIssueType::InvalidIndex, r, None, format!("2D BSP of reference #{} references 2D BSP node #{}", r, child))
+                        }
+                        continue;
+                    }
+
+                    let surface = (child & 0x7FFFFFFF) as usize;
+                    if surface >= self.surfaces.len() {
+                        issue(CollisionBSPIssueType::InvalidIndex, r, None, format!("2D BSP of reference #{} references surface #{}", r, surface));
+                        continue;
+                    }
+                    let vertices = match self.surface_vertices(surface) {
+                        Ok(n) => n,
+                        Err(_) => continue
+                    };
+                    if let Some(distance) = vertices.iter().map(|v| plane_distance(&plane, &self.vertices[*v]).abs()).fold(None, |a : Option<f32>, d| Some(a.map_or(d, |a| a.max(d)))) {
+                        if distance > COLLISION_PLANE_TOLERANCE {
+                            issue(CollisionBSPIssueType::PhantomBSP, surface, Some(self.centroid(&vertices)), format!("surface #{} is reachable from 2D BSP reference #{} but is {} units off its plane", surface, r, distance));
+                        }
+                    }
+                }
+            }
+
+            for (s, surface) in self.surfaces.iter().enumerate() {
+                let plane = self.plane(surface.plane);
+                if plane.is_none() {
+                    issue(CollisionBSPIssueType::InvalidIndex, s, None, format!("surface #{} references plane #{}", s, surface.plane & 0x7FFFFFFF));
+                }
+                let vertices = match self.surface_vertices(s) {
+                    Ok(n) => n,
+                    Err(e) => {
+                        issue(CollisionBSPIssueType::BrokenSurface, s, None, format!("surface #{}: {}", s, e));
+                        continue;
+                    }
+                };
+                let centroid = self.centroid(&vertices);
+                let area = self.area(&vertices);
+                if vertices.len() < 3 || !(area >= COLLISION_MINIMUM_SURFACE_AREA) {
+                    issue(CollisionBSPIssueType::DegenerateSurface, s, Some(centroid), format!("surface #{} has {} vertices and an area of {}", s, vertices.len(), area));
+                }
+                if let Some(plane) = plane {
+                    for v in &vertices {
+                        let distance = plane_distance(&plane, &self.vertices[*v]);
+                        if !(distance.abs() <= COLLISION_PLANE_TOLERANCE) {
+                            issue(CollisionBSPIssueType::NonPlanarSurface, s, Some(self.vertices[*v]), format!("vertex #{} of surface #{} is {} units off its plane", v, s, distance));
+                        }
+                    }
+                }
+            }
+
+            for (e, edge) in self.edges.iter().enumerate() {
+                let start = self.vertices.get(edge.start_vertex as usize).filter(|_| edge.start_vertex >= 0);
+                let end = self.vertices.get(edge.end_vertex as usize).filter(|_| edge.end_vertex >= 0);
+                let position = match (start, end) {
+                    (Some(a), Some(b)) => Some([(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0, (a[2] + b[2]) / 2.0]),
+                    _ => {
+                        issue(CollisionBSPIssueType::InvalidIndex, e, None, format!("edge #{} references vertices #{} and #{}", e, edge.start_vertex, edge.end_vertex));
+                        None
+                    }
+                };
+                for &(name, index, count, is_surface) in &[("forward edge", edge.forward_edge, self.edges.len(), false), ("reverse edge", edge.reverse_edge, self.edges.len(), false), ("left surface", edge.left_surface, self.surfaces.len(), true), ("right surface", edge.right_surface, self.surfaces.len(), true)] {
+                    if index == -1 && is_surface {
+                        issue(CollisionBSPIssueType::OpenEdge, e, position, format!("edge #{} has no {}", e, name));
+                    }
+                    else if index < 0 || index as usize >= count {
+                        issue(CollisionBSPIssueType::InvalidIndex, e, position, format!("edge #{} references {} #{}", e, name, index));
+                    }
+                }
+            }
+        }
+        issues
+    }
+
+    // Get the average position of a set of vertices.
+    fn centroid(&self, vertices : &[usize]) -> [f32 ; 3] {
+        let mut centroid = [0.0 ; 3];
+        for v in vertices {
+            for c in 0..3 {
+                centroid[c] += self.vertices[*v][c] / vertices.len() as f32;
+            }
+        }
+        centroid
+    }
+
+    // Get the area of a polygon.
+    fn area(&self, vertices : &[usize]) -> f32 {
+        let mut sum = [0.0f32 ; 3];
+        for i in 0..vertices.len() {
+            let a = self.vertices[vertices[i]];
+            let b = self.vertices[vertices[(i + 1) % vertices.len()]];
+            sum[0] += a[1] * b[2] - a[2] * b[1];
+            sum[1] += a[2] * b[0] - a[0] * b[2];
+            sum[2] += a[0] * b[1] - a[1] * b[0];
+        }
+        (sum[0] * sum[0] + sum[1] * sum[1] + sum[2] * sum[2]).sqrt() / 2.0
+    }
+
+    /// Get the vertex indices of a surface by walking its edges.
+    ///
+    /// Returns `Err` if the edges do not form a closed loop around the surface.
+    pub fn surface_vertices(&self, surface : usize) -> Result<Vec<usize>,&'static str> {
+        let first_edge = match self.surfaces.get(surface) {
+            Some(n) => n.first_edge,
+            None => return Err("surface index out of bounds")
+        };
+        let mut vertices = Vec::new();
+        let mut edge_index = first_edge;
+        loop {
+            let edge = match self.edges.get(edge_index as usize) {
+                Some(n) if edge_index >= 0 => n,
+                _ => return Err("invalid edge index on collision surface")
+            };
+            let (vertex, next) = if edge.left_surface as usize == surface {
+                (edge.start_vertex, edge.forward_edge)
+            }
+            else if edge.right_surface as usize == surface {
+                (edge.end_vertex, edge.reverse_edge)
+            }
+            else {
+                return Err("collision edge does not belong to the surface");
+            };
+            if vertex < 0 || vertex as usize >= self.vertices.len() {
+                return Err("invalid vertex index on collision edge");
+            }
+            vertices.push(vertex as usize);
+            if vertices.len() > self.edges.len() {
+                return Err("collision surface edges do not form a loop");
+            }
+            edge_index = next;
+            if edge_index == first_edge {
+                break;
+            }
+        }
+        Ok(vertices)
+    }
+
+    /// Export the collision BSP as a Wavefront OBJ file with one group for each material.
+    /// Surfaces are triangulated as fans.
+    ///
+    /// `material_names` are used as group names. Materials without a name are named by index.
+    pub fn as_obj(&self, name : &str, material_names : &[String]) -> Result<String,&'static str> {
+        let mut obj = String::new();
+        obj += &format!("o {}\n", name);
+        self.p_write_obj(&mut obj, None, material_names, 1, &transform_matrix(&[0.0 ; 3], &[0.0, 0.0, 0.0, 1.0]))?;
+        Ok(obj)
+    }
+
+    // Write the vertices and faces of the collision BSP to an OBJ file, transforming the vertices
+    // by a matrix. `vertex_base` is the OBJ index of the first vertex. If `group` is set, every
+    // material is put in that group instead of one group for each material.
+    fn p_write_obj(&self, obj : &mut String, group : Option<&str>, material_names : &[String], vertex_base : usize, matrix : &[f32 ; 16]) -> Result<(),&'static str> {
+        for vertex in &self.vertices {
+            let vertex = transform_point(matrix, vertex);
+            *obj += &format!("v {:?} {:?} {:?}\n", vertex[0], vertex[1], vertex[2]);
+        }
+        if let Some(group) = group {
+            *obj += &format!("g {}\n", group);
+        }
+
+        let mut materials : Vec<i16> = self.surfaces.iter().map(|s| s.material).collect();
+        materials.sort();
+        materials.dedup();
+        for material in materials {
+            let material_name = match material_names.get(material as usize) {
+                Some(n) if material >= 0 => n.clone(),
+                _ => format!("material_{}", material)
+            };
+            if group.is_none() {
+                *obj += &format!("g {}\n", material_name);
+            }
+            *obj += &format!("usemtl {}\n", material_name);
+            for (s,_) in self.surfaces.iter().enumerate().filter(|&(_,s)| s.material == material) {
+                let vertices = self.surface_vertices(s)?;
+                for i in 1..vertices.len().saturating_sub(1) {
+                    *obj += &format!("f {} {} {}\n", vertices[0] + vertex_base, vertices[i] + vertex_base, vertices[i + 1] + vertex_base);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Get the signed distance from a plane to a point.
+fn plane_distance(plane : &[f32 ; 4], point : &[f32 ; 3]) -> f32 {
+    plane[0] * point[0] + plane[1] * point[1] + plane[2] * point[2] - plane[3]
+}
+
+impl Tag {
+    /// Read a collision BSP at an offset in this tag's data.
+    pub fn collision_bsp(&self, offset : usize) -> Result<CollisionBSP,&'static str> {
+        let data = match self.data.as_ref() {
+            Some(n) if offset + COLLISION_BSP_SIZE <= n.len() => n,
+            _ => return Err("collision BSP is outside of the tag data")
+        };
+        let elements = |reflexive : usize, size : usize| -> Result<Vec<&[u8]>,&'static str> {
+            Ok(self.reflexive_elements(offset + reflexive, size)?.into_iter().map(|o| &data[o .. o + size]).collect())
+        };
+        let i32_at = |e : &[u8], o : usize| LittleEndian::read_i32(&e[o..]);
+        let f32_at = |e : &[u8], o : usize| LittleEndian::read_f32(&e[o..]);
+
+        Ok(CollisionBSP {
+            offset : offset,
+            bsp3d_nodes : elements(0x0, 0xC)?.iter().map(|e| CollisionBSP3DNode {
+                plane : i32_at(e, 0x0),
+                back_child : i32_at(e, 0x4),
+                front_child : i32_at(e, 0x8)
+            }).collect(),
+            planes : elements(0xC, 0x10)?.iter().map(|e| [f32_at(e, 0x0), f32_at(e, 0x4), f32_at(e, 0x8), f32_at(e, 0xC)]).collect(),
+            leaves : elements(0x18, 0x8)?.iter().map(|e| CollisionLeaf {
+                flags : LittleEndian::read_u16(&e[0x0..]),
+                bsp2d_reference_count : LittleEndian::read_u16(&e[0x2..]),
+                first_bsp2d_reference : i32_at(e, 0x4)
+            }).collect(),
+            bsp2d_references : elements(0x24, 0x8)?.iter().map(|e| CollisionBSP2DReference {
+                plane : i32_at(e, 0x0),
+                bsp2d_node : i32_at(e, 0x4)
+            }).collect(),
+            bsp2d_nodes : elements(0x30, 0x14)?.iter().map(|e| CollisionBSP2DNode {
+                plane : [f32_at(e, 0x0), f32_at(e, 0x4), f32_at(e, 0x8)],
+                left_child : i32_at(e, 0xC),
+                right_child : i32_at(e, 0x10)
+            }).collect(),
+            surfaces : elements(0x3C, 0xC)?.iter().map(|e| CollisionSurface {
+                plane : i32_at(e, 0x0),
+                first_edge : i32_at(e, 0x4),
+                flags : e[0x8],
+                breakable_surface : e[0x9] as i8,
+                material : LittleEndian::read_i16(&e[0xA..])
+            }).collect(),
+            edges : elements(0x48, 0x18)?.iter().map(|e| CollisionEdge {
+                start_vertex : i32_at(e, 0x0),
+                end_vertex : i32_at(e, 0x4),
+                forward_edge : i32_at(e, 0x8),
+                reverse_edge : i32_at(e, 0xC),
+                left_surface : i32_at(e, 0x10),
+                right_surface : i32_at(e, 0x14)
+            }).collect(),
+            vertices : elements(0x54, 0x10)?.iter().map(|e| [f32_at(e, 0x0), f32_at(e, 0x4), f32_at(e, 0x8)]).collect()
+        })
+    }
+
+    /// Get the material names of this coll tag.
+    pub fn collision_model_materials(&self) -> Result<Vec<String>,&'static str> {
+        let data = self.p_collision_model_data()?;
+        self.reflexive_elements(0x234, COLLISION_MODEL_MATERIAL_SIZE)?.into_iter().map(|o| string_from_slice(&data[o .. o + 0x20])).collect()
+    }
+
+    /// Get the regions and permutations of this coll tag.
+    pub fn collision_model_regions(&self) -> Result<Vec<CollisionModelRegion>,&'static str> {
+        let data = self.p_collision_model_data()?;
+        let mut regions = Vec::new();
+        for offset in self.reflexive_elements(0x240, COLLISION_MODEL_REGION_SIZE)? {
+            let mut permutations = Vec::new();
+            for p in self.reflexive_elements(offset + 0x48, COLLISION_MODEL_PERMUTATION_SIZE)? {
+                permutations.push(string_from_slice(&data[p .. p + 0x20])?);
+            }
+            regions.push(CollisionModelRegion {
+                name : string_from_slice(&data[offset .. offset + 0x20])?,
+                permutations : permutations
+            });
+        }
+        Ok(regions)
+    }
+
+    /// Get the pathfinding spheres of this coll tag.
+    pub fn collision_model_pathfinding_spheres(&self) -> Result<Vec<CollisionPathfindingSphere>,&'static str> {
+        let data = self.p_collision_model_data()?;
+        Ok(self.reflexive_elements(0x280, COLLISION_MODEL_PATHFINDING_SPHERE_SIZE)?.into_iter().map(|o| {
+            let mut sphere = [0f32 ; 4];
+            LittleEndian::read_f32_into(&data[o + 0x10 .. o + 0x20], &mut sphere);
+            let node = LittleEndian::read_i16(&data[o..]);
+            CollisionPathfindingSphere {
+                node : if node < 0 { None } else { Some(node as usize) },
+                center : [sphere[0], sphere[1], sphere[2]],
+                radius : sphere[3]
+            }
+        }).collect())
+    }
+
+    /// Get the nodes of this coll tag, including their collision BSPs.
+    pub fn collision_model_nodes(&self) -> Result<Vec<CollisionModelNode>,&'static str> {
+        let data = self.p_collision_model_data()?;
+        let index_or_none = |index : i16| if index < 0 { None } else { Some(index as usize) };
+        let mut nodes = Vec::new();
+        for offset in self.reflexive_elements(0x28C, COLLISION_MODEL_NODE_SIZE)? {
+            let mut bsps = Vec::new();
+            for b in self.reflexive_elements(offset + 0x34, COLLISION_BSP_SIZE)? {
+                bsps.push(self.collision_bsp(b)?);
+            }
+            nodes.push(CollisionModelNode {
+                name : string_from_slice(&data[offset .. offset + 0x20])?,
+                region : index_or_none(LittleEndian::read_i16(&data[offset + 0x20..])),
+                parent : index_or_none(LittleEndian::read_i16(&data[offset + 0x22..])),
+                next_sibling : index_or_none(LittleEndian::read_i16(&data[offset + 0x24..])),
+                first_child : index_or_none(LittleEndian::read_i16(&data[offset + 0x26..])),
+                bsps : bsps
+            });
+        }
+        Ok(nodes)
+    }
+
+    /// Export the collision BSPs and pathfinding spheres of this coll tag as a Wavefront OBJ
+    /// file. Each node's collision BSPs are grouped by region and permutation, and each
+    /// pathfinding sphere is put in its own group.
+    ///
+    /// Collision geometry is stored in node space. If `model` is a mod2 tag, nodes are moved to
+    /// the default pose of the model's nodes with the same name so the result lines up with the
+    /// render model. Otherwise, everything is left in node space.
+    pub fn export_collision_model_obj(&self, model : Option<&Tag>) -> Result<String,&'static str> {
+        let materials = self.collision_model_materials()?;
+        let regions = self.collision_model_regions()?;
+        let nodes = self.collision_model_nodes()?;
+        let spheres = self.collision_model_pathfinding_spheres()?;
+
+        let identity = transform_matrix(&[0.0 ; 3], &[0.0, 0.0, 0.0, 1.0]);
+        let matrices = match model {
+            Some(model) => {
+                let model_nodes = model.model_nodes()?;
+                let model_matrices = model.model_node_world_matrices()?;
+                nodes.iter().map(|n| match model_nodes.iter().position(|m| m.name.eq_ignore_ascii_case(&n.name)) {
+                    Some(m) => model_matrices[m],
+                    None => identity
+                }).collect()
+            },
+            None => vec![identity ; nodes.len()]
+        };
+
+        let mut obj = String::new();
+        obj += &format!("# {}\n", self.tag_path);
+        let mut vertex_base = 1;
+        for (n,node) in nodes.iter().enumerate() {
+            for (b,bsp) in node.bsps.iter().enumerate() {
+                let (region_name, permutation_name) = match node.region.and_then(|r| regions.get(r)) {
+                    Some(r) => (r.name.clone(), r.permutations.get(b).cloned().unwrap_or_else(|| format!("permutation_{}", b))),
+                    None => ("region_none".to_owned(), format!("permutation_{}", b))
+                };
+                obj += &format!("o {}_{}_{}\n", region_name, permutation_name, node.name);
+                bsp.p_write_obj(&mut obj, Some(&format!("{} {}", region_name, permutation_name)), &materials, vertex_base, &matrices[n])?;
+                vertex_base += bsp.vertices.len();
+            }
+        }
+
+        // Spheres are approximated with latitude and longitude lines.
+        const RINGS : usize = 6;
+        const SEGMENTS : usize = 8;
+        for (s,sphere) in spheres.iter().enumerate() {
+            let matrix = match sphere.node.and_then(|n| matrices.get(n)) {
+                Some(n) => n,
+                None => &identity
+            };
+            obj += &format!("o pathfinding_sphere_{}\ng pathfinding_spheres\n", s);
+            for ring in 0..RINGS + 1 {
+                let latitude = ::std::f32::consts::PI * ring as f32 / RINGS as f32;
+                for segment in 0..SEGMENTS {
+                    let longitude = 2.0 * ::std::f32::consts::PI * segment as f32 / SEGMENTS as f32;
+                    let point = [
+                        sphere.center[0] + sphere.radius * latitude.sin() * longitude.cos(),
+                        sphere.center[1] + sphere.radius * latitude.sin() * longitude.sin(),
+                        sphere.center[2] + sphere.radius * latitude.cos()
+                    ];
+                    let point = transform_point(matrix, &point);
+                    obj += &format!("v {:?} {:?} {:?}\n", point[0], point[1], point[2]);
+                }
+            }
+            for ring in 0..RINGS {
+                for segment in 0..SEGMENTS {
+                    let a = vertex_base + ring * SEGMENTS + segment;
+                    let b = vertex_base + ring * SEGMENTS + (segment + 1) % SEGMENTS;
+                    obj += &format!("f {} {} {} {}\n", a, a + SEGMENTS, b + SEGMENTS, b);
+                }
+            }
+            vertex_base += (RINGS + 1) * SEGMENTS;
+        }
+        Ok(obj)
+    }
+
+    // Get the tag data of a coll tag, checking that the header is there.
+    fn p_collision_model_data(&self) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != COLL {
+            return Err("tag is not a collision model tag");
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= 0x298 => Ok(n),
+            Some(_) => Err("coll tag is too small"),
+            None => Err("coll tag has no data")
+        }
+    }
+}
@@ -0,0 +1,372 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, TagArray, read_dependency, write_dependency, MATG, ITEM, UNIT, SHDR, SND, EFFE, GRHI, EQIP, PROJ, JPT};
+
+/// Offset of the grenades reflexive in a matg tag.
+pub const GLOBALS_GRENADES_OFFSET : usize = 0x128;
+
+/// Size of a grenade in a matg tag.
+pub const GLOBALS_GRENADE_SIZE : usize = 0x44;
+
+/// Offset of the weapon list reflexive in a matg tag.
+pub const GLOBALS_WEAPONS_OFFSET : usize = 0x14C;
+
+/// Size of a weapon in the weapon list of a matg tag.
+pub const GLOBALS_WEAPON_SIZE : usize = 0x10;
+
+/// Offset of the multiplayer information reflexive in a matg tag.
+pub const GLOBALS_MULTIPLAYER_INFORMATION_OFFSET : usize = 0x164;
+
+/// Size of the multiplayer information in a matg tag.
+pub const GLOBALS_MULTIPLAYER_INFORMATION_SIZE : usize = 0xA0;
+
+/// Offset of the player information reflexive in a matg tag.
+pub const GLOBALS_PLAYER_INFORMATION_OFFSET : usize = 0x170;
+
+/// Size of the player information in a matg tag.
+pub const GLOBALS_PLAYER_INFORMATION_SIZE : usize = 0xF4;
+
+/// Offset of the falling damage reflexive in a matg tag.
+pub const GLOBALS_FALLING_DAMAGE_OFFSET : usize = 0x188;
+
+/// Size of the falling damage in a matg tag.
+pub const GLOBALS_FALLING_DAMAGE_SIZE : usize = 0x98;
+
+#[derive(Clone,PartialEq,Debug)]
+/// The multiplayer information of a matg tag. References are tag paths and classes.
+pub struct MultiplayerInformation {
+    /// This is the item used as the CTF flag.
+    pub flag : Option<(String,u32)>,
+    pub unit : Option<(String,u32)>,
+    pub vehicles : Vec<Option<(String,u32)>>,
+    pub hill_shader : Option<(String,u32)>,
+    pub flag_shader : Option<(String,u32)>,
+
+    /// This is the item used as the oddball.
+    pub ball : Option<(String,u32)>,
+
+    /// These are the announcer sounds.
+    pub sounds : Vec<Option<(String,u32)>>
+}
+
+#[derive(Clone,PartialEq,Debug)]
+/// The player information of a matg tag. Speeds are in world units per second.
+pub struct PlayerInformation {
+    pub unit : Option<(String,u32)>,
+    pub walking_speed : f32,
+    pub double_speed_multiplier : f32,
+    pub run_forward : f32,
+    pub run_backward : f32,
+    pub run_sideways : f32,
+    pub run_acceleration : f32,
+    pub sneak_forward : f32,
+    pub sneak_backward : f32,
+    pub sneak_sideways : f32,
+    pub sneak_acceleration : f32,
+    pub airborne_acceleration : f32,
+    pub coop_respawn_effect : Option<(String,u32)>
+}
+
+#[derive(Clone,PartialEq,Debug)]
+/// A grenade type in a matg tag. References are tag paths and classes.
+pub struct Grenade {
+    pub maximum_count : u16,
+
+    /// This is how many grenades players spawn with in multiplayer.
+    pub multiplayer_spawn_count : u16,
+    pub throwing_effect : Option<(String,u32)>,
+    pub hud_interface : Option<(String,u32)>,
+    pub equipment : Option<(String,u32)>,
+    pub projectile : Option<(String,u32)>
+}
+
+#[derive(Clone,PartialEq,Debug)]
+/// The falling damage of a matg tag. References are tag paths and classes of damage effects.
+pub struct FallingDamage {
+    /// These are the distances, in world units, between which falling is harmful.
+    pub harmful_falling_distance : [f32 ; 2],
+    pub falling_damage : Option<(String,u32)>,
+    pub maximum_falling_distance : f32,
+    pub distance_damage : Option<(String,u32)>,
+    pub vehicle_environment_collision_damage : Option<(String,u32)>,
+    pub vehicle_killed_unit_damage : Option<(String,u32)>,
+    pub vehicle_collision_damage : Option<(String,u32)>,
+    pub flaming_death_damage : Option<(String,u32)>
+}
+
+impl Tag {
+    // Get the offset of the first element of a reflexive in a matg tag.
+    fn p_globals_element(&self, reflexive : usize, element_size : usize) -> Result<usize,&'static str> {
+        match self.reflexive_elements(reflexive, element_size)?.first() {
+            Some(n) => Ok(*n),
+            None => Err("matg tag is missing a globals block")
+        }
+    }
+
+    // Get the tag data of a matg tag, checking that the header is there.
+    fn p_globals_data(&self) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != MATG {
+            return Err("tag is not a globals tag");
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= 0x1AC => Ok(n),
+            Some(_) => Err("matg tag is too small"),
+            None => Err("matg tag has no data")
+        }
+    }
+}
+
+impl TagArray {
+    /// Get the index of the globals\globals matg tag, if there is one.
+    pub fn globals_tag(&self) -> Option<usize> {
+        self.find_tag("globals\\globals", MATG)
+    }
+
+    /// Get the multiplayer information of the globals\globals matg tag.
+    pub fn globals_multiplayer_information(&self) -> Result<MultiplayerInformation,&'static str> {
+        let tag = &self.tags()[self.p_globals_tag()?];
+        let data = tag.data.as_ref().unwrap();
+        let offset = tag.p_globals_element(GLOBALS_MULTIPLAYER_INFORMATION_OFFSET, GLOBALS_MULTIPLAYER_INFORMATION_SIZE)?;
+        Ok(MultiplayerInformation {
+            flag : self.p_reference(&data[offset + 0x0..])?,
+            unit : self.p_reference(&data[offset + 0x10..])?,
+            vehicles : self.p_reference_list(tag, offset + 0x20)?,
+            hill_shader : self.p_reference(&data[offset + 0x2C..])?,
+            flag_shader : self.p_reference(&data[offset + 0x3C..])?,
+            ball : self.p_reference(&data[offset + 0x4C..])?,
+            sounds : self.p_reference_list(tag, offset + 0x5C)?
+        })
+    }
+
+    /// Get the player information of the globals\globals matg tag.
+    pub fn globals_player_information(&self) -> Result<PlayerInformation,&'static str> {
+        let tag = &self.tags()[self.p_globals_tag()?];
+        let data = tag.data.as_ref().unwrap();
+        let offset = tag.p_globals_element(GLOBALS_PLAYER_INFORMATION_OFFSET, GLOBALS_PLAYER_INFORMATION_SIZE)?;
+        let mut speeds = [0f32 ; 12];
+        LittleEndian::read_f32_into(&data[offset + 0x2C .. offset + 0x5C], &mut speeds);
+        Ok(PlayerInformation {
+            unit : self.p_reference(&data[offset + 0x0..])?,
+            walking_speed : speeds[0],
+            double_speed_multiplier : speeds[1],
+            run_forward : speeds[2],
+            run_backward : speeds[3],
+            run_sideways : speeds[4],
+            run_acceleration : speeds[5],
+            sneak_forward : speeds[6],
+            sneak_backward : speeds[7],
+            sneak_sideways : speeds[8],
+            sneak_acceleration : speeds[9],
+            airborne_acceleration : speeds[10],
+            coop_respawn_effect : self.p_reference(&data[offset + 0xB8..])?
+        })
+    }
+
+    /// Get the grenades of the globals\globals matg tag.
+    pub fn globals_grenades(&self) -> Result<Vec<Grenade>,&'static str> {
+        let tag = &self.tags()[self.p_globals_tag()?];
+        let data = tag.data.as_ref().unwrap();
+        tag.reflexive_elements(GLOBALS_GRENADES_OFFSET, GLOBALS_GRENADE_SIZE)?.into_iter().map(|o| Ok(Grenade {
+            maximum_count : LittleEndian::read_u16(&data[o + 0x0..]),
+            multiplayer_spawn_count : LittleEndian::read_u16(&data[o + 0x2..]),
+            throwing_effect : self.p_reference(&data[o + 0x4..])?,
+            hud_interface : self.p_reference(&data[o + 0x14..])?,
+            equipment : self.p_reference(&data[o + 0x24..])?,
+            projectile : self.p_reference(&data[o + 0x34..])?
+        })).collect()
+    }
+
+    /// Get the falling damage of the globals\globals matg tag.
+    pub fn globals_falling_damage(&self) -> Result<FallingDamage,&'static str> {
+        let tag = &self.tags()[self.p_globals_tag()?];
+        let data = tag.data.as_ref().unwrap();
+        let offset = tag.p_globals_element(GLOBALS_FALLING_DAMAGE_OFFSET, GLOBALS_FALLING_DAMAGE_SIZE)?;
+        Ok(FallingDamage {
+            harmful_falling_distance : [LittleEndian::read_f32(&data[offset + 0x8..]), LittleEndian::read_f32(&data[offset + 0xC..])],
+            falling_damage : self.p_reference(&data[offset + 0x10..])?,
+            maximum_falling_distance : LittleEndian::read_f32(&data[offset + 0x28..]),
+            distance_damage : self.p_reference(&data[offset + 0x2C..])?,
+            vehicle_environment_collision_damage : self.p_reference(&data[offset + 0x3C..])?,
+            vehicle_killed_unit_damage : self.p_reference(&data[offset + 0x4C..])?,
+            vehicle_collision_damage : self.p_reference(&data[offset + 0x5C..])?,
+            flaming_death_damage : self.p_reference(&data[offset + 0x6C..])?
+        })
+    }
+
+    /// Get the weapon list of the globals\globals matg tag.
+    pub fn globals_weapons(&self) -> Result<Vec<Option<(String,u32)>>,&'static str> {
+        let tag = &self.tags()[self.p_globals_tag()?];
+        self.p_reference_list(tag, GLOBALS_WEAPONS_OFFSET)
+    }
+
+    /// Set the multiplayer information of the globals\globals matg tag.
+    ///
+    /// Referenced tags that are not in this tag array are inserted recursively from the origin
+    /// tag array, if one is given. Returns `Err` without changing anything if a referenced tag
+    /// cannot be found or is not of the right class.
+    pub fn set_globals_multiplayer_information(&mut self, information : &MultiplayerInformation, origin_tag_array : Option<&TagArray>) -> Result<(),&'static str> {
+        let globals = self.p_globals_tag()?;
+        let offset = self.tags()[globals].p_globals_element(GLOBALS_MULTIPLAYER_INFORMATION_OFFSET, GLOBALS_MULTIPLAYER_INFORMATION_SIZE)?;
+        let mut references = vec![(&information.flag, ITEM), (&information.unit, UNIT), (&information.hill_shader, SHDR), (&information.flag_shader, SHDR), (&information.ball, ITEM)];
+        references.extend(information.vehicles.iter().map(|v| (v, UNIT)));
+        references.extend(information.sounds.iter().map(|s| (s, SND)));
+        let indices = self.p_import_references(&references, origin_tag_array)?;
+
+        let mut element = self.tags()[globals].data.as_ref().unwrap()[offset .. offset + GLOBALS_MULTIPLAYER_INFORMATION_SIZE].to_owned();
+        for (i, o) in [0x0, 0x10, 0x2C, 0x3C, 0x4C].iter().enumerate() {
+            write_dependency(&mut element[*o..], self.p_dependency_class(indices[i], references[i].1)?, indices[i]);
+        }
+        let vehicles = self.p_dependency_list(&indices[5 .. 5 + information.vehicles.len()], UNIT)?;
+        let sounds = self.p_dependency_list(&indices[5 + information.vehicles.len()..], SND)?;
+
+        let tag = &mut self.tags_mut()[globals];
+        tag.data.as_mut().unwrap()[offset .. offset + 0x20].copy_from_slice(&element[0x0 .. 0x20]);
+        tag.data.as_mut().unwrap()[offset + 0x2C .. offset + 0x5C].copy_from_slice(&element[0x2C .. 0x5C]);
+        tag.replace_reflexive(offset + 0x20, 0x10, &vehicles);
+        let offset = tag.p_globals_element(GLOBALS_MULTIPLAYER_INFORMATION_OFFSET, GLOBALS_MULTIPLAYER_INFORMATION_SIZE)?;
+        tag.replace_reflexive(offset + 0x5C, 0x10, &sounds);
+        Ok(())
+    }
+
+    /// Set the player information of the globals\globals matg tag. See
+    /// `set_globals_multiplayer_information` for how references are handled.
+    pub fn set_globals_player_information(&mut self, information : &PlayerInformation, origin_tag_array : Option<&TagArray>) -> Result<(),&'static str> {
+        let globals = self.p_globals_tag()?;
+        let offset = self.tags()[globals].p_globals_element(GLOBALS_PLAYER_INFORMATION_OFFSET, GLOBALS_PLAYER_INFORMATION_SIZE)?;
+        let indices = self.p_import_references(&[(&information.unit, UNIT), (&information.coop_respawn_effect, EFFE)], origin_tag_array)?;
+        let mut element = self.tags()[globals].data.as_ref().unwrap()[offset .. offset + GLOBALS_PLAYER_INFORMATION_SIZE].to_owned();
+        write_dependency(&mut element[0x0..], self.p_dependency_class(indices[0], UNIT)?, indices[0]);
+        write_dependency(&mut element[0xB8..], self.p_dependency_class(indices[1], EFFE)?, indices[1]);
+        LittleEndian::write_f32_into(&[
+            information.walking_speed,
+            information.double_speed_multiplier,
+            information.run_forward,
+            information.run_backward,
+            information.run_sideways,
+            information.run_acceleration,
+            information.sneak_forward,
+            information.sneak_backward,
+            information.sneak_sideways,
+            information.sneak_acceleration,
+            information.airborne_acceleration
+        ], &mut element[0x2C .. 0x58]);
+        self.tags_mut()[globals].data.as_mut().unwrap()[offset .. offset + GLOBALS_PLAYER_INFORMATION_SIZE].copy_from_slice(&element);
+        Ok(())
+    }
+
+    /// Set the grenades of the globals\globals matg tag. See
+    /// `set_globals_multiplayer_information` for how references are handled.
+    pub fn set_globals_grenades(&mut self, grenades : &[Grenade], origin_tag_array : Option<&TagArray>) -> Result<(),&'static str> {
+        let globals = self.p_globals_tag()?;
+        self.tags()[globals].reflexive_elements(GLOBALS_GRENADES_OFFSET, GLOBALS_GRENADE_SIZE)?;
+        let classes = [EFFE, GRHI, EQIP, PROJ];
+        let references : Vec<(&Option<(String,u32)>,u32)> = grenades.iter().flat_map(|g| vec![&g.throwing_effect, &g.hud_interface, &g.equipment, &g.projectile].into_iter().zip(classes.iter().cloned())).collect();
+        let indices = self.p_import_references(&references, origin_tag_array)?;
+
+        let mut new_data = vec![0u8 ; grenades.len() * GLOBALS_GRENADE_SIZE];
+        for ((grenade, element), indices) in grenades.iter().zip(new_data.chunks_mut(GLOBALS_GRENADE_SIZE)).zip(indices.chunks(classes.len())) {
+            LittleEndian::write_u16(&mut element[0x0..], grenade.maximum_count);
+            LittleEndian::write_u16(&mut element[0x2..], grenade.multiplayer_spawn_count);
+            for (i, o) in [0x4, 0x14, 0x24, 0x34].iter().enumerate() {
+                write_dependency(&mut element[*o..], self.p_dependency_class(indices[i], classes[i])?, indices[i]);
+            }
+        }
+        self.tags_mut()[globals].replace_reflexive(GLOBALS_GRENADES_OFFSET, GLOBALS_GRENADE_SIZE, &new_data);
+        Ok(())
+    }
+
+    /// Set the falling damage of the globals\globals matg tag. See
+    /// `set_globals_multiplayer_information` for how references are handled.
+    pub fn set_globals_falling_damage(&mut self, damage : &FallingDamage, origin_tag_array : Option<&TagArray>) -> Result<(),&'static str> {
+        let globals = self.p_globals_tag()?;
+        let offset = self.tags()[globals].p_globals_element(GLOBALS_FALLING_DAMAGE_OFFSET, GLOBALS_FALLING_DAMAGE_SIZE)?;
+        let references = [
+            (&damage.falling_damage, JPT),
+            (&damage.distance_damage, JPT),
+            (&damage.vehicle_environment_collision_damage, JPT),
+            (&damage.vehicle_killed_unit_damage, JPT),
+            (&damage.vehicle_collision_damage, JPT),
+            (&damage.flaming_death_damage, JPT)
+        ];
+        let indices = self.p_import_references(&references, origin_tag_array)?;
+
+        let mut element = self.tags()[globals].data.as_ref().unwrap()[offset .. offset + GLOBALS_FALLING_DAMAGE_SIZE].to_owned();
+        LittleEndian::write_f32_into(&damage.harmful_falling_distance, &mut element[0x8 .. 0x10]);
+        LittleEndian::write_f32(&mut element[0x28..], damage.maximum_falling_distance);
+        for (o, index) in [0x10, 0x2C, 0x3C, 0x4C, 0x5C, 0x6C].iter().zip(indices) {
+            write_dependency(&mut element[*o..], self.p_dependency_class(index, JPT)?, index);
+        }
+        self.tags_mut()[globals].data.as_mut().unwrap()[offset .. offset + GLOBALS_FALLING_DAMAGE_SIZE].copy_from_slice(&element);
+        Ok(())
+    }
+
+    /// Set the weapon list of the globals\globals matg tag. See
+    /// `set_globals_multiplayer_information` for how references are handled.
+    pub fn set_globals_weapons(&mut self, weapons : &[Option<(String,u32)>], origin_tag_array : Option<&TagArray>) -> Result<(),&'static str> {
+        let globals = self.p_globals_tag()?;
+        self.tags()[globals].reflexive_elements(GLOBALS_WEAPONS_OFFSET, GLOBALS_WEAPON_SIZE)?;
+        let references : Vec<(&Option<(String,u32)>,u32)> = weapons.iter().map(|w| (w, ITEM)).collect();
+        let indices = self.p_import_references(&references, origin_tag_array)?;
+        let new_data = self.p_dependency_list(&indices, ITEM)?;
+        self.tags_mut()[globals].replace_reflexive(GLOBALS_WEAPONS_OFFSET, GLOBALS_WEAPON_SIZE, &new_data);
+        Ok(())
+    }
+
+    // Get the globals\globals matg tag, checking that it has data.
+    fn p_globals_tag(&self) -> Result<usize,&'static str> {
+        match self.globals_tag() {
+            Some(n) => self.tags()[n].p_globals_data().map(|_| n),
+            None => Err("tag array has no globals tag")
+        }
+    }
+
+    // Get the path and class of the tag referenced by a dependency.
+    fn p_reference(&self, dependency : &[u8]) -> Result<Option<(String,u32)>,&'static str> {
+        match read_dependency(dependency) {
+            Some(n) => match self.tags().get(n) {
+                Some(t) => Ok(Some((t.tag_path.clone(), t.tag_class.0))),
+                None => Err("invalid tag reference in globals tag")
+            },
+            None => Ok(None)
+        }
+    }
+
+    // Get the paths and classes of the tags referenced by a reflexive of dependencies.
+    fn p_reference_list(&self, tag : &Tag, reflexive : usize) -> Result<Vec<Option<(String,u32)>>,&'static str> {
+        let data = tag.data.as_ref().unwrap();
+        tag.reflexive_elements(reflexive, 0x10)?.into_iter().map(|o| self.p_reference(&data[o..])).collect()
+    }
+
+    // Find each referenced tag, inserting it from the origin tag array if it is not here. Every
+    // reference is checked against its class before any tag is inserted.
+    fn p_import_references(&mut self, references : &[(&Option<(String,u32)>,u32)], origin_tag_array : Option<&TagArray>) -> Result<Vec<Option<usize>>,&'static str> {
+        for &(reference, tag_class) in references {
+            if let Some((ref path, class)) = *reference {
+                let tag = match self.find_tag(path, class) {
+                    Some(n) => &self.tags()[n],
+                    None => match origin_tag_array.and_then(|o| o.find_tag(path, class).map(|i| &o.tags()[i])) {
+                        Some(n) => n,
+                        None => return Err("referenced tag could not be found")
+                    }
+                };
+                if tag.tag_class.0 != tag_class && tag.tag_class.1 != tag_class && tag.tag_class.2 != tag_class {
+                    return Err("referenced tag is the wrong class");
+                }
+            }
+        }
+        references.iter().map(|&(reference, _)| match *reference {
+            Some((ref path, class)) => self.find_or_insert_recursive(path, class, origin_tag_array).map(Some),
+            None => Ok(None)
+        }).collect()
+    }
+
+    // Build the elements of a reflexive of dependencies.
+    fn p_dependency_list(&self, references : &[Option<usize>], tag_class : u32) -> Result<Vec<u8>,&'static str> {
+        let mut data = vec![0u8 ; references.len() * 0x10];
+        for (reference, dependency) in references.iter().zip(data.chunks_mut(0x10)) {
+            write_dependency(dependency, self.p_dependency_class(*reference, tag_class)?, *reference);
+        }
+        Ok(data)
+    }
+}
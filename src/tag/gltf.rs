@@ -0,0 +1,206 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+const BASE64_ALPHABET : &[u8 ; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Buffer view targets.
+pub const ARRAY_BUFFER : u32 = 34962;
+pub const ELEMENT_ARRAY_BUFFER : u32 = 34963;
+
+// Builds a glTF 2.0 file with a single embedded buffer. Objects other than buffer views and
+// accessors are added as already-formatted JSON.
+pub struct Gltf {
+    buffer : Vec<u8>,
+    buffer_views : Vec<String>,
+    accessors : Vec<String>,
+    pub nodes : Vec<String>,
+    pub meshes : Vec<String>,
+    pub skins : Vec<String>,
+    pub animations : Vec<String>,
+    pub scene_nodes : Vec<usize>
+}
+impl Gltf {
+    pub fn new() -> Gltf {
+        Gltf {
+            buffer : Vec::new(),
+            buffer_views : Vec::new(),
+            accessors : Vec::new(),
+            nodes : Vec::new(),
+            meshes : Vec::new(),
+            skins : Vec::new(),
+            animations : Vec::new(),
+            scene_nodes : Vec::new()
+        }
+    }
+
+    // Add a buffer view, returning its index.
+    fn add_buffer_view(&mut self, data : &[u8], target : Option<u32>) -> usize {
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+        let target = match target {
+            Some(n) => format!(",\"target\":{}", n),
+            None => String::new()
+        };
+        self.buffer_views.push(format!("{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{}{}}}", self.buffer.len(), data.len(), target));
+        self.buffer.extend_from_slice(data);
+        self.buffer_views.len() - 1
+    }
+
+    // Add an accessor of 32-bit floats, returning its index. `components` is the number of floats
+    // per element, and `element_type` is the glTF type (such as VEC3).
+    pub fn add_floats(&mut self, data : &[f32], components : usize, element_type : &str, target : Option<u32>, bounds : bool) -> usize {
+        let mut bytes = vec![0u8 ; data.len() * 4];
+        LittleEndian::write_f32_into(data, &mut bytes);
+        let view = self.add_buffer_view(&bytes, target);
+        let count = data.len() / components;
+        let bounds = if bounds && count > 0 {
+            let mut min = vec![::std::f32::MAX ; components];
+            let mut max = vec![::std::f32::MIN ; components];
+            for element in data.chunks(components) {
+                for c in 0..components {
+                    min[c] = min[c].min(element[c]);
+                    max[c] = max[c].max(element[c]);
+                }
+            }
+            format!(",\"min\":{},\"max\":{}", json_floats(&min), json_floats(&max))
+        }
+        else {
+            String::new()
+        };
+        self.accessors.push(format!("{{\"bufferView\":{},\"componentType\":5126,\"count\":{},\"type\":\"{}\"{}}}", view, count, element_type, bounds));
+        self.accessors.len() - 1
+    }
+
+    // Add an accessor of 16-bit unsigned integers, returning its index.
+    pub fn add_u16s(&mut self, data : &[u16], components : usize, element_type : &str, target : Option<u32>) -> usize {
+        let mut bytes = vec![0u8 ; data.len() * 2];
+        LittleEndian::write_u16_into(data, &mut bytes);
+        let view = self.add_buffer_view(&bytes, target);
+        self.accessors.push(format!("{{\"bufferView\":{},\"componentType\":5123,\"count\":{},\"type\":\"{}\"}}", view, data.len() / components, element_type));
+        self.accessors.len() - 1
+    }
+
+    // Encode the glTF file as JSON with the buffer embedded as a data URI.
+    pub fn as_json(&self) -> String {
+        let mut json = String::from("{\"asset\":{\"version\":\"2.0\",\"generator\":\"tritium\"}");
+        json += &format!(",\"scene\":0,\"scenes\":[{{\"nodes\":[{}]}}]", self.scene_nodes.iter().map(|n| n.to_string()).collect::<Vec<String>>().join(","));
+        for &(name, objects) in &[("nodes", &self.nodes), ("meshes", &self.meshes), ("skins", &self.skins), ("animations", &self.animations), ("accessors", &self.accessors), ("bufferViews", &self.buffer_views)] {
+            if !objects.is_empty() {
+                json += &format!(",\"{}\":[{}]", name, objects.join(","));
+            }
+        }
+        if !self.buffer.is_empty() {
+            json += &format!(",\"buffers\":[{{\"byteLength\":{},\"uri\":\"data:application/octet-stream;base64,{}\"}}]", self.buffer.len(), base64(&self.buffer));
+        }
+        json += "}";
+        json
+    }
+}
+
+// Format a float as a JSON number. JSON has no infinity or NaN, so those become zero.
+pub fn json_float(float : f32) -> String {
+    if float.is_finite() { format!("{:?}", float) } else { "0.0".to_owned() }
+}
+
+// Format floats as a JSON array.
+pub fn json_floats(floats : &[f32]) -> String {
+    format!("[{}]", floats.iter().map(|f| json_float(*f)).collect::<Vec<String>>().join(","))
+}
+
+// Format a string as a JSON string, escaping it as needed.
+pub fn json_string(string : &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            c if (c as u32) < 0x20 => json += &format!("\\u{:04x}", c as u32),
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+    json
+}
+
+// Encode data as base64.
+fn base64(data : &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let value = (bytes[0] as usize) << 16 | (bytes[1] as usize) << 8 | bytes[2] as usize;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(value >> (18 - i * 6)) & 0x3F] as char);
+            }
+            else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Multiply two column-major 4x4 matrices.
+pub fn multiply_matrices(a : &[f32 ; 16], b : &[f32 ; 16]) -> [f32 ; 16] {
+    let mut result = [0.0 ; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            result[column * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+        }
+    }
+    result
+}
+
+// Make a column-major 4x4 matrix from a translation and a rotation quaternion (i, j, k, w).
+pub fn transform_matrix(translation : &[f32 ; 3], rotation : &[f32 ; 4]) -> [f32 ; 16] {
+    let length = rotation.iter().map(|r| r * r).sum::<f32>().sqrt();
+    let (x, y, z, w) = if length > 0.0 {
+        (rotation[0] / length, rotation[1] / length, rotation[2] / length, rotation[3] / length)
+    }
+    else {
+        (0.0, 0.0, 0.0, 1.0)
+    };
+    [
+        1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w), 0.0,
+        2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w), 0.0,
+        2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y), 0.0,
+        translation[0], translation[1], translation[2], 1.0
+    ]
+}
+
+// Invert a column-major 4x4 matrix made of only a rotation and a translation.
+pub fn invert_rigid_matrix(m : &[f32 ; 16]) -> [f32 ; 16] {
+    let mut result = [0.0 ; 16];
+    for column in 0..3 {
+        for row in 0..3 {
+            result[column * 4 + row] = m[row * 4 + column];
+        }
+    }
+    for row in 0..3 {
+        result[12 + row] = -(0..3).map(|k| result[k * 4 + row] * m[12 + k]).sum::<f32>();
+    }
+    result[15] = 1.0;
+    result
+}
+
+// Normalize a quaternion, using the identity if it has no length.
+pub fn normalized_quaternion(rotation : &[f32 ; 4]) -> [f32 ; 4] {
+    let length = rotation.iter().map(|r| r * r).sum::<f32>().sqrt();
+    if length > 0.0 {
+        [rotation[0] / length, rotation[1] / length, rotation[2] / length, rotation[3] / length]
+    }
+    else {
+        [0.0, 0.0, 0.0, 1.0]
+    }
+}
+
+// Transform a point by a column-major 4x4 matrix.
+pub fn transform_point(m : &[f32 ; 16], point : &[f32 ; 3]) -> [f32 ; 3] {
+    let mut result = [0.0 ; 3];
+    for row in 0..3 {
+        result[row] = m[row] * point[0] + m[4 + row] * point[1] + m[8 + row] * point[2] + m[12 + row];
+    }
+    result
+}
@@ -6,12 +6,69 @@ use self::byteorder::{ByteOrder,LittleEndian};
 mod tag_array;
 pub use self::tag_array::*;
 
+mod root_set;
+pub use self::root_set::*;
+
+mod script;
+pub use self::script::*;
+
+mod script_compiler;
+pub use self::script_compiler::*;
+
+mod bitmap;
+pub use self::bitmap::*;
+
+mod sound;
+pub use self::sound::*;
+
+mod model;
+pub use self::model::*;
+
+mod animation;
+pub use self::animation::*;
+
+mod collision;
+pub use self::collision::*;
+
+mod bsp;
+pub use self::bsp::*;
+
+mod scenario;
+pub use self::scenario::*;
+
+mod globals;
+pub use self::globals::*;
+
+mod collection;
+pub use self::collection::*;
+
+mod gltf;
+mod png;
+mod script_definitions;
+mod wav;
+
 const BITM : u32 = 0x6269746D;
 const SND : u32 = 0x736E6421;
 const OBJE : u32 = 0x6F626A65;
 const SBSP : u32 = 0x73627370;
 const SCNR : u32 = 0x73636E72;
 const EFFE : u32 = 0x65666665;
+const ANTR : u32 = 0x616E7472;
+const COLL : u32 = 0x636F6C6C;
+const DELA : u32 = 0x44654C61;
+const EQIP : u32 = 0x65716970;
+const GRHI : u32 = 0x67726869;
+const ITEM : u32 = 0x6974656D;
+const ITMC : u32 = 0x69746D63;
+const JPT : u32 = 0x6A707421;
+const MATG : u32 = 0x6D617467;
+const MOD2 : u32 = 0x6D6F6432;
+const MODE : u32 = 0x6D6F6465;
+const PROJ : u32 = 0x70726F6A;
+const SHDR : u32 = 0x73686472;
+const TAGC : u32 = 0x74616763;
+const UNIT : u32 = 0x756E6974;
+const USTR : u32 = 0x75737472;
 
 #[derive(Clone)]
 /// Tags can vary on how they reference other tags.
@@ -109,6 +166,26 @@ impl Tag {
         }
     }
 
+    /// Get the offsets of each element of a reflexive at an offset in this tag's data.
+    ///
+    /// Returns `Err` if the reflexive points outside of the tag data.
+    ///
+    /// Panics if there is no data or memory address used by this tag.
+    pub fn reflexive_elements(&self, offset : usize, element_size : usize) -> Result<Vec<usize>,&'static str> {
+        let data = self.data.as_ref().unwrap();
+        if offset + 0xC > data.len() {
+            return Err("reflexive is outside of the tag data");
+        }
+        let count = LittleEndian::read_u32(&data[offset..]) as usize;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        match self.offset_from_memory_address(LittleEndian::read_u32(&data[offset + 4..])) {
+            Some(n) if n + count * element_size <= data.len() => Ok((0..count).map(|i| n + i * element_size).collect()),
+            _ => Err("invalid address on reflexive")
+        }
+    }
+
     /// Change the memory address to something else.
     ///
     /// Panics if the address given cannot be used, if there is no memory address used by this tag,
@@ -360,6 +437,9 @@ impl Tag {
             for i in add_predicted_resources(0xEC) {
                 references.push(i);
             }
+            for i in self.script_references(tag_array) {
+                references.push(i);
+            }
         }
         if self.tag_class.0 == SBSP {
             let clusters_count = LittleEndian::read_u32(&data[0x14C..]) as usize;
@@ -427,12 +507,75 @@ impl Tag {
     /// This function will panic if there is no tag data or memory address used by the tag.
     pub fn delete_data(&mut self, offset : usize, size : usize) {
         self.offset_pointers(offset+size,size as u32,true);
-        let mut tag_data = self.data.as_mut().unwrap();
-        for _ in 0..size {
-            tag_data.remove(offset);
-        }
+        self.data.as_mut().unwrap().drain(offset .. offset + size);
+    }
+
+    /// Replace the contents of a tag data block (such as the script syntax data) at an offset in
+    /// this tag's data, adjusting pointers as needed.
+    ///
+    /// This function will panic if there is no memory address or data used by the tag.
+    pub fn replace_data_block(&mut self, offset : usize, new_data : &[u8]) {
+        let old = self.data_block_offset(offset);
+        let new_offset = self.p_replace_region(old, new_data);
+        let memory_address = *self.memory_address.as_ref().unwrap();
+        let data = self.data.as_mut().unwrap();
+        LittleEndian::write_u32(&mut data[offset..], new_data.len() as u32);
+        LittleEndian::write_u32(&mut data[offset + 0x4..], 0);
+        LittleEndian::write_u32(&mut data[offset + 0x8..], 0);
+        LittleEndian::write_u32(&mut data[offset + 0xC..], match new_offset {
+            Some(n) => memory_address + n as u32,
+            None => 0
+        });
+    }
+
+    /// Replace the elements of a reflexive at an offset in this tag's data, adjusting pointers as
+    /// needed. The length of `new_data` must be a multiple of `element_size`.
+    ///
+    /// This function will panic if there is no memory address or data used by the tag.
+    pub fn replace_reflexive(&mut self, offset : usize, element_size : usize, new_data : &[u8]) {
+        assert!(new_data.len() % element_size == 0, "reflexive data is not a multiple of the element size");
+        let old = {
+            let data = self.data.as_ref().unwrap();
+            let count = LittleEndian::read_u32(&data[offset..]) as usize;
+            if count == 0 {
+                None
+            }
+            else {
+                match self.offset_from_memory_address(LittleEndian::read_u32(&data[offset + 4..])) {
+                    Some(n) if n + count * element_size <= data.len() => Some((n, count * element_size)),
+                    _ => None
+                }
+            }
+        };
+        let new_offset = self.p_replace_region(old, new_data);
+        let memory_address = *self.memory_address.as_ref().unwrap();
+        let data = self.data.as_mut().unwrap();
+        LittleEndian::write_u32(&mut data[offset..], (new_data.len() / element_size) as u32);
+        LittleEndian::write_u32(&mut data[offset + 4..], match new_offset {
+            Some(n) => memory_address + n as u32,
+            None => 0
+        });
     }
 
+    // Replace a region of tag data with new data, returning the offset of the new data. If there
+    // was no region, the new data is appended. Pointers to the region itself are not changed.
+    fn p_replace_region(&mut self, old : Option<(usize,usize)>, new_data : &[u8]) -> Option<usize> {
+        match old {
+            Some((offset,size)) => {
+                self.insert_data(offset + size, new_data);
+                self.delete_data(offset, size);
+                if new_data.is_empty() { None } else { Some(offset) }
+            },
+            None => {
+                if new_data.is_empty() {
+                    return None;
+                }
+                let offset = self.data.as_ref().unwrap().len();
+                self.insert_data(offset, new_data);
+                Some(offset)
+            }
+        }
+    }
     /// Offset pointers that point to the offset or after without adding or removing any data.
     /// Setting `subtract` to true will decrease the pointers instead of increasing them.
     ///
@@ -531,7 +674,7 @@ impl Tag {
             _ => {
                 let mut i = 0;
                 if tag_data.len() >= 12 {
-                    while i < tag_data.len()-12+2 {
+                    while i + 12 <= tag_data.len() {
                         let count = LittleEndian::read_u32(&tag_data[i..]);
                         let address = LittleEndian::read_u32(&tag_data[i + 4..]);
                         let zero = LittleEndian::read_u32(&tag_data[i + 8..]);
@@ -547,7 +690,55 @@ impl Tag {
             }
         }
 
+        // Script data blocks are not reflexives, so they cannot be pattern-matched.
+        if self.tag_class.0 == SCNR {
+            for offset in &[SCRIPT_SYNTAX_DATA_OFFSET, SCRIPT_STRING_DATA_OFFSET] {
+                let pointer = offset + 0xC;
+                if pointer + 4 > tag_data.len() || LittleEndian::read_u32(&tag_data[*offset..]) == 0 || pointers.contains(&pointer) {
+                    continue;
+                }
+                let address = LittleEndian::read_u32(&tag_data[pointer..]);
+                if address >= memory_address && address < memory_address_end {
+                    pointers.push(pointer);
+                }
+            }
+        }
+
+        // Neither are the mouth and subtitle data blocks of sound permutations.
+        if self.tag_class.0 == SND {
+            if let Ok(ranges) = self.sound_pitch_ranges() {
+                for permutation in ranges.iter().flat_map(|r| r.permutations.iter()) {
+                    for offset in &[permutation.offset + 0x54, permutation.offset + 0x68] {
+                        let pointer = offset + 0xC;
+                        if LittleEndian::read_u32(&tag_data[*offset..]) == 0 || pointers.contains(&pointer) {
+                            continue;
+                        }
+                        let address = LittleEndian::read_u32(&tag_data[pointer..]);
+                        if address >= memory_address && address < memory_address_end {
+                            pointers.push(pointer);
+                        }
+                    }
+                }
+            }
+        }
 
         pointers
     }
 }
+
+// Read the tag index of a dependency, if it references a tag.
+fn read_dependency(dependency : &[u8]) -> Option<usize> {
+    let tag_id = LittleEndian::read_u32(&dependency[0xC..]);
+    if tag_id == 0xFFFFFFFF { None } else { Some(tag_id as usize & 0xFFFF) }
+}
+
+// Write a dependency to a slice. The path pointer is cleared as maps do not use it.
+fn write_dependency(dependency : &mut [u8], tag_class : u32, tag_index : Option<usize>) {
+    LittleEndian::write_u32(&mut dependency[0x0..], tag_class);
+    LittleEndian::write_u32(&mut dependency[0x4..], 0);
+    LittleEndian::write_u32(&mut dependency[0x8..], 0);
+    LittleEndian::write_u32(&mut dependency[0xC..], match tag_index {
+        Some(n) => tag_index_to_tag_id(n),
+        None => 0xFFFFFFFF
+    });
+}
@@ -0,0 +1,938 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, MOD2, MODE};
+use super::gltf::{Gltf, ARRAY_BUFFER, ELEMENT_ARRAY_BUFFER, json_floats, json_string, multiply_matrices, transform_matrix, invert_rigid_matrix, normalized_quaternion};
+use super::super::string_from_slice;
+
+/// Size of an uncompressed model vertex.
+pub const MODEL_VERTEX_SIZE : usize = 0x44;
+
+/// Size of a compressed model vertex.
+pub const MODEL_COMPRESSED_VERTEX_SIZE : usize = 0x20;
+
+/// Vertex buffers of this type hold uncompressed model vertices.
+pub const MODEL_VERTEX_TYPE_UNCOMPRESSED : u32 = 4;
+
+/// Vertex buffers of this type hold compressed model vertices.
+pub const MODEL_VERTEX_TYPE_COMPRESSED : u32 = 5;
+
+/// Size of a geometry part in a mod2 tag.
+pub const MODEL_PART_SIZE : usize = 0x84;
+
+/// Size of a geometry part in a mode tag. These parts have no local nodes.
+pub const MODEL_XBOX_PART_SIZE : usize = 0x68;
+
+/// Size of a geometry in a model tag.
+pub const MODEL_GEOMETRY_SIZE : usize = 0x30;
+
+/// Size of a node in a model tag.
+pub const MODEL_NODE_SIZE : usize = 0x9C;
+
+/// Size of a region in a model tag.
+pub const MODEL_REGION_SIZE : usize = 0x4C;
+
+/// Size of a region permutation in a model tag.
+pub const MODEL_PERMUTATION_SIZE : usize = 0x58;
+
+/// mod2 tags with this flag have parts that use local node indices.
+pub const MODEL_FLAG_PARTS_HAVE_LOCAL_NODES : u32 = 0x2;
+
+#[derive(Clone,Copy,PartialEq)]
+/// Levels of detail of a model, from the least detailed to the most detailed.
+pub enum ModelLOD {
+    SuperLow,
+    Low,
+    Medium,
+    High,
+    SuperHigh
+}
+impl ModelLOD {
+    /// Every level of detail, from the least detailed to the most detailed.
+    pub const ALL : [ModelLOD ; 5] = [ModelLOD::SuperLow, ModelLOD::Low, ModelLOD::Medium, ModelLOD::High, ModelLOD::SuperHigh];
+
+    /// Get the name of the level of detail as used by the tools.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ModelLOD::SuperLow => "superlow",
+            ModelLOD::Low => "low",
+            ModelLOD::Medium => "medium",
+            ModelLOD::High => "high",
+            ModelLOD::SuperHigh => "superhigh"
+        }
+    }
+
+    /// Get the index of the level of detail, where super low is 0.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+#[derive(Clone,Copy,PartialEq)]
+/// An uncompressed model vertex.
+pub struct ModelVertex {
+    pub position : [f32 ; 3],
+    pub normal : [f32 ; 3],
+    pub binormal : [f32 ; 3],
+    pub tangent : [f32 ; 3],
+    pub texture_coords : [f32 ; 2],
+
+    /// These are the indices of the nodes the vertex is weighted to. Unused nodes are -1.
+    pub node_indices : [i16 ; 2],
+    pub node_weights : [f32 ; 2]
+}
+impl ModelVertex {
+    /// Read a model vertex from a slice.
+    ///
+    /// This function will panic if `vertex` is smaller than `MODEL_VERTEX_SIZE`.
+    pub fn from_slice(vertex : &[u8]) -> ModelVertex {
+        let mut floats = [0f32 ; 14];
+        LittleEndian::read_f32_into(&vertex[0..0x38], &mut floats);
+        ModelVertex {
+            position : [floats[0], floats[1], floats[2]],
+            normal : [floats[3], floats[4], floats[5]],
+            binormal : [floats[6], floats[7], floats[8]],
+            tangent : [floats[9], floats[10], floats[11]],
+            texture_coords : [floats[12], floats[13]],
+            node_indices : [LittleEndian::read_i16(&vertex[0x38..]), LittleEndian::read_i16(&vertex[0x3A..])],
+            node_weights : [LittleEndian::read_f32(&vertex[0x3C..]), LittleEndian::read_f32(&vertex[0x40..])]
+        }
+    }
+
+    /// Write the model vertex to a slice.
+    ///
+    /// This function will panic if `vertex` is smaller than `MODEL_VERTEX_SIZE`.
+    pub fn write_to_slice(&self, vertex : &mut [u8]) {
+        let mut floats = [0f32 ; 14];
+        floats[0..3].copy_from_slice(&self.position);
+        floats[3..6].copy_from_slice(&self.normal);
+        floats[6..9].copy_from_slice(&self.binormal);
+        floats[9..12].copy_from_slice(&self.tangent);
+        floats[12..14].copy_from_slice(&self.texture_coords);
+        LittleEndian::write_f32_into(&floats, &mut vertex[0..0x38]);
+        LittleEndian::write_i16(&mut vertex[0x38..], self.node_indices[0]);
+        LittleEndian::write_i16(&mut vertex[0x3A..], self.node_indices[1]);
+        LittleEndian::write_f32(&mut vertex[0x3C..], self.node_weights[0]);
+        LittleEndian::write_f32(&mut vertex[0x40..], self.node_weights[1]);
+    }
+
+    /// Read a compressed model vertex from a slice.
+    ///
+    /// This function will panic if `vertex` is smaller than `MODEL_COMPRESSED_VERTEX_SIZE`.
+    pub fn from_compressed_slice(vertex : &[u8]) -> ModelVertex {
+        let mut position = [0f32 ; 3];
+        LittleEndian::read_f32_into(&vertex[0..0xC], &mut position);
+
+        // Node indices are stored multiplied by three, and the second weight is implied.
+        let node = |index : i8| if index < 0 { -1 } else { index as i16 / 3 };
+        let weight = LittleEndian::read_i16(&vertex[0x1E..]) as f32 / 32767.0;
+        ModelVertex {
+            position : position,
+            normal : unpack_vector(LittleEndian::read_u32(&vertex[0xC..])),
+            binormal : unpack_vector(LittleEndian::read_u32(&vertex[0x10..])),
+            tangent : unpack_vector(LittleEndian::read_u32(&vertex[0x14..])),
+            texture_coords : [LittleEndian::read_i16(&vertex[0x18..]) as f32 / 32767.0, LittleEndian::read_i16(&vertex[0x1A..]) as f32 / 32767.0],
+            node_indices : [node(vertex[0x1C] as i8), node(vertex[0x1D] as i8)],
+            node_weights : [weight, 1.0 - weight]
+        }
+    }
+
+    /// Write the model vertex to a slice as a compressed vertex. Normals, binormals, and tangents
+    /// are packed into 11:11:10 bit vectors, texture coordinates and the first node weight are
+    /// stored as 16-bit fixed point numbers, and the second node weight is dropped.
+    ///
+    /// Returns `Err` if a node index is greater than 42 or if the texture coordinates are not
+    /// between -1 and 1.
+    ///
+    /// This function will panic if `vertex` is smaller than `MODEL_COMPRESSED_VERTEX_SIZE`.
+    pub fn write_compressed_to_slice(&self, vertex : &mut [u8]) -> Result<(),&'static str> {
+        if self.texture_coords.iter().any(|t| !(*t >= -1.0 && *t <= 1.0)) {
+            return Err("texture coordinates are out of range for compressed vertices");
+        }
+        let mut nodes = [0u8 ; 2];
+        for (n, node) in nodes.iter_mut().enumerate() {
+            *node = match self.node_indices[n] {
+                n if n < 0 => (-3i8) as u8,
+                n if n <= 42 => (n * 3) as u8,
+                _ => return Err("node index is out of range for compressed vertices")
+            };
+        }
+        let total = self.node_weights[0] + self.node_weights[1];
+        let weight = if self.node_indices[1] < 0 || total <= 0.0 { 1.0 } else { self.node_weights[0] / total };
+
+        LittleEndian::write_f32_into(&self.position, &mut vertex[0..0xC]);
+        LittleEndian::write_u32(&mut vertex[0xC..], pack_vector(&self.normal));
+        LittleEndian::write_u32(&mut vertex[0x10..], pack_vector(&self.binormal));
+        LittleEndian::write_u32(&mut vertex[0x14..], pack_vector(&self.tangent));
+        LittleEndian::write_i16(&mut vertex[0x18..], (self.texture_coords[0] * 32767.0).round() as i16);
+        LittleEndian::write_i16(&mut vertex[0x1A..], (self.texture_coords[1] * 32767.0).round() as i16);
+        vertex[0x1C] = nodes[0];
+        vertex[0x1D] = nodes[1];
+        LittleEndian::write_i16(&mut vertex[0x1E..], (weight.max(0.0).min(1.0) * 32767.0).round() as i16);
+        Ok(())
+    }
+}
+
+/// Pack a unit vector into 32 bits, using 11 bits for X and Y and 10 bits for Z.
+pub fn pack_vector(vector : &[f32 ; 3]) -> u32 {
+    let pack = |value : f32, bits : u32| -> u32 {
+        let maximum = ((1 << (bits - 1)) - 1) as f32;
+        let value = if value.is_finite() { value.max(-1.0).min(1.0) } else { 0.0 };
+        ((value * maximum).round() as i32 as u32) & ((1 << bits) - 1)
+    };
+    pack(vector[0], 11) | pack(vector[1], 11) << 11 | pack(vector[2], 10) << 22
+}
+
+/// Unpack a vector packed with `pack_vector`.
+pub fn unpack_vector(packed : u32) -> [f32 ; 3] {
+    let unpack = |value : u32, bits : u32| -> f32 {
+        let maximum = ((1 << (bits - 1)) - 1) as f32;
+        let shift = 32 - bits;
+        (((value << shift) as i32) >> shift) as f32 / maximum
+    };
+    [unpack(packed & 0x7FF, 11), unpack((packed >> 11) & 0x7FF, 11), unpack(packed >> 22, 10)]
+}
+
+/// Get the size of a vertex in a vertex buffer of a type. Anything not compressed is treated as
+/// uncompressed, as that is all PC maps use.
+pub fn model_vertex_size(vertex_type : u32) -> usize {
+    match vertex_type {
+        MODEL_VERTEX_TYPE_COMPRESSED => MODEL_COMPRESSED_VERTEX_SIZE,
+        _ => MODEL_VERTEX_SIZE
+    }
+}
+
+#[derive(Clone)]
+/// A node in a model tag's skeleton.
+pub struct ModelNode {
+    pub name : String,
+    pub next_sibling : Option<usize>,
+    pub first_child : Option<usize>,
+    pub parent : Option<usize>,
+
+    /// This is the translation relative to the parent node.
+    pub translation : [f32 ; 3],
+
+    /// This is the rotation quaternion (i, j, k, w) relative to the parent node.
+    pub rotation : [f32 ; 4]
+}
+
+#[derive(Clone)]
+/// A marker in a model tag.
+pub struct ModelMarker {
+    pub name : String,
+
+    /// This is the region the marker belongs to, or `None` if it applies to all regions.
+    pub region : Option<usize>,
+
+    /// This is the permutation the marker belongs to, or `None` if it applies to all permutations.
+    pub permutation : Option<usize>,
+    pub node : usize,
+
+    /// This is the translation relative to the node.
+    pub translation : [f32 ; 3],
+
+    /// This is the rotation quaternion (i, j, k, w) relative to the node.
+    pub rotation : [f32 ; 4]
+}
+
+#[derive(Clone)]
+/// A permutation of a region in a model tag.
+pub struct ModelPermutation {
+    pub name : String,
+
+    /// These are the geometry indices of each level of detail, indexed by `ModelLOD::index`.
+    pub geometries : [Option<usize> ; 5]
+}
+
+#[derive(Clone)]
+/// A region in a model tag.
+pub struct ModelRegion {
+    pub name : String,
+    pub permutations : Vec<ModelPermutation>
+}
+
+#[derive(Clone)]
+/// A part of a geometry in a model tag with its vertices and indices.
+pub struct ModelPart {
+    /// This is the offset of the part in the tag data.
+    pub offset : usize,
+    pub shader_index : u16,
+    pub vertices : Vec<ModelVertex>,
+
+    /// These are indices of a triangle strip.
+    pub indices : Vec<u16>
+}
+impl ModelPart {
+    /// Convert the part's triangle strip into a triangle list, dropping degenerate triangles.
+    pub fn triangles(&self) -> Vec<[u16 ; 3]> {
+        triangle_strip_to_list(&self.indices)
+    }
+
+    /// Replace the part's triangle strip with a triangle list.
+    pub fn set_triangles(&mut self, triangles : &[[u16 ; 3]]) {
+        self.indices = triangle_list_to_strip(triangles);
+    }
+}
+
+/// Convert a triangle strip into a triangle list, keeping the winding order of each triangle and
+/// dropping degenerate triangles.
+pub fn triangle_strip_to_list(strip : &[u16]) -> Vec<[u16 ; 3]> {
+    let mut triangles = Vec::with_capacity(strip.len().saturating_sub(2));
+    for i in 2..strip.len() {
+        let (a, b, c) = (strip[i - 2], strip[i - 1], strip[i]);
+        if a == b || b == c || a == c {
+            continue;
+        }
+        triangles.push(if i % 2 == 0 { [a, b, c] } else { [a, c, b] });
+    }
+    triangles
+}
+
+/// Convert a triangle list into a triangle strip, joining triangles with degenerate triangles.
+pub fn triangle_list_to_strip(triangles : &[[u16 ; 3]]) -> Vec<u16> {
+    let mut strip : Vec<u16> = Vec::with_capacity(triangles.len() * 6);
+    for triangle in triangles {
+        // The first index of each triangle has to land on an even index to keep its winding order.
+        if let Some(&last) = strip.last() {
+            strip.push(last);
+            strip.push(triangle[0]);
+            if strip.len() % 2 == 1 {
+                strip.push(triangle[0]);
+            }
+        }
+        strip.extend_from_slice(triangle);
+    }
+    strip
+}
+
+#[derive(Clone)]
+/// An exported model file.
+pub struct ModelExport {
+    pub region_name : String,
+    pub permutation_name : String,
+    pub lod : ModelLOD,
+    pub data : String
+}
+
+impl Tag {
+    /// Get the nodes of this model tag.
+    pub fn model_nodes(&self) -> Result<Vec<ModelNode>,&'static str> {
+        let data = self.p_model_data()?;
+        let node_or_none = |index : u16| if index == 0xFFFF { None } else { Some(index as usize) };
+        let mut nodes = Vec::new();
+        for offset in self.reflexive_elements(0xB8, MODEL_NODE_SIZE)? {
+            let node = &data[offset .. offset + MODEL_NODE_SIZE];
+            let mut transform = [0f32 ; 7];
+            LittleEndian::read_f32_into(&node[0x28..0x44], &mut transform);
+            nodes.push(ModelNode {
+                name : string_from_slice(&node[0..0x20])?,
+                next_sibling : node_or_none(LittleEndian::read_u16(&node[0x20..])),
+                first_child : node_or_none(LittleEndian::read_u16(&node[0x22..])),
+                parent : node_or_none(LittleEndian::read_u16(&node[0x24..])),
+                translation : [transform[0], transform[1], transform[2]],
+                rotation : [transform[3], transform[4], transform[5], transform[6]]
+            });
+        }
+        Ok(nodes)
+    }
+
+    /// Get the regions and permutations of this model tag.
+    pub fn model_regions(&self) -> Result<Vec<ModelRegion>,&'static str> {
+        let data = self.p_model_data()?;
+        let mut regions = Vec::new();
+        for region_offset in self.reflexive_elements(0xC4, MODEL_REGION_SIZE)? {
+            let mut permutations = Vec::new();
+            for offset in self.reflexive_elements(region_offset + 0x40, MODEL_PERMUTATION_SIZE)? {
+                let permutation = &data[offset .. offset + MODEL_PERMUTATION_SIZE];
+                let mut geometries = [None ; 5];
+                for (lod, geometry) in geometries.iter_mut().enumerate() {
+                    let index = LittleEndian::read_u16(&permutation[0x40 + lod * 2..]);
+                    if index != 0xFFFF {
+                        *geometry = Some(index as usize);
+                    }
+                }
+                permutations.push(ModelPermutation {
+                    name : string_from_slice(&permutation[0..0x20])?,
+                    geometries : geometries
+                });
+            }
+            regions.push(ModelRegion {
+                name : string_from_slice(&data[region_offset .. region_offset + 0x20])?,
+                permutations : permutations
+            });
+        }
+        Ok(regions)
+    }
+
+    /// Get the markers of this model tag, including both the markers of the model and the markers
+    /// of each region permutation.
+    pub fn model_markers(&self) -> Result<Vec<ModelMarker>,&'static str> {
+        let data = self.p_model_data()?;
+        let mut markers = Vec::new();
+        for marker_offset in self.reflexive_elements(0xAC, 0x40)? {
+            let name = string_from_slice(&data[marker_offset .. marker_offset + 0x20])?;
+            for offset in self.reflexive_elements(marker_offset + 0x34, 0x20)? {
+                let instance = &data[offset .. offset + 0x20];
+                let mut transform = [0f32 ; 7];
+                LittleEndian::read_f32_into(&instance[0x4..0x20], &mut transform);
+                markers.push(ModelMarker {
+                    name : name.clone(),
+                    region : if instance[0] == 0xFF { None } else { Some(instance[0] as usize) },
+                    permutation : if instance[1] == 0xFF { None } else { Some(instance[1] as usize) },
+                    node : instance[2] as usize,
+                    translation : [transform[0], transform[1], transform[2]],
+                    rotation : [transform[3], transform[4], transform[5], transform[6]]
+                });
+            }
+        }
+        for (r, region_offset) in self.reflexive_elements(0xC4, MODEL_REGION_SIZE)?.into_iter().enumerate() {
+            for (p, permutation_offset) in self.reflexive_elements(region_offset + 0x40, MODEL_PERMUTATION_SIZE)?.into_iter().enumerate() {
+                for offset in self.reflexive_elements(permutation_offset + 0x4C, 0x50)? {
+                    let marker = &data[offset .. offset + 0x50];
+                    let mut rotation = [0f32 ; 4];
+                    let mut translation = [0f32 ; 3];
+                    LittleEndian::read_f32_into(&marker[0x24..0x34], &mut rotation);
+                    LittleEndian::read_f32_into(&marker[0x34..0x40], &mut translation);
+                    markers.push(ModelMarker {
+                        name : string_from_slice(&marker[0..0x20])?,
+                        region : Some(r),
+                        permutation : Some(p),
+                        node : LittleEndian::read_u16(&marker[0x20..]) as usize,
+                        translation : translation,
+                        rotation : rotation
+                    });
+                }
+            }
+        }
+        Ok(markers)
+    }
+
+    /// Get the number of geometries in this model tag.
+    pub fn model_geometry_count(&self) -> Result<usize,&'static str> {
+        self.p_model_data()?;
+        Ok(self.reflexive_elements(0xD0, MODEL_GEOMETRY_SIZE)?.len())
+    }
+
+    /// Get the parts of a geometry in this model tag, decoding their vertices and indices from
+    /// the tag's asset data. Compressed vertices are decompressed, and local node indices are
+    /// converted to node indices.
+    ///
+    /// Returns `Err` if the geometry does not exist or if any part is invalid.
+    pub fn model_geometry_parts(&self, geometry : usize) -> Result<Vec<ModelPart>,&'static str> {
+        let data = self.p_model_data()?;
+        let geometry_offset = match self.reflexive_elements(0xD0, MODEL_GEOMETRY_SIZE)?.get(geometry) {
+            Some(n) => *n,
+            None => return Err("geometry index out of bounds")
+        };
+        let asset_data = match self.asset_data.as_ref() {
+            Some(n) => n,
+            None => return Err("model tag has no asset data")
+        };
+        let local_nodes = self.tag_class.0 == MOD2 && LittleEndian::read_u32(&data[0x0..]) & MODEL_FLAG_PARTS_HAVE_LOCAL_NODES != 0;
+        let part_size = self.p_model_part_size();
+
+        let mut parts = Vec::new();
+        for offset in self.reflexive_elements(geometry_offset + 0x24, part_size)? {
+            let part = &data[offset .. offset + part_size];
+            let index_count = LittleEndian::read_u32(&part[0x48..]) as usize + 2;
+            let index_offset = LittleEndian::read_u32(&part[0x4C..]) as usize;
+            let vertex_type = LittleEndian::read_u32(&part[0x54..]);
+            let vertex_count = LittleEndian::read_u32(&part[0x58..]) as usize;
+            let vertex_offset = LittleEndian::read_u32(&part[0x64..]) as usize;
+            let vertex_size = model_vertex_size(vertex_type);
+            if index_offset + index_count * 2 > asset_data.len() {
+                return Err("invalid model index offset/size");
+            }
+            if vertex_offset + vertex_count * vertex_size > asset_data.len() {
+                return Err("invalid model vertex offset/size");
+            }
+
+            let mut indices = vec![0u16 ; index_count];
+            LittleEndian::read_u16_into(&asset_data[index_offset .. index_offset + index_count * 2], &mut indices);
+            if indices.iter().any(|i| *i as usize >= vertex_count) {
+                return Err("model index is out of bounds");
+            }
+            let vertex_data = asset_data[vertex_offset .. vertex_offset + vertex_count * vertex_size].chunks(vertex_size);
+            let mut vertices : Vec<ModelVertex> = if vertex_type == MODEL_VERTEX_TYPE_COMPRESSED {
+                vertex_data.map(ModelVertex::from_compressed_slice).collect()
+            }
+            else {
+                vertex_data.map(ModelVertex::from_slice).collect()
+            };
+
+            if local_nodes {
+                let local_node_count = (part[0x6D] as usize).min(22);
+                let local_node_indices = &part[0x6E .. 0x6E + local_node_count];
+                for vertex in &mut vertices {
+                    for node in &mut vertex.node_indices {
+                        if *node >= 0 {
+                            *node = match local_node_indices.get(*node as usize) {
+                                Some(n) => *n as i16,
+                                None => return Err("invalid local node index on model vertex")
+                            };
+                        }
+                    }
+                }
+            }
+
+            parts.push(ModelPart {
+                offset : offset,
+                shader_index : LittleEndian::read_u16(&part[0x4..]),
+                vertices : vertices,
+                indices : indices
+            });
+        }
+        Ok(parts)
+    }
+
+    /// Replace the vertices and indices of every part of a geometry in this model tag, rebuilding
+    /// the tag's asset data and updating the vertex and index counts and offsets of each part.
+    /// Vertices are written in the format the part already uses, and node indices are converted
+    /// to local node indices if the model uses them.
+    ///
+    /// `parts` must have one part for each part of the geometry, in the same order, such as the
+    /// parts returned by `model_geometry_parts`. Shader indices are updated as well.
+    ///
+    /// Returns `Err` without changing the tag if the parts are invalid.
+    pub fn set_model_geometry_parts(&mut self, geometry : usize, parts : &[ModelPart]) -> Result<(),&'static str> {
+        let mut geometries = self.p_model_geometries()?;
+        match geometries.get_mut(geometry) {
+            Some(n) if n.len() == parts.len() => *n = parts.to_owned(),
+            Some(_) => return Err("part count does not match the geometry"),
+            None => return Err("geometry index out of bounds")
+        }
+        self.p_write_model_geometries(&geometries, None)
+    }
+
+    /// Convert the vertices of every part in this model tag to compressed or uncompressed vertices,
+    /// rebuilding the tag's asset data and updating the vertex type of each part.
+    ///
+    /// Xbox maps use compressed vertices, while PC maps use uncompressed vertices, so a map with
+    /// compressed mod2 vertices cannot be written as a PC cache file.
+    ///
+    /// Returns `Err` without changing the tag if any vertex cannot be converted. Compressed
+    /// vertices can only use the first 43 nodes and texture coordinates between -1 and 1.
+    pub fn set_model_vertices_compressed(&mut self, compressed : bool) -> Result<(),&'static str> {
+        let geometries = self.p_model_geometries()?;
+        self.p_write_model_geometries(&geometries, Some(compressed))
+    }
+
+    /// Convert this model tag to a mode tag (Xbox) or a mod2 tag (PC), changing its class and
+    /// resizing the parts of every geometry. Local node indices are removed when converting to a
+    /// mode tag, as mode parts use node indices directly.
+    ///
+    /// Vertices are not compressed or decompressed, so use `set_model_vertices_compressed` as well
+    /// when moving a model between Xbox and PC maps.
+    ///
+    /// Returns `Err` without changing the tag if any part is invalid.
+    pub fn set_model_xbox(&mut self, xbox : bool) -> Result<(),&'static str> {
+        let geometries = self.p_model_geometries()?;
+        let (tag_class, new_part_size) = if xbox { (MODE, MODEL_XBOX_PART_SIZE) } else { (MOD2, MODEL_PART_SIZE) };
+        if self.tag_class.0 == tag_class {
+            return Ok(());
+        }
+        let old_part_size = self.p_model_part_size();
+
+        let mut model = self.clone();
+        for geometry in 0..geometries.len() {
+            let geometry_offset = model.reflexive_elements(0xD0, MODEL_GEOMETRY_SIZE)?[geometry];
+            let mut parts = Vec::new();
+            for offset in model.reflexive_elements(geometry_offset + 0x24, old_part_size)? {
+                let part_start = parts.len();
+                parts.extend_from_slice(&model.data.as_ref().unwrap()[offset .. offset + old_part_size.min(new_part_size)]);
+                parts.resize(part_start + new_part_size, 0);
+            }
+
+            // The old parts are a different size than the new parts, so they are removed first.
+            model.replace_reflexive(geometry_offset + 0x24, old_part_size, &[]);
+            let geometry_offset = model.reflexive_elements(0xD0, MODEL_GEOMETRY_SIZE)?[geometry];
+            model.replace_reflexive(geometry_offset + 0x24, new_part_size, &parts);
+        }
+        if xbox {
+            let flags = LittleEndian::read_u32(&model.data.as_ref().unwrap()[0x0..]);
+            LittleEndian::write_u32(&mut model.data.as_mut().unwrap()[0x0..], flags & !MODEL_FLAG_PARTS_HAVE_LOCAL_NODES);
+        }
+        model.tag_class.0 = tag_class;
+
+        // Write the vertices again since mod2 parts may need local node indices.
+        model.p_write_model_geometries(&geometries, None)?;
+        *self = model;
+        Ok(())
+    }
+
+    // Get the parts of every geometry in a model tag.
+    fn p_model_geometries(&self) -> Result<Vec<Vec<ModelPart>>,&'static str> {
+        (0..self.model_geometry_count()?).map(|g| self.model_geometry_parts(g)).collect()
+    }
+
+    // Write the parts of every geometry in a model tag, optionally changing whether or not the
+    // vertices are compressed.
+    fn p_write_model_geometries(&mut self, geometries : &[Vec<ModelPart>], compressed : Option<bool>) -> Result<(),&'static str> {
+        let data = self.p_model_data()?;
+        let local_nodes = self.tag_class.0 == MOD2 && LittleEndian::read_u32(&data[0x0..]) & MODEL_FLAG_PARTS_HAVE_LOCAL_NODES != 0;
+        let part_size = self.p_model_part_size();
+        let geometry_offsets = self.reflexive_elements(0xD0, MODEL_GEOMETRY_SIZE)?;
+        if geometry_offsets.len() != geometries.len() {
+            return Err("geometry count does not match the model");
+        }
+
+        // Encode every part, putting its vertices and then its indices in the asset data.
+        let mut asset_data = Vec::new();
+        let mut part_updates = Vec::new();
+        for (geometry_offset, parts) in geometry_offsets.iter().zip(geometries) {
+            let part_offsets = self.reflexive_elements(geometry_offset + 0x24, part_size)?;
+            if part_offsets.len() != parts.len() {
+                return Err("part count does not match the geometry");
+            }
+            for (offset, part) in part_offsets.into_iter().zip(parts) {
+                if part.vertices.len() > 0xFFFF {
+                    return Err("model part has too many vertices");
+                }
+                if part.indices.len() < 3 {
+                    return Err("model part triangle strip has fewer than three indices");
+                }
+                if part.indices.iter().any(|i| *i as usize >= part.vertices.len()) {
+                    return Err("model index is out of bounds");
+                }
+                let vertex_type = match compressed {
+                    Some(true) => MODEL_VERTEX_TYPE_COMPRESSED,
+                    Some(false) => MODEL_VERTEX_TYPE_UNCOMPRESSED,
+                    None => LittleEndian::read_u32(&data[offset + 0x54..])
+                };
+                let vertex_size = model_vertex_size(vertex_type);
+
+                let mut local_node_indices : Vec<u8> = Vec::new();
+                let vertex_offset = asset_data.len();
+                asset_data.resize(vertex_offset + part.vertices.len() * vertex_size, 0);
+                for (vertex, vertex_bytes) in part.vertices.iter().zip(asset_data[vertex_offset..].chunks_mut(vertex_size)) {
+                    let mut vertex = *vertex;
+                    if local_nodes {
+                        for node in &mut vertex.node_indices {
+                            if *node < 0 {
+                                continue;
+                            }
+                            if *node > 0xFF {
+                                return Err("invalid node index on model vertex");
+                            }
+                            *node = match local_node_indices.iter().position(|n| *n as i16 == *node) {
+                                Some(n) => n as i16,
+                                None => {
+                                    if local_node_indices.len() == 22 {
+                                        return Err("model part uses more than 22 nodes");
+                                    }
+                                    local_node_indices.push(*node as u8);
+                                    local_node_indices.len() as i16 - 1
+                                }
+                            };
+                        }
+                    }
+                    if vertex_type == MODEL_VERTEX_TYPE_COMPRESSED {
+                        vertex.write_compressed_to_slice(vertex_bytes)?;
+                    }
+                    else {
+                        vertex.write_to_slice(vertex_bytes);
+                    }
+                }
+
+                let index_offset = asset_data.len();
+                asset_data.resize(index_offset + part.indices.len() * 2, 0);
+                LittleEndian::write_u16_into(&part.indices, &mut asset_data[index_offset..]);
+                part_updates.push((offset, part, vertex_type, vertex_offset, index_offset, local_node_indices));
+            }
+        }
+
+        let tag_data = self.data.as_mut().unwrap();
+        for (offset, part, vertex_type, vertex_offset, index_offset, local_node_indices) in part_updates {
+            LittleEndian::write_u16(&mut tag_data[offset + 0x4..], part.shader_index);
+            LittleEndian::write_u32(&mut tag_data[offset + 0x48..], part.indices.len() as u32 - 2);
+            LittleEndian::write_u32(&mut tag_data[offset + 0x4C..], index_offset as u32);
+            LittleEndian::write_u32(&mut tag_data[offset + 0x50..], index_offset as u32);
+            LittleEndian::write_u32(&mut tag_data[offset + 0x54..], vertex_type);
+            LittleEndian::write_u32(&mut tag_data[offset + 0x58..], part.vertices.len() as u32);
+            LittleEndian::write_u32(&mut tag_data[offset + 0x64..], vertex_offset as u32);
+            if local_nodes {
+                tag_data[offset + 0x6D] = local_node_indices.len() as u8;
+                for (i, node) in tag_data[offset + 0x6E .. offset + 0x6E + 22].iter_mut().enumerate() {
+                    *node = *local_node_indices.get(i).unwrap_or(&0);
+                }
+            }
+        }
+        self.asset_data = Some(asset_data);
+
+        Ok(())
+    }
+
+    /// Export the geometry of a region permutation at a level of detail of this model tag as a
+    /// Wavefront OBJ file. Each part is put in its own group using a material named after its
+    /// shader index, and texture coordinates are scaled by the model's base map scale.
+    ///
+    /// If the permutation does not have geometry for the level of detail, the next most detailed
+    /// geometry is used.
+    pub fn export_model_obj(&self, region : usize, permutation : usize, lod : ModelLOD) -> Result<String,&'static str> {
+        let regions = self.model_regions()?;
+        let (region, permutation) = match regions.get(region).and_then(|r| r.permutations.get(permutation).map(|p| (r, p))) {
+            Some(n) => n,
+            None => return Err("region or permutation index out of bounds")
+        };
+        let geometry = match permutation_geometry(permutation, lod) {
+            Some(n) => n,
+            None => return Err("permutation has no geometry")
+        };
+        let (u_scale, v_scale) = self.p_model_uv_scale();
+
+        let mut obj = String::new();
+        obj += &format!("# {}\n", self.tag_path);
+        obj += &format!("o {}_{}_{}\n", region.name, permutation.name, lod.name());
+        let mut vertex_base = 1;
+        for (p,part) in self.model_geometry_parts(geometry)?.iter().enumerate() {
+            for vertex in &part.vertices {
+                obj += &format!("v {:?} {:?} {:?}\n", vertex.position[0], vertex.position[1], vertex.position[2]);
+            }
+            for vertex in &part.vertices {
+                obj += &format!("vt {:?} {:?}\n", vertex.texture_coords[0] * u_scale, 1.0 - vertex.texture_coords[1] * v_scale);
+            }
+            for vertex in &part.vertices {
+                obj += &format!("vn {:?} {:?} {:?}\n", vertex.normal[0], vertex.normal[1], vertex.normal[2]);
+            }
+            obj += &format!("g part_{}\nusemtl shader_{}\n", p, part.shader_index);
+            for triangle in part.triangles() {
+                let (a, b, c) = (triangle[0] as usize + vertex_base, triangle[1] as usize + vertex_base, triangle[2] as usize + vertex_base);
+                obj += &format!("f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}\n", a, b, c);
+            }
+            vertex_base += part.vertices.len();
+        }
+        Ok(obj)
+    }
+
+    /// Export every region permutation at every level of detail of this model tag as Wavefront OBJ
+    /// files. Levels of detail that share geometry with a more detailed level are skipped.
+    pub fn export_models_obj(&self) -> Result<Vec<ModelExport>,&'static str> {
+        let mut exports = Vec::new();
+        for (r,region) in self.model_regions()?.iter().enumerate() {
+            for (p,permutation) in region.permutations.iter().enumerate() {
+                for lod in ModelLOD::ALL.iter() {
+                    match (permutation.geometries[lod.index()], permutation.geometries.get(lod.index() + 1).cloned()) {
+                        (None, _) => continue,
+                        (Some(a), Some(Some(b))) if a == b => continue,
+                        _ => ()
+                    }
+                    exports.push(ModelExport {
+                        region_name : region.name.clone(),
+                        permutation_name : permutation.name.clone(),
+                        lod : *lod,
+                        data : self.export_model_obj(r, p, *lod)?
+                    });
+                }
+            }
+        }
+        Ok(exports)
+    }
+
+    /// Export this model tag as a glTF 2.0 file at a level of detail.
+    ///
+    /// The nodes are exported as a skeleton with markers as child nodes, and each region
+    /// permutation is exported as a skinned mesh with a primitive for each part. The buffer is
+    /// embedded in the file.
+    pub fn export_model_gltf(&self, lod : ModelLOD) -> Result<String,&'static str> {
+        Ok(self.p_model_gltf(lod)?.as_json())
+    }
+
+    // Build a glTF file of this model tag at a level of detail. The first glTF nodes are the
+    // model's nodes, in order, so animations can be added afterwards.
+    pub(super) fn p_model_gltf(&self, lod : ModelLOD) -> Result<Gltf,&'static str> {
+        let nodes = self.model_nodes()?;
+        let regions = self.model_regions()?;
+        let markers = self.model_markers()?;
+        let (u_scale, v_scale) = self.p_model_uv_scale();
+        let mut gltf = Gltf::new();
+
+        // Nodes come first so their glTF indices match.
+        let world_matrices = node_world_matrices(&nodes)?;
+        let mut children : Vec<Vec<usize>> = vec![Vec::new() ; nodes.len()];
+        for (n,node) in nodes.iter().enumerate() {
+            match node.parent {
+                Some(p) => children[p].push(n),
+                None => gltf.scene_nodes.push(n)
+            }
+        }
+        for (m,marker) in markers.iter().enumerate() {
+            if marker.node >= nodes.len() {
+                return Err("invalid node on model marker");
+            }
+            children[marker.node].push(nodes.len() + m);
+        }
+        for (n,node) in nodes.iter().enumerate() {
+            let children = if children[n].is_empty() { String::new() } else { format!(",\"children\":[{}]", children[n].iter().map(|c| c.to_string()).collect::<Vec<String>>().join(",")) };
+            gltf.nodes.push(format!("{{\"name\":{},\"translation\":{},\"rotation\":{}{}}}", json_string(&node.name), json_floats(&node.translation), json_floats(&normalized_quaternion(&node.rotation)), children));
+        }
+        for marker in &markers {
+            let mut name = format!("#{}", marker.name);
+            if let (Some(r), Some(p)) = (marker.region, marker.permutation) {
+                if let Some((region, permutation)) = regions.get(r).and_then(|region| region.permutations.get(p).map(|permutation| (region, permutation))) {
+                    name = format!("#{} ({} {})", marker.name, region.name, permutation.name);
+                }
+            }
+            gltf.nodes.push(format!("{{\"name\":{},\"translation\":{},\"rotation\":{}}}", json_string(&name), json_floats(&marker.translation), json_floats(&normalized_quaternion(&marker.rotation))));
+        }
+
+        // Every mesh shares the same skin.
+        if !nodes.is_empty() {
+            let mut inverse_bind_matrices = Vec::with_capacity(nodes.len() * 16);
+            for matrix in &world_matrices {
+                inverse_bind_matrices.extend_from_slice(&invert_rigid_matrix(matrix));
+            }
+            let accessor = gltf.add_floats(&inverse_bind_matrices, 16, "MAT4", None, false);
+            gltf.skins.push(format!("{{\"inverseBindMatrices\":{},\"joints\":[{}]}}", accessor, (0..nodes.len()).map(|n| n.to_string()).collect::<Vec<String>>().join(",")));
+        }
+
+        for region in &regions {
+            for permutation in &region.permutations {
+                let geometry = match permutation_geometry(permutation, lod) {
+                    Some(n) => n,
+                    None => continue
+                };
+                let mut primitives = Vec::new();
+                for part in self.model_geometry_parts(geometry)? {
+                    let triangles = part.triangles();
+                    if triangles.is_empty() {
+                        continue;
+                    }
+                    let mut positions = Vec::with_capacity(part.vertices.len() * 3);
+                    let mut normals = Vec::with_capacity(part.vertices.len() * 3);
+                    let mut tangents = Vec::with_capacity(part.vertices.len() * 4);
+                    let mut texture_coords = Vec::with_capacity(part.vertices.len() * 2);
+                    let mut joints = Vec::with_capacity(part.vertices.len() * 4);
+                    let mut weights = Vec::with_capacity(part.vertices.len() * 4);
+                    for vertex in &part.vertices {
+                        positions.extend_from_slice(&vertex.position);
+                        normals.extend_from_slice(&vertex.normal);
+                        tangents.extend_from_slice(&vertex.tangent);
+                        tangents.push(tangent_handedness(vertex));
+                        texture_coords.push(vertex.texture_coords[0] * u_scale);
+                        texture_coords.push(vertex.texture_coords[1] * v_scale);
+
+                        let (node_joints, node_weights) = vertex_skin(vertex, nodes.len())?;
+                        joints.extend_from_slice(&node_joints);
+                        weights.extend_from_slice(&node_weights);
+                    }
+                    let indices : Vec<u16> = triangles.iter().flat_map(|t| t.iter().cloned()).collect();
+
+                    let position = gltf.add_floats(&positions, 3, "VEC3", Some(ARRAY_BUFFER), true);
+                    let normal = gltf.add_floats(&normals, 3, "VEC3", Some(ARRAY_BUFFER), false);
+                    let tangent = gltf.add_floats(&tangents, 4, "VEC4", Some(ARRAY_BUFFER), false);
+                    let texture_coord = gltf.add_floats(&texture_coords, 2, "VEC2", Some(ARRAY_BUFFER), false);
+                    let indices = gltf.add_u16s(&indices, 1, "SCALAR", Some(ELEMENT_ARRAY_BUFFER));
+                    let skin_attributes = if nodes.is_empty() {
+                        String::new()
+                    }
+                    else {
+                        let joint = gltf.add_u16s(&joints, 4, "VEC4", Some(ARRAY_BUFFER));
+                        let weight = gltf.add_floats(&weights, 4, "VEC4", Some(ARRAY_BUFFER), false);
+                        format!(",\"JOINTS_0\":{},\"WEIGHTS_0\":{}", joint, weight)
+                    };
+                    primitives.push(format!("{{\"attributes\":{{\"POSITION\":{},\"NORMAL\":{},\"TANGENT\":{},\"TEXCOORD_0\":{}{}}},\"indices\":{}}}", position, normal, tangent, texture_coord, skin_attributes, indices));
+                }
+                if primitives.is_empty() {
+                    continue;
+                }
+
+                let name = json_string(&format!("{} {} {}", region.name, permutation.name, lod.name()));
+                gltf.meshes.push(format!("{{\"name\":{},\"primitives\":[{}]}}", name, primitives.join(",")));
+                let skin = if nodes.is_empty() { String::new() } else { ",\"skin\":0".to_owned() };
+                gltf.nodes.push(format!("{{\"name\":{},\"mesh\":{}{}}}", name, gltf.meshes.len() - 1, skin));
+                gltf.scene_nodes.push(gltf.nodes.len() - 1);
+            }
+        }
+
+        Ok(gltf)
+    }
+
+    /// Get the default pose of each node of this model tag as a column-major 4x4 matrix that
+    /// transforms from node space to model space.
+    pub fn model_node_world_matrices(&self) -> Result<Vec<[f32 ; 16]>,&'static str> {
+        node_world_matrices(&self.model_nodes()?)
+    }
+
+    // Get the base map U and V scale of a model tag. Zero means no scaling.
+    fn p_model_uv_scale(&self) -> (f32,f32) {
+        let data = self.data.as_ref().unwrap();
+        let scale = |s : f32| if s == 0.0 { 1.0 } else { s };
+        (scale(LittleEndian::read_f32(&data[0x30..])), scale(LittleEndian::read_f32(&data[0x34..])))
+    }
+
+    // Get the size of a geometry part in a mod2 or mode tag.
+    fn p_model_part_size(&self) -> usize {
+        if self.tag_class.0 == MODE { MODEL_XBOX_PART_SIZE } else { MODEL_PART_SIZE }
+    }
+
+    // Get the tag data of a mod2 or mode tag, checking that the header is there.
+    fn p_model_data(&self) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != MOD2 && self.tag_class.0 != MODE {
+            return Err("tag is not a model tag");
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= 0xDC + 0xC => Ok(n),
+            Some(_) => Err("model tag is too small"),
+            None => Err("model tag has no data")
+        }
+    }
+}
+
+// Get the model space matrix of each node by walking up to the root node.
+fn node_world_matrices(nodes : &[ModelNode]) -> Result<Vec<[f32 ; 16]>,&'static str> {
+    let mut world_matrices = Vec::with_capacity(nodes.len());
+    for n in 0..nodes.len() {
+        let mut chain = vec![n];
+        while let Some(parent) = nodes[*chain.last().unwrap()].parent {
+            if parent >= nodes.len() || chain.contains(&parent) {
+                return Err("invalid parent node on model node");
+            }
+            chain.push(parent);
+        }
+        let mut matrix = transform_matrix(&[0.0 ; 3], &[0.0, 0.0, 0.0, 1.0]);
+        for c in chain.iter().rev() {
+            matrix = multiply_matrices(&matrix, &transform_matrix(&nodes[*c].translation, &nodes[*c].rotation));
+        }
+        world_matrices.push(matrix);
+    }
+    Ok(world_matrices)
+}
+
+// Get the geometry of a permutation at a level of detail, falling back to more detailed levels.
+fn permutation_geometry(permutation : &ModelPermutation, lod : ModelLOD) -> Option<usize> {
+    permutation.geometries[lod.index()..].iter().filter_map(|g| *g).next()
+}
+
+// Get the handedness of a vertex's tangent space for glTF, which stores the binormal as a sign.
+fn tangent_handedness(vertex : &ModelVertex) -> f32 {
+    let (n, t, b) = (vertex.normal, vertex.tangent, vertex.binormal);
+    let cross = [n[1] * t[2] - n[2] * t[1], n[2] * t[0] - n[0] * t[2], n[0] * t[1] - n[1] * t[0]];
+    if cross[0] * b[0] + cross[1] * b[1] + cross[2] * b[2] < 0.0 { -1.0 } else { 1.0 }
+}
+
+// Get the glTF joints and weights of a vertex. Weights are normalized so they add up to one.
+fn vertex_skin(vertex : &ModelVertex, node_count : usize) -> Result<([u16 ; 4],[f32 ; 4]),&'static str> {
+    let mut joints = [0u16 ; 4];
+    let mut weights = [0f32 ; 4];
+    for i in 0..2 {
+        let node = vertex.node_indices[i];
+        if node < 0 {
+            continue;
+        }
+        if node as usize >= node_count {
+            return Err("invalid node index on model vertex");
+        }
+        joints[i] = node as u16;
+        weights[i] = vertex.node_weights[i].max(0.0);
+    }
+    let total = weights[0] + weights[1];
+    if total > 0.0 {
+        weights[0] /= total;
+        weights[1] /= total;
+    }
+    else {
+        weights[0] = 1.0;
+    }
+    Ok((joints, weights))
+}
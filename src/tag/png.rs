@@ -0,0 +1,125 @@
+extern crate png;
+use self::png::{BitDepth,ColorType,Decoder,DecodingError,Encoder,Limits,Transformations};
+use std::io::Cursor;
+
+/// Largest decoded image accepted, in bytes.
+const MAXIMUM_DECODED_SIZE : usize = 0x10000000;
+
+/// Encode 32-bit RGBA pixels as a PNG file.
+///
+/// This function will panic if there are not exactly `width * height * 4` bytes of pixels or if either dimension is zero.
+pub fn encode_png(width : usize, height : usize, pixels : &[u8]) -> Vec<u8> {
+    assert!(pixels.len() == width * height * 4, "pixel data does not match the dimensions");
+    let mut png = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut png, width as u32, height as u32);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder.write_header().expect("failed to write png header");
+        writer.write_image_data(pixels).expect("failed to write png data");
+        writer.finish().expect("failed to finish png");
+    }
+    png
+}
+
+fn decoding_error(error : DecodingError) -> &'static str {
+    match error {
+        DecodingError::IoError(_) => "png file is truncated",
+        DecodingError::LimitsExceeded => "png image is too large",
+        _ => "invalid png file"
+    }
+}
+
+/// Decode a PNG file into its width, height, and 32-bit RGBA pixels.
+pub fn decode_png(png : &[u8]) -> Result<(usize,usize,Vec<u8>),&'static str> {
+    let mut decoder = Decoder::new_with_limits(Cursor::new(png), Limits { bytes : MAXIMUM_DECODED_SIZE });
+    decoder.set_transformations(Transformations::EXPAND | Transformations::ALPHA | Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(decoding_error)?;
+    let mut buffer = vec![0u8 ; reader.output_buffer_size().ok_or("png image is too large")?];
+    let info = reader.next_frame(&mut buffer).map_err(decoding_error)?;
+    buffer.truncate(info.buffer_size());
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let pixels = match info.color_type {
+        ColorType::Rgba => buffer,
+        ColorType::Rgb => buffer.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        ColorType::Grayscale => buffer.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        ColorType::Indexed => return Err("invalid png file")
+    };
+    if pixels.len() != width * height * 4 {
+        return Err("invalid png file");
+    }
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An 8x8 RGB PNG made with zlib at its highest level (dynamic Huffman codes), using every
+    // filter type. Pixel (x, y) is ((x * 32 + y * 5) % 256, (y * 32) % 256, (x * y * 7) % 256).
+    const FIXTURE : [u8 ; 195] = [
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x08, 0x08, 0x02, 0x00, 0x00, 0x00, 0x4B, 0x6D, 0x29,
+        0xDC, 0x00, 0x00, 0x00, 0x8A, 0x49, 0x44, 0x41, 0x54, 0x78, 0xDA, 0x75, 0x8D, 0x21, 0x0E, 0xC2,
+        0x40, 0x10, 0x00, 0xA7, 0xC0, 0xA6, 0x09, 0xE2, 0xB2, 0x61, 0x53, 0xD1, 0xA4, 0x21, 0x9B, 0xD4,
+        0xD5, 0x21, 0x71, 0x55, 0xFC, 0x01, 0x87, 0xE5, 0x09, 0x38, 0x1E, 0xC0, 0x07, 0x70, 0xFB, 0x83,
+        0x6A, 0x5C, 0xDF, 0x80, 0xC3, 0xF1, 0x09, 0x04, 0x8E, 0xC3, 0x60, 0x1A, 0x92, 0x71, 0x93, 0xC9,
+        0x00, 0x38, 0xF4, 0x70, 0x80, 0x33, 0x04, 0x8C, 0xF0, 0x84, 0x42, 0x3C, 0x8B, 0x72, 0xCA, 0x2C,
+        0x0B, 0xF1, 0x52, 0x3C, 0x89, 0x57, 0xE2, 0x8D, 0x78, 0x2B, 0xDE, 0x89, 0x6F, 0xE6, 0xCB, 0x1E,
+        0xD3, 0x64, 0xBA, 0x32, 0xAD, 0x4C, 0x6B, 0xD3, 0xC6, 0xD4, 0x4D, 0xDB, 0xC5, 0xB7, 0xA0, 0x14,
+        0x92, 0x50, 0x09, 0xCD, 0x0F, 0xEA, 0x60, 0x1B, 0xED, 0x3E, 0x76, 0xA7, 0x38, 0x5E, 0xE3, 0x72,
+        0x8B, 0xE1, 0x11, 0xF7, 0x77, 0xBC, 0x8A, 0xF5, 0x98, 0xE7, 0xDD, 0x94, 0xBF, 0xF3, 0x0F, 0x10,
+        0xDF, 0x1B, 0x6A, 0xF0, 0x0A, 0xFB, 0x1B, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE,
+        0x42, 0x60, 0x82
+    ];
+
+    #[test]
+    fn decode_fixture() {
+        let (width, height, pixels) = decode_png(&FIXTURE).unwrap();
+        assert_eq!((width, height), (8, 8));
+        for y in 0..8 {
+            for x in 0..8 {
+                let expected = [((x * 32 + y * 5) % 256) as u8, ((y * 32) % 256) as u8, ((x * y * 7) % 256) as u8, 255];
+                assert_eq!(&pixels[(y * 8 + x) * 4 .. (y * 8 + x + 1) * 4], &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn encode_then_decode() {
+        let pixels : Vec<u8> = (0..5 * 3 * 4).map(|n| (n * 37 % 256) as u8).collect();
+        assert_eq!(decode_png(&encode_png(5, 3, &pixels)).unwrap(), (5, 3, pixels));
+    }
+
+    #[test]
+    fn reject_bad_signature() {
+        let mut png = FIXTURE;
+        png[1] = 0x51;
+        assert_eq!(decode_png(&png), Err("invalid png file"));
+    }
+
+    #[test]
+    fn reject_truncated_data() {
+        assert!(decode_png(&FIXTURE[..100]).is_err());
+        assert!(decode_png(&FIXTURE[..4]).is_err());
+        assert!(decode_png(&[]).is_err());
+    }
+
+    #[test]
+    fn reject_corrupt_data() {
+        // Flip a byte inside the compressed IDAT data; the chunk's CRC no longer matches.
+        let mut png = FIXTURE;
+        png[60] ^= 0xFF;
+        assert!(decode_png(&png).is_err());
+    }
+
+    #[test]
+    fn reject_oversized_dimensions() {
+        // Claim a 65535x65535 image with the fixture's 8x8 data.
+        let mut png = FIXTURE;
+        png[16..24].copy_from_slice(&[0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF]);
+        assert!(decode_png(&png).is_err());
+    }
+}
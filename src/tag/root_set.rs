@@ -0,0 +1,115 @@
+use super::{Tag, BITM, SND, USTR, MATG, TAGC, DELA};
+use super::super::map::MapType;
+
+#[derive(Clone)]
+/// A root set determines which tags are always kept when removing dead tags. Every tag
+/// referenced (recursively) by a root is kept as well.
+pub struct RootSet {
+    /// Tags with one of these paths and classes are roots.
+    pub paths : Vec<(String,u32)>,
+
+    /// Tags with one of these primary classes are roots.
+    pub classes : Vec<u32>,
+
+    /// The principal scenario tag is a root.
+    pub principal_tag : bool
+}
+impl RootSet {
+    /// Create an empty root set. Nothing is kept when using it, not even the principal tag.
+    pub fn new() -> RootSet {
+        RootSet {
+            paths : Vec::new(),
+            classes : Vec::new(),
+            principal_tag : false
+        }
+    }
+
+    /// Create a root set containing the principal scenario tag, `globals\globals`, and every tag
+    /// collection.
+    pub fn minimal() -> RootSet {
+        RootSet::new().with_principal_tag().with_path("globals\\globals", MATG).with_class(TAGC)
+    }
+
+    /// Create a root set containing everything the engine loads regardless of the map type. This
+    /// is the root set used by `TagArray::remove_dead_tags`.
+    pub fn essential() -> RootSet {
+        RootSet::minimal()
+            .with_path("ui\\shell\\bitmaps\\background", BITM)
+            .with_path("ui\\shell\\bitmaps\\trouble_brewing", BITM)
+            .with_path("sound\\sfx\\ui\\cursor", SND)
+            .with_path("sound\\sfx\\ui\\forward", SND)
+            .with_path("sound\\sfx\\ui\\back", SND)
+            .with_path("ui\\shell\\strings\\loading", USTR)
+            .with_path("ui\\shell\\main_menu\\mp_map_list", USTR)
+    }
+
+    /// Create a root set for multiplayer maps. This adds the strings used by the multiplayer game
+    /// text, scoreboard, and player names to the essential root set.
+    pub fn multiplayer() -> RootSet {
+        RootSet::essential()
+            .with_path("ui\\multiplayer_game_text", USTR)
+            .with_path("ui\\random_player_names", USTR)
+            .with_path("ui\\default_multiplayer_game_setting_names", USTR)
+    }
+
+    /// Create a root set for singleplayer maps. This adds the saved game strings to the essential
+    /// root set, and the multiplayer map list is not kept.
+    pub fn singleplayer() -> RootSet {
+        let mut root_set = RootSet::essential().with_path("ui\\saved_game_file_strings", USTR);
+        root_set.paths.retain(|p| p.0 != "ui\\shell\\main_menu\\mp_map_list");
+        root_set
+    }
+
+    /// Create a root set for user interface maps. This adds the main menu and the strings the
+    /// menus show for multiplayer games, saved games, and player profiles to the essential root
+    /// set.
+    pub fn user_interface() -> RootSet {
+        RootSet::essential()
+            .with_path("ui\\shell\\main_menu\\main_menu", DELA)
+            .with_path("ui\\multiplayer_game_text", USTR)
+            .with_path("ui\\random_player_names", USTR)
+            .with_path("ui\\default_multiplayer_game_setting_names", USTR)
+            .with_path("ui\\saved_game_file_strings", USTR)
+            .with_path("ui\\shell\\strings\\default_player_profile_names", USTR)
+    }
+
+    /// Create a root set for a type of map. Unknown map types use the essential root set.
+    pub fn from_map_type(map_type : &MapType) -> RootSet {
+        match *map_type {
+            MapType::Multiplayer => RootSet::multiplayer(),
+            MapType::Singleplayer => RootSet::singleplayer(),
+            MapType::UserInterface => RootSet::user_interface(),
+            MapType::Unknown(_) => RootSet::essential()
+        }
+    }
+
+    /// Add a tag path and class to the root set.
+    pub fn with_path(mut self, tag_path : &str, tag_class : u32) -> RootSet {
+        self.paths.push((tag_path.to_owned(), tag_class));
+        self
+    }
+
+    /// Add a tag class to the root set.
+    pub fn with_class(mut self, tag_class : u32) -> RootSet {
+        self.classes.push(tag_class);
+        self
+    }
+
+    /// Add the principal scenario tag to the root set.
+    pub fn with_principal_tag(mut self) -> RootSet {
+        self.principal_tag = true;
+        self
+    }
+
+    /// Determine whether or not a tag is a root.
+    pub fn contains(&self, tag : &Tag, is_principal_tag : bool) -> bool {
+        if self.principal_tag && is_principal_tag {
+            return true;
+        }
+        let class = tag.tag_class.0;
+        if self.classes.contains(&class) {
+            return true;
+        }
+        self.paths.iter().any(|p| p.1 == class && p.0 == tag.tag_path)
+    }
+}
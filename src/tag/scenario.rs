@@ -0,0 +1,601 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, TagArray, read_dependency, write_dependency, SCNR, ITMC};
+
+/// Offset of the player starting locations reflexive in a scnr tag.
+pub const SCENARIO_PLAYER_STARTING_LOCATIONS_OFFSET : usize = 0x354;
+
+/// Size of a player starting location in a scnr tag.
+pub const SCENARIO_PLAYER_STARTING_LOCATION_SIZE : usize = 0x34;
+
+/// Offset of the netgame flags reflexive in a scnr tag.
+pub const SCENARIO_NETGAME_FLAGS_OFFSET : usize = 0x378;
+
+/// Size of a netgame flag in a scnr tag.
+pub const SCENARIO_NETGAME_FLAG_SIZE : usize = 0x94;
+
+/// Offset of the netgame equipment reflexive in a scnr tag.
+pub const SCENARIO_NETGAME_EQUIPMENT_OFFSET : usize = 0x384;
+
+/// Size of a netgame equipment entry in a scnr tag.
+pub const SCENARIO_NETGAME_EQUIPMENT_SIZE : usize = 0x90;
+
+/// Size of an object palette entry in a scnr tag.
+pub const SCENARIO_OBJECT_PALETTE_ENTRY_SIZE : usize = 0x30;
+
+#[derive(PartialEq,Clone,Copy,Debug)]
+/// Types of objects that are placed in a scnr tag. Each type has its own palette of tags and its
+/// own placements which refer to the palette by index.
+pub enum ScenarioObjectType {
+    Scenery,
+    Biped,
+    Vehicle,
+    Equipment,
+    Weapon,
+    Machine,
+    Control,
+    LightFixture,
+    SoundScenery
+}
+impl ScenarioObjectType {
+    /// Every object type.
+    pub const ALL : [ScenarioObjectType ; 9] = [
+        ScenarioObjectType::Scenery,
+        ScenarioObjectType::Biped,
+        ScenarioObjectType::Vehicle,
+        ScenarioObjectType::Equipment,
+        ScenarioObjectType::Weapon,
+        ScenarioObjectType::Machine,
+        ScenarioObjectType::Control,
+        ScenarioObjectType::LightFixture,
+        ScenarioObjectType::SoundScenery
+    ];
+
+    /// Get the tag class used by palette entries of this object type.
+    pub fn tag_class(&self) -> u32 {
+        match *self {
+            ScenarioObjectType::Scenery => 0x7363656E,
+            ScenarioObjectType::Biped => 0x62697064,
+            ScenarioObjectType::Vehicle => 0x76656869,
+            ScenarioObjectType::Equipment => 0x65716970,
+            ScenarioObjectType::Weapon => 0x77656170,
+            ScenarioObjectType::Machine => 0x6D616368,
+            ScenarioObjectType::Control => 0x6374726C,
+            ScenarioObjectType::LightFixture => 0x6C696669,
+            ScenarioObjectType::SoundScenery => 0x73736365
+        }
+    }
+
+    /// Get the offset of the placements reflexive in a scnr tag.
+    pub fn placements_offset(&self) -> usize {
+        match *self {
+            ScenarioObjectType::Scenery => 0x210,
+            ScenarioObjectType::Biped => 0x228,
+            ScenarioObjectType::Vehicle => 0x240,
+            ScenarioObjectType::Equipment => 0x258,
+            ScenarioObjectType::Weapon => 0x270,
+            ScenarioObjectType::Machine => 0x294,
+            ScenarioObjectType::Control => 0x2AC,
+            ScenarioObjectType::LightFixture => 0x2C4,
+            ScenarioObjectType::SoundScenery => 0x2DC
+        }
+    }
+
+    /// Get the offset of the palette reflexive in a scnr tag.
+    pub fn palette_offset(&self) -> usize {
+        self.placements_offset() + 0xC
+    }
+
+    /// Get the size of a placement in a scnr tag.
+    pub fn placement_size(&self) -> usize {
+        match *self {
+            ScenarioObjectType::Scenery => 0x48,
+            ScenarioObjectType::Biped => 0x78,
+            ScenarioObjectType::Vehicle => 0x78,
+            ScenarioObjectType::Equipment => 0x28,
+            ScenarioObjectType::Weapon => 0x5C,
+            ScenarioObjectType::Machine => 0x40,
+            ScenarioObjectType::Control => 0x40,
+            ScenarioObjectType::LightFixture => 0x58,
+            ScenarioObjectType::SoundScenery => 0x28
+        }
+    }
+}
+
+#[derive(Clone,PartialEq,Debug)]
+/// An object placed in a scnr tag.
+pub struct ObjectPlacement {
+    /// This is the index of the object's tag in the palette of its type.
+    pub palette_index : Option<usize>,
+
+    /// This is the index of the object's name in the scenario's object names.
+    pub name_index : Option<usize>,
+    pub position : [f32 ; 3],
+
+    /// This is the rotation as yaw, pitch, and roll in radians.
+    pub rotation : [f32 ; 3],
+
+    /// This is the whole placement as stored in the tag. Fields that are specific to the object
+    /// type are kept from here, while the fields above replace their part of it when written.
+    pub data : Vec<u8>
+}
+impl ObjectPlacement {
+    /// Create a placement of an object type with every type-specific field set to zero.
+    pub fn new(object_type : ScenarioObjectType, palette_index : usize, position : [f32 ; 3], rotation : [f32 ; 3]) -> ObjectPlacement {
+        ObjectPlacement {
+            palette_index : Some(palette_index),
+            name_index : None,
+            position : position,
+            rotation : rotation,
+            data : vec![0u8 ; object_type.placement_size()]
+        }
+    }
+
+    /// Read an object placement from a slice. The whole slice is kept as the placement's data.
+    ///
+    /// This function will panic if `placement` is smaller than 0x20 bytes.
+    pub fn from_slice(placement : &[u8]) -> ObjectPlacement {
+        let index_or_none = |index : i16| if index < 0 { None } else { Some(index as usize) };
+        let mut floats = [0f32 ; 6];
+        LittleEndian::read_f32_into(&placement[0x8..0x20], &mut floats);
+        ObjectPlacement {
+            palette_index : index_or_none(LittleEndian::read_i16(&placement[0x0..])),
+            name_index : index_or_none(LittleEndian::read_i16(&placement[0x2..])),
+            position : [floats[0], floats[1], floats[2]],
+            rotation : [floats[3], floats[4], floats[5]],
+            data : placement.to_owned()
+        }
+    }
+
+    /// Write the object placement to a slice.
+    ///
+    /// This function will panic if `placement` is smaller than the placement's data.
+    pub fn write_to_slice(&self, placement : &mut [u8]) {
+        placement[0..self.data.len()].copy_from_slice(&self.data);
+        let index_or_none = |index : Option<usize>| match index { Some(n) => n as i16, None => -1 };
+        LittleEndian::write_i16(&mut placement[0x0..], index_or_none(self.palette_index));
+        LittleEndian::write_i16(&mut placement[0x2..], index_or_none(self.name_index));
+        LittleEndian::write_f32_into(&self.position, &mut placement[0x8..0x14]);
+        LittleEndian::write_f32_into(&self.rotation, &mut placement[0x14..0x20]);
+    }
+}
+
+#[derive(PartialEq,Clone,Copy,Debug)]
+/// Game types determine which multiplayer games use a spawn point.
+pub enum GameType {
+    None,
+    CaptureTheFlag,
+    Slayer,
+    Oddball,
+    KingOfTheHill,
+    Race,
+    Terminator,
+    Stub,
+
+    /// Every game type.
+    All,
+
+    /// Every game type except capture the flag.
+    AllExceptCaptureTheFlag,
+
+    /// Every game type except race and capture the flag.
+    AllExceptRaceAndCaptureTheFlag,
+
+    /// The game type isn't known.
+    Unknown(u16)
+}
+impl GameType {
+    /// Convert a 16-bit unsigned integer into a GameType.
+    pub fn from_u16(game_type : u16) -> GameType {
+        match game_type {
+            0 => GameType::None,
+            1 => GameType::CaptureTheFlag,
+            2 => GameType::Slayer,
+            3 => GameType::Oddball,
+            4 => GameType::KingOfTheHill,
+            5 => GameType::Race,
+            6 => GameType::Terminator,
+            7 => GameType::Stub,
+            12 => GameType::All,
+            13 => GameType::AllExceptCaptureTheFlag,
+            14 => GameType::AllExceptRaceAndCaptureTheFlag,
+            n => GameType::Unknown(n)
+        }
+    }
+
+    /// Convert a GameType to its equivalent 16-bit integer.
+    pub fn as_u16(&self) -> u16 {
+        match *self {
+            GameType::None => 0,
+            GameType::CaptureTheFlag => 1,
+            GameType::Slayer => 2,
+            GameType::Oddball => 3,
+            GameType::KingOfTheHill => 4,
+            GameType::Race => 5,
+            GameType::Terminator => 6,
+            GameType::Stub => 7,
+            GameType::All => 12,
+            GameType::AllExceptCaptureTheFlag => 13,
+            GameType::AllExceptRaceAndCaptureTheFlag => 14,
+            GameType::Unknown(n) => n
+        }
+    }
+}
+
+#[derive(PartialEq,Clone,Copy,Debug)]
+/// Netgame flag types determine what a netgame flag is used for.
+pub enum NetgameFlagType {
+    CaptureTheFlagFlag,
+    CaptureTheFlagVehicle,
+    OddballBallSpawn,
+    RaceTrack,
+    RaceVehicle,
+    VegasBank,
+    TeleportFrom,
+    TeleportTo,
+    HillFlag,
+
+    /// The flag type isn't known.
+    Unknown(u16)
+}
+impl NetgameFlagType {
+    /// Convert a 16-bit unsigned integer into a NetgameFlagType.
+    pub fn from_u16(flag_type : u16) -> NetgameFlagType {
+        match flag_type {
+            0 => NetgameFlagType::CaptureTheFlagFlag,
+            1 => NetgameFlagType::CaptureTheFlagVehicle,
+            2 => NetgameFlagType::OddballBallSpawn,
+            3 => NetgameFlagType::RaceTrack,
+            4 => NetgameFlagType::RaceVehicle,
+            5 => NetgameFlagType::VegasBank,
+            6 => NetgameFlagType::TeleportFrom,
+            7 => NetgameFlagType::TeleportTo,
+            8 => NetgameFlagType::HillFlag,
+            n => NetgameFlagType::Unknown(n)
+        }
+    }
+
+    /// Convert a NetgameFlagType to its equivalent 16-bit integer.
+    pub fn as_u16(&self) -> u16 {
+        match *self {
+            NetgameFlagType::CaptureTheFlagFlag => 0,
+            NetgameFlagType::CaptureTheFlagVehicle => 1,
+            NetgameFlagType::OddballBallSpawn => 2,
+            NetgameFlagType::RaceTrack => 3,
+            NetgameFlagType::RaceVehicle => 4,
+            NetgameFlagType::VegasBank => 5,
+            NetgameFlagType::TeleportFrom => 6,
+            NetgameFlagType::TeleportTo => 7,
+            NetgameFlagType::HillFlag => 8,
+            NetgameFlagType::Unknown(n) => n
+        }
+    }
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+/// A player spawn point in a scnr tag.
+pub struct PlayerStartingLocation {
+    pub position : [f32 ; 3],
+
+    /// This is the facing angle in radians.
+    pub facing : f32,
+    pub team_index : u16,
+    pub bsp_index : u16,
+
+    /// These are the game types that can use the spawn point.
+    pub game_types : [GameType ; 4]
+}
+impl PlayerStartingLocation {
+    /// Read a player starting location from a slice.
+    ///
+    /// This function will panic if `location` is smaller than
+    /// `SCENARIO_PLAYER_STARTING_LOCATION_SIZE`.
+    pub fn from_slice(location : &[u8]) -> PlayerStartingLocation {
+        let game_type = |o : usize| GameType::from_u16(LittleEndian::read_u16(&location[o..]));
+        PlayerStartingLocation {
+            position : [LittleEndian::read_f32(&location[0x0..]), LittleEndian::read_f32(&location[0x4..]), LittleEndian::read_f32(&location[0x8..])],
+            facing : LittleEndian::read_f32(&location[0xC..]),
+            team_index : LittleEndian::read_u16(&location[0x10..]),
+            bsp_index : LittleEndian::read_u16(&location[0x12..]),
+            game_types : [game_type(0x14), game_type(0x16), game_type(0x18), game_type(0x1A)]
+        }
+    }
+
+    /// Write the player starting location to a slice.
+    ///
+    /// This function will panic if `location` is smaller than
+    /// `SCENARIO_PLAYER_STARTING_LOCATION_SIZE`.
+    pub fn write_to_slice(&self, location : &mut [u8]) {
+        LittleEndian::write_f32_into(&self.position, &mut location[0x0..0xC]);
+        LittleEndian::write_f32(&mut location[0xC..], self.facing);
+        LittleEndian::write_u16(&mut location[0x10..], self.team_index);
+        LittleEndian::write_u16(&mut location[0x12..], self.bsp_index);
+        for (g, game_type) in self.game_types.iter().enumerate() {
+            LittleEndian::write_u16(&mut location[0x14 + g * 2..], game_type.as_u16());
+        }
+    }
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+/// A netgame flag in a scnr tag, such as a CTF flag, race checkpoint, hill marker, or
+/// teleporter.
+pub struct NetgameFlag {
+    pub position : [f32 ; 3],
+
+    /// This is the facing angle in radians.
+    pub facing : f32,
+    pub flag_type : NetgameFlagType,
+
+    /// This is the team index for CTF flags, the checkpoint order for race tracks, the hill index
+    /// for hill flags, and the channel for teleporters.
+    pub usage_id : u16,
+
+    /// This is the tag index of the itmc tag used by vehicle and ball flags, if any.
+    pub weapon_group : Option<usize>
+}
+impl NetgameFlag {
+    /// Read a netgame flag from a slice.
+    ///
+    /// This function will panic if `flag` is smaller than `SCENARIO_NETGAME_FLAG_SIZE`.
+    pub fn from_slice(flag : &[u8]) -> NetgameFlag {
+        NetgameFlag {
+            position : [LittleEndian::read_f32(&flag[0x0..]), LittleEndian::read_f32(&flag[0x4..]), LittleEndian::read_f32(&flag[0x8..])],
+            facing : LittleEndian::read_f32(&flag[0xC..]),
+            flag_type : NetgameFlagType::from_u16(LittleEndian::read_u16(&flag[0x10..])),
+            usage_id : LittleEndian::read_u16(&flag[0x12..]),
+            weapon_group : read_dependency(&flag[0x14..])
+        }
+    }
+
+    /// Write the netgame flag to a slice. The weapon group must be an itmc tag in `tag_array`.
+    ///
+    /// This function will panic if `flag` is smaller than `SCENARIO_NETGAME_FLAG_SIZE`.
+    pub fn write_to_slice(&self, flag : &mut [u8], tag_array : &TagArray) -> Result<(),&'static str> {
+        let tag_class = tag_array.p_dependency_class(self.weapon_group, ITMC)?;
+        LittleEndian::write_f32_into(&self.position, &mut flag[0x0..0xC]);
+        LittleEndian::write_f32(&mut flag[0xC..], self.facing);
+        LittleEndian::write_u16(&mut flag[0x10..], self.flag_type.as_u16());
+        LittleEndian::write_u16(&mut flag[0x12..], self.usage_id);
+        write_dependency(&mut flag[0x14..], tag_class, self.weapon_group);
+        Ok(())
+    }
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+/// A netgame equipment entry in a scnr tag, which spawns items from an item collection in
+/// multiplayer games.
+pub struct NetgameEquipment {
+    /// If true, the item floats in place instead of falling.
+    pub levitate : bool,
+
+    /// These are the game types that spawn the item.
+    pub game_types : [GameType ; 4],
+    pub team_index : u16,
+
+    /// This is the time in seconds before the item respawns. Zero uses the game's default.
+    pub spawn_time : u16,
+    pub position : [f32 ; 3],
+
+    /// This is the facing angle in radians.
+    pub facing : f32,
+
+    /// This is the tag index of the itmc tag, if any.
+    pub item_collection : Option<usize>
+}
+impl NetgameEquipment {
+    /// Read a netgame equipment entry from a slice.
+    ///
+    /// This function will panic if `equipment` is smaller than `SCENARIO_NETGAME_EQUIPMENT_SIZE`.
+    pub fn from_slice(equipment : &[u8]) -> NetgameEquipment {
+        let game_type = |o : usize| GameType::from_u16(LittleEndian::read_u16(&equipment[o..]));
+        NetgameEquipment {
+            levitate : LittleEndian::read_u32(&equipment[0x0..]) & 1 != 0,
+            game_types : [game_type(0x4), game_type(0x6), game_type(0x8), game_type(0xA)],
+            team_index : LittleEndian::read_u16(&equipment[0xC..]),
+            spawn_time : LittleEndian::read_u16(&equipment[0xE..]),
+            position : [LittleEndian::read_f32(&equipment[0x40..]), LittleEndian::read_f32(&equipment[0x44..]), LittleEndian::read_f32(&equipment[0x48..])],
+            facing : LittleEndian::read_f32(&equipment[0x4C..]),
+            item_collection : read_dependency(&equipment[0x50..])
+        }
+    }
+
+    /// Write the netgame equipment entry to a slice. The item collection must be an itmc tag in
+    /// `tag_array`.
+    ///
+    /// This function will panic if `equipment` is smaller than `SCENARIO_NETGAME_EQUIPMENT_SIZE`.
+    pub fn write_to_slice(&self, equipment : &mut [u8], tag_array : &TagArray) -> Result<(),&'static str> {
+        let tag_class = tag_array.p_dependency_class(self.item_collection, ITMC)?;
+        LittleEndian::write_u32(&mut equipment[0x0..], if self.levitate { 1 } else { 0 });
+        for (g, game_type) in self.game_types.iter().enumerate() {
+            LittleEndian::write_u16(&mut equipment[0x4 + g * 2..], game_type.as_u16());
+        }
+        LittleEndian::write_u16(&mut equipment[0xC..], self.team_index);
+        LittleEndian::write_u16(&mut equipment[0xE..], self.spawn_time);
+        LittleEndian::write_f32_into(&self.position, &mut equipment[0x40..0x4C]);
+        LittleEndian::write_f32(&mut equipment[0x4C..], self.facing);
+        write_dependency(&mut equipment[0x50..], tag_class, self.item_collection);
+        Ok(())
+    }
+}
+
+impl Tag {
+    /// Get the player starting locations of this scnr tag.
+    pub fn player_starting_locations(&self) -> Result<Vec<PlayerStartingLocation>,&'static str> {
+        let data = self.p_scenario_data()?;
+        Ok(self.reflexive_elements(SCENARIO_PLAYER_STARTING_LOCATIONS_OFFSET, SCENARIO_PLAYER_STARTING_LOCATION_SIZE)?.into_iter().map(|o| {
+            PlayerStartingLocation::from_slice(&data[o .. o + SCENARIO_PLAYER_STARTING_LOCATION_SIZE])
+        }).collect())
+    }
+
+    /// Replace the player starting locations of this scnr tag. Spawn points can be added, moved,
+    /// and removed by getting the current ones with `player_starting_locations`, changing them,
+    /// and then setting them.
+    pub fn set_player_starting_locations(&mut self, locations : &[PlayerStartingLocation]) -> Result<(),&'static str> {
+        self.p_scenario_data()?;
+        self.reflexive_elements(SCENARIO_PLAYER_STARTING_LOCATIONS_OFFSET, SCENARIO_PLAYER_STARTING_LOCATION_SIZE)?;
+        let mut new_data = vec![0u8 ; locations.len() * SCENARIO_PLAYER_STARTING_LOCATION_SIZE];
+        for (location, slice) in locations.iter().zip(new_data.chunks_mut(SCENARIO_PLAYER_STARTING_LOCATION_SIZE)) {
+            location.write_to_slice(slice);
+        }
+        self.replace_reflexive(SCENARIO_PLAYER_STARTING_LOCATIONS_OFFSET, SCENARIO_PLAYER_STARTING_LOCATION_SIZE, &new_data);
+        Ok(())
+    }
+
+    /// Get the netgame flags of this scnr tag.
+    pub fn netgame_flags(&self) -> Result<Vec<NetgameFlag>,&'static str> {
+        let data = self.p_scenario_data()?;
+        Ok(self.reflexive_elements(SCENARIO_NETGAME_FLAGS_OFFSET, SCENARIO_NETGAME_FLAG_SIZE)?.into_iter().map(|o| {
+            NetgameFlag::from_slice(&data[o .. o + SCENARIO_NETGAME_FLAG_SIZE])
+        }).collect())
+    }
+
+    /// Get the netgame equipment of this scnr tag.
+    pub fn netgame_equipment(&self) -> Result<Vec<NetgameEquipment>,&'static str> {
+        let data = self.p_scenario_data()?;
+        Ok(self.reflexive_elements(SCENARIO_NETGAME_EQUIPMENT_OFFSET, SCENARIO_NETGAME_EQUIPMENT_SIZE)?.into_iter().map(|o| {
+            NetgameEquipment::from_slice(&data[o .. o + SCENARIO_NETGAME_EQUIPMENT_SIZE])
+        }).collect())
+    }
+
+    /// Get the tag indices of the palette of an object type in this scnr tag.
+    pub fn object_palette(&self, object_type : ScenarioObjectType) -> Result<Vec<Option<usize>>,&'static str> {
+        let data = self.p_scenario_data()?;
+        Ok(self.reflexive_elements(object_type.palette_offset(), SCENARIO_OBJECT_PALETTE_ENTRY_SIZE)?.into_iter().map(|o| read_dependency(&data[o..])).collect())
+    }
+
+    /// Remove every palette entry of an object type in this scnr tag that no placement uses,
+    /// renumbering the placements' palette indices. The number of removed entries is returned.
+    pub fn compact_object_palette(&mut self, object_type : ScenarioObjectType) -> Result<usize,&'static str> {
+        let palette_data = self.p_object_palette_data(object_type)?;
+        let mut placements = self.object_placements(object_type)?;
+        let palette_count = palette_data.len() / SCENARIO_OBJECT_PALETTE_ENTRY_SIZE;
+
+        let mut new_indices = vec![None ; palette_count];
+        let mut new_palette_data = Vec::new();
+        for p in 0..palette_count {
+            if placements.iter().any(|placement| placement.palette_index == Some(p)) {
+                new_indices[p] = Some(new_palette_data.len() / SCENARIO_OBJECT_PALETTE_ENTRY_SIZE);
+                new_palette_data.extend_from_slice(&palette_data[p * SCENARIO_OBJECT_PALETTE_ENTRY_SIZE .. (p + 1) * SCENARIO_OBJECT_PALETTE_ENTRY_SIZE]);
+            }
+        }
+        for placement in &mut placements {
+            placement.palette_index = match placement.palette_index {
+                Some(n) if n < palette_count => new_indices[n],
+                _ => None
+            };
+        }
+
+        self.replace_reflexive(object_type.palette_offset(), SCENARIO_OBJECT_PALETTE_ENTRY_SIZE, &new_palette_data);
+        self.set_object_placements(object_type, &placements)?;
+        Ok(palette_count - new_palette_data.len() / SCENARIO_OBJECT_PALETTE_ENTRY_SIZE)
+    }
+
+    /// Get the placements of an object type in this scnr tag.
+    pub fn object_placements(&self, object_type : ScenarioObjectType) -> Result<Vec<ObjectPlacement>,&'static str> {
+        let data = self.p_scenario_data()?;
+        let size = object_type.placement_size();
+        Ok(self.reflexive_elements(object_type.placements_offset(), size)?.into_iter().map(|o| ObjectPlacement::from_slice(&data[o .. o + size])).collect())
+    }
+
+    /// Replace the placements of an object type in this scnr tag. Objects can be placed, moved,
+    /// and deleted by getting the current placements with `object_placements`, changing them,
+    /// and then setting them.
+    ///
+    /// Returns `Err` without changing the tag if a placement uses a palette index that does not
+    /// exist or if its data is not the size of a placement of the object type.
+    pub fn set_object_placements(&mut self, object_type : ScenarioObjectType, placements : &[ObjectPlacement]) -> Result<(),&'static str> {
+        let palette_count = self.object_palette(object_type)?.len();
+        self.reflexive_elements(object_type.placements_offset(), object_type.placement_size())?;
+        let size = object_type.placement_size();
+        let mut new_data = vec![0u8 ; placements.len() * size];
+        for (placement, slice) in placements.iter().zip(new_data.chunks_mut(size)) {
+            if placement.data.len() != size {
+                return Err("placement data is the wrong size for the object type");
+            }
+            if placement.palette_index.map_or(false, |p| p >= palette_count) {
+                return Err("placement uses an invalid palette index");
+            }
+            placement.write_to_slice(slice);
+        }
+        self.replace_reflexive(object_type.placements_offset(), size, &new_data);
+        Ok(())
+    }
+
+    // Get a copy of the palette entries of an object type in a scnr tag.
+    fn p_object_palette_data(&self, object_type : ScenarioObjectType) -> Result<Vec<u8>,&'static str> {
+        let data = self.p_scenario_data()?;
+        Ok(self.reflexive_elements(object_type.palette_offset(), SCENARIO_OBJECT_PALETTE_ENTRY_SIZE)?.into_iter().flat_map(|o| data[o .. o + SCENARIO_OBJECT_PALETTE_ENTRY_SIZE].to_owned()).collect())
+    }
+
+    // Get the tag data of a scnr tag, checking that the header is there.
+    fn p_scenario_data(&self) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != SCNR {
+            return Err("tag is not a scenario tag");
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= 0x5B0 => Ok(n),
+            Some(_) => Err("scnr tag is too small"),
+            None => Err("scnr tag has no data")
+        }
+    }
+}
+
+impl TagArray {
+    /// Replace the netgame flags of a scnr tag. Flags can be added, moved, and removed by getting
+    /// the current ones with `Tag::netgame_flags`, changing them, and then setting them.
+    ///
+    /// Returns `Err` without changing the tag if a weapon group is not an itmc tag in this tag
+    /// array.
+    pub fn set_netgame_flags(&mut self, scenario : usize, flags : &[NetgameFlag]) -> Result<(),&'static str> {
+        self.p_scenario_tag(scenario)?.reflexive_elements(SCENARIO_NETGAME_FLAGS_OFFSET, SCENARIO_NETGAME_FLAG_SIZE)?;
+        let mut new_data = vec![0u8 ; flags.len() * SCENARIO_NETGAME_FLAG_SIZE];
+        for (flag, slice) in flags.iter().zip(new_data.chunks_mut(SCENARIO_NETGAME_FLAG_SIZE)) {
+            flag.write_to_slice(slice, self)?;
+        }
+        self.tags_mut()[scenario].replace_reflexive(SCENARIO_NETGAME_FLAGS_OFFSET, SCENARIO_NETGAME_FLAG_SIZE, &new_data);
+        Ok(())
+    }
+
+    /// Replace the netgame equipment of a scnr tag.
+    ///
+    /// Item collection tags for new entries can be found or imported from another map with
+    /// `find_or_insert_recursive`. Returns `Err` without changing the tag if an item collection
+    /// is not an itmc tag in this tag array.
+    pub fn set_netgame_equipment(&mut self, scenario : usize, equipment : &[NetgameEquipment]) -> Result<(),&'static str> {
+        self.p_scenario_tag(scenario)?.reflexive_elements(SCENARIO_NETGAME_EQUIPMENT_OFFSET, SCENARIO_NETGAME_EQUIPMENT_SIZE)?;
+        let mut new_data = vec![0u8 ; equipment.len() * SCENARIO_NETGAME_EQUIPMENT_SIZE];
+        for (entry, slice) in equipment.iter().zip(new_data.chunks_mut(SCENARIO_NETGAME_EQUIPMENT_SIZE)) {
+            entry.write_to_slice(slice, self)?;
+        }
+        self.tags_mut()[scenario].replace_reflexive(SCENARIO_NETGAME_EQUIPMENT_OFFSET, SCENARIO_NETGAME_EQUIPMENT_SIZE, &new_data);
+        Ok(())
+    }
+
+    /// Add a tag to the palette of an object type in a scnr tag, returning its palette index. If
+    /// the tag is already in the palette, the existing index is returned.
+    ///
+    /// The tag must be of the object type's class. Tags can be found or imported from another map
+    /// with `find_or_insert_recursive`.
+    pub fn add_object_palette_entry(&mut self, scenario : usize, object_type : ScenarioObjectType, tag_index : usize) -> Result<usize,&'static str> {
+        let palette = self.p_scenario_tag(scenario)?.object_palette(object_type)?;
+        if let Some(n) = palette.iter().position(|p| *p == Some(tag_index)) {
+            return Ok(n);
+        }
+        let mut entry = [0u8 ; SCENARIO_OBJECT_PALETTE_ENTRY_SIZE];
+        write_dependency(&mut entry, self.p_dependency_class(Some(tag_index), object_type.tag_class())?, Some(tag_index));
+        let tag = &mut self.tags_mut()[scenario];
+        let mut new_data = tag.p_object_palette_data(object_type)?;
+        new_data.extend_from_slice(&entry);
+        tag.replace_reflexive(object_type.palette_offset(), SCENARIO_OBJECT_PALETTE_ENTRY_SIZE, &new_data);
+        Ok(palette.len())
+    }
+
+    // Get a scnr tag, checking that it exists and has data.
+    fn p_scenario_tag(&self, scenario : usize) -> Result<&Tag,&'static str> {
+        match self.tags().get(scenario) {
+            Some(n) => n.p_scenario_data().map(|_| n),
+            None => Err("scenario tag does not exist")
+        }
+    }
+}
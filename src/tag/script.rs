@@ -0,0 +1,431 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, TagArray, TagReference, TagReferenceType};
+
+/// Offset of the script syntax data in a scnr tag.
+pub const SCRIPT_SYNTAX_DATA_OFFSET : usize = 0x474;
+
+/// Offset of the script string data in a scnr tag.
+pub const SCRIPT_STRING_DATA_OFFSET : usize = 0x488;
+
+/// Offset of the scripts reflexive in a scnr tag.
+pub const SCRIPTS_OFFSET : usize = 0x49C;
+
+/// Offset of the globals reflexive in a scnr tag.
+pub const GLOBALS_OFFSET : usize = 0x4A8;
+
+/// Size of the script syntax data header.
+pub const SCRIPT_NODE_TABLE_HEADER_SIZE : usize = 0x38;
+
+/// Size of a script syntax node.
+pub const SCRIPT_NODE_SIZE : usize = 0x14;
+
+/// Script nodes with this flag are values rather than function calls.
+pub const SCRIPT_NODE_FLAG_PRIMITIVE : u16 = 1;
+
+/// Script nodes with this flag reference a script.
+pub const SCRIPT_NODE_FLAG_SCRIPT_INDEX : u16 = 2;
+
+/// Script nodes with this flag reference a global.
+pub const SCRIPT_NODE_FLAG_GLOBAL : u16 = 4;
+
+/// Get the tag class used by a script value type, if the value type refers to a tag.
+///
+/// Object definitions may be any object class, so `obje` is returned for those.
+pub fn script_value_type_tag_class(value_type : u16) -> Option<u32> {
+    match value_type {
+        // sound
+        24 => Some(0x736E6421),
+        // effect
+        25 => Some(0x65666665),
+        // damage
+        26 => Some(0x6A707421),
+        // looping_sound
+        27 => Some(0x6C736E64),
+        // animation_graph
+        28 => Some(0x616E7472),
+        // actor_variant
+        29 => Some(0x61637476),
+        // damage_effect
+        30 => Some(0x6A707421),
+        // object_definition
+        31 => Some(0x6F626A65),
+        _ => None
+    }
+}
+
+impl Tag {
+    /// Get the offset and size of a tag data block (such as the script syntax data) in this tag.
+    ///
+    /// Returns `None` if there is no data or if it is outside of the tag data.
+    pub fn data_block_offset(&self, offset : usize) -> Option<(usize,usize)> {
+        let data = match self.data.as_ref() {
+            Some(n) => n,
+            None => return None
+        };
+        if offset + 0x14 > data.len() {
+            return None;
+        }
+        let size = LittleEndian::read_u32(&data[offset..]) as usize;
+        if size == 0 {
+            return None;
+        }
+        let block_offset = match self.offset_from_memory_address(LittleEndian::read_u32(&data[offset + 0xC..])) {
+            Some(n) => n,
+            None => return None
+        };
+        if block_offset + size > data.len() {
+            return None;
+        }
+        Some((block_offset,size))
+    }
+
+    /// Find the script syntax nodes in a scnr tag, returning their offsets in the tag data.
+    ///
+    /// Returns an empty vector if this is not a scenario tag or if it has no script syntax data.
+    pub fn script_node_offsets(&self) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        if self.tag_class.0 != 0x73636E72 || self.memory_address.is_none() {
+            return offsets;
+        }
+        let (syntax_offset, syntax_size) = match self.data_block_offset(SCRIPT_SYNTAX_DATA_OFFSET) {
+            Some(n) => n,
+            None => return offsets
+        };
+        if syntax_size < SCRIPT_NODE_TABLE_HEADER_SIZE {
+            return offsets;
+        }
+        let data = self.data.as_ref().unwrap();
+        let header = &data[syntax_offset .. syntax_offset + SCRIPT_NODE_TABLE_HEADER_SIZE];
+        let element_size = LittleEndian::read_u16(&header[0x22..]) as usize;
+        let count = LittleEndian::read_u16(&header[0x2E..]) as usize;
+        if element_size != SCRIPT_NODE_SIZE || SCRIPT_NODE_TABLE_HEADER_SIZE + count * SCRIPT_NODE_SIZE > syntax_size {
+            return offsets;
+        }
+        for i in 0..count {
+            offsets.push(syntax_offset + SCRIPT_NODE_TABLE_HEADER_SIZE + i * SCRIPT_NODE_SIZE);
+        }
+        offsets
+    }
+
+    /// Find the tag references in a scnr tag's script syntax data. Only primitive nodes with a tag
+    /// value type (such as sounds, effects, and object definitions) are included.
+    ///
+    /// This function will panic if there is no memory address or data used by the tag.
+    pub fn script_references(&self, tag_array : &TagArray) -> Vec<TagReference> {
+        let mut references = Vec::new();
+        let data = self.data.as_ref().unwrap();
+        let tags = tag_array.tags();
+        for offset in self.script_node_offsets() {
+            let node = &data[offset .. offset + SCRIPT_NODE_SIZE];
+
+            // Unused nodes have a salt of zero.
+            if LittleEndian::read_u16(&node[0x0..]) == 0 {
+                continue;
+            }
+            let flags = LittleEndian::read_u16(&node[0x6..]);
+            if flags & SCRIPT_NODE_FLAG_PRIMITIVE == 0 || flags & SCRIPT_NODE_FLAG_GLOBAL != 0 {
+                continue;
+            }
+            if script_value_type_tag_class(LittleEndian::read_u16(&node[0x4..])).is_none() {
+                continue;
+            }
+            let identity = LittleEndian::read_u32(&node[0x10..]);
+            if identity == 0xFFFFFFFF {
+                continue;
+            }
+            let tag_index = identity as usize & 0xFFFF;
+            assert!(tag_index < tags.len(), "invalid script tag reference");
+            references.push(TagReference {
+                tag_index : tag_index,
+                offset : offset + 0x10,
+                tag_class : tags[tag_index].tag_class.0,
+                reference_type : TagReferenceType::TagID
+            });
+        }
+        references
+    }
+}
+
+/// Names of the script value types, indexed by value type.
+pub const SCRIPT_VALUE_TYPE_NAMES : [&str ; 49] = [
+    "unparsed", "special_form", "function_name", "passthrough", "void", "boolean", "real", "short",
+    "long", "string", "script", "trigger_volume", "cutscene_flag", "cutscene_camera_point",
+    "cutscene_title", "cutscene_recording", "device_group", "ai", "ai_command_list",
+    "starting_profile", "conversation", "navpoint", "hud_message", "object_list", "sound", "effect",
+    "damage", "looping_sound", "animation_graph", "actor_variant", "damage_effect",
+    "object_definition", "game_difficulty", "team", "ai_default_state", "actor_type", "hud_corner",
+    "object", "unit", "vehicle", "weapon", "device", "scenery", "object_name", "unit_name",
+    "vehicle_name", "weapon_name", "device_name", "scenery_name"
+];
+
+/// Names of the script types, indexed by script type.
+pub const SCRIPT_TYPE_NAMES : [&str ; 5] = ["startup", "dormant", "continuous", "static", "stub"];
+
+/// Size of a script in the scripts reflexive.
+pub const SCRIPT_SIZE : usize = 0x5C;
+
+/// Size of a global in the globals reflexive.
+pub const GLOBAL_SIZE : usize = 0x5C;
+
+#[derive(Clone)]
+/// Script syntax nodes make up the expressions of scripts and globals.
+pub struct ScriptNode {
+    /// This is used with the index of the node to make a node ID. It is zero for unused nodes.
+    pub salt : u16,
+
+    /// For function calls, this is the index of the function or script being called.
+    pub index_union : u16,
+
+    /// This is the value type of the node.
+    pub value_type : u16,
+
+    /// These are the flags of the node, such as `SCRIPT_NODE_FLAG_PRIMITIVE`.
+    pub flags : u16,
+
+    /// This is the node ID of the next node in the expression, or 0xFFFFFFFF if there is none.
+    pub next_node : u32,
+
+    /// This is the offset of the node's source text in the script string data.
+    pub string_offset : u32,
+
+    /// For function calls, this is the node ID of the first node of the call. For primitives, this
+    /// is the value.
+    pub data : u32
+}
+impl ScriptNode {
+    /// Parse a script node from a slice.
+    pub fn from_slice(node : &[u8]) -> ScriptNode {
+        ScriptNode {
+            salt : LittleEndian::read_u16(&node[0x0..]),
+            index_union : LittleEndian::read_u16(&node[0x2..]),
+            value_type : LittleEndian::read_u16(&node[0x4..]),
+            flags : LittleEndian::read_u16(&node[0x6..]),
+            next_node : LittleEndian::read_u32(&node[0x8..]),
+            string_offset : LittleEndian::read_u32(&node[0xC..]),
+            data : LittleEndian::read_u32(&node[0x10..])
+        }
+    }
+
+    /// Write the script node into a slice.
+    pub fn write_to_slice(&self, node : &mut [u8]) {
+        LittleEndian::write_u16(&mut node[0x0..], self.salt);
+        LittleEndian::write_u16(&mut node[0x2..], self.index_union);
+        LittleEndian::write_u16(&mut node[0x4..], self.value_type);
+        LittleEndian::write_u16(&mut node[0x6..], self.flags);
+        LittleEndian::write_u32(&mut node[0x8..], self.next_node);
+        LittleEndian::write_u32(&mut node[0xC..], self.string_offset);
+        LittleEndian::write_u32(&mut node[0x10..], self.data);
+    }
+}
+
+impl Tag {
+    /// Get the script syntax nodes of a scnr tag.
+    pub fn script_nodes(&self) -> Vec<ScriptNode> {
+        let data = match self.data.as_ref() {
+            Some(n) => n,
+            None => return Vec::new()
+        };
+        self.script_node_offsets().iter().map(|o| ScriptNode::from_slice(&data[*o .. *o + SCRIPT_NODE_SIZE])).collect()
+    }
+
+    /// Decompile the scripts and globals of a scnr tag into HaloScript source.
+    ///
+    /// Function and global names as well as literals are taken from the script string data. Tag
+    /// values are resolved to tag paths using the tag array.
+    pub fn decompile_scripts(&self, tag_array : &TagArray) -> Result<String,&'static str> {
+        if self.tag_class.0 != 0x73636E72 {
+            return Err("tag is not a scenario tag");
+        }
+        let data = match self.data.as_ref() {
+            Some(n) => n,
+            None => return Err("scenario tag has no data")
+        };
+        if data.len() < GLOBALS_OFFSET + 0xC || self.memory_address.is_none() {
+            return Err("scenario tag is too small");
+        }
+
+        let decompiler = ScriptDecompiler {
+            nodes : self.script_nodes(),
+            strings : match self.data_block_offset(SCRIPT_STRING_DATA_OFFSET) {
+                Some((offset,size)) => &data[offset .. offset + size],
+                None => &[]
+            },
+            tag_array : tag_array
+        };
+
+        let mut source = String::new();
+
+        for global in self.p_script_reflexive(GLOBALS_OFFSET, GLOBAL_SIZE)? {
+            let name = super::super::string_from_slice(&global[0x0..0x20])?;
+            let value_type = LittleEndian::read_u16(&global[0x20..]) as usize;
+            let type_name = match SCRIPT_VALUE_TYPE_NAMES.get(value_type) {
+                Some(n) => n,
+                None => return Err("invalid global type")
+            };
+            let value = decompiler.expression(LittleEndian::read_u32(&global[0x28..]), 0)?;
+            source.push_str(&format!("(global {} {} {})\n", type_name, name, value));
+        }
+
+        for script in self.p_script_reflexive(SCRIPTS_OFFSET, SCRIPT_SIZE)? {
+            if !source.is_empty() {
+                source.push('\n');
+            }
+            let name = super::super::string_from_slice(&script[0x0..0x20])?;
+            let script_type = LittleEndian::read_u16(&script[0x20..]) as usize;
+            let script_type_name = match SCRIPT_TYPE_NAMES.get(script_type) {
+                Some(n) => n,
+                None => return Err("invalid script type")
+            };
+            source.push_str("(script ");
+            source.push_str(script_type_name);
+            // Static scripts and stubs have return types.
+            if script_type >= 3 {
+                match SCRIPT_VALUE_TYPE_NAMES.get(LittleEndian::read_u16(&script[0x22..]) as usize) {
+                    Some(n) => {
+                        source.push(' ');
+                        source.push_str(n);
+                    },
+                    None => return Err("invalid script return type")
+                }
+            }
+            source.push(' ');
+            source.push_str(&name);
+
+            // The root expression is usually a begin call, so its contents are the script body.
+            let root = LittleEndian::read_u32(&script[0x24..]);
+            let body = match decompiler.function_call(root)? {
+                Some((ref function, ref arguments)) if function == "begin" => arguments.to_owned(),
+                _ => vec![root]
+            };
+            for expression in body {
+                source.push_str("\n    ");
+                source.push_str(&decompiler.expression(expression, 1)?);
+            }
+            source.push_str(")\n");
+        }
+
+        Ok(source)
+    }
+
+    // Get the elements of a scnr reflexive used by scripts.
+    fn p_script_reflexive(&self, offset : usize, size : usize) -> Result<Vec<&[u8]>,&'static str> {
+        let data = self.data.as_ref().unwrap();
+        let count = LittleEndian::read_u32(&data[offset..]) as usize;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let elements_offset = match self.offset_from_memory_address(LittleEndian::read_u32(&data[offset + 4..])) {
+            Some(n) => n,
+            None => return Err("invalid script reflexive")
+        };
+        if elements_offset + count * size > data.len() {
+            return Err("invalid script reflexive")
+        }
+        Ok((0..count).map(|i| &data[elements_offset + i * size .. elements_offset + (i + 1) * size]).collect())
+    }
+}
+
+// Converts script syntax nodes back into source.
+struct ScriptDecompiler<'a> {
+    nodes : Vec<ScriptNode>,
+    strings : &'a [u8],
+    tag_array : &'a TagArray
+}
+impl<'a> ScriptDecompiler<'a> {
+    fn node(&self, node_id : u32) -> Result<&ScriptNode,&'static str> {
+        match self.nodes.get(node_id as usize & 0xFFFF) {
+            Some(n) => Ok(n),
+            None => Err("invalid script node")
+        }
+    }
+
+    fn string(&self, offset : u32) -> Result<String,&'static str> {
+        let offset = offset as usize;
+        if offset >= self.strings.len() {
+            return Err("invalid script string offset");
+        }
+        super::super::string_from_slice(&self.strings[offset..])
+    }
+
+    // If the node is a function call, get the function name and the node IDs of the arguments.
+    fn function_call(&self, node_id : u32) -> Result<Option<(String,Vec<u32>)>,&'static str> {
+        let node = self.node(node_id)?;
+        if node.flags & SCRIPT_NODE_FLAG_PRIMITIVE != 0 {
+            return Ok(None);
+        }
+        let function_node = self.node(node.data)?;
+        let name = self.string(function_node.string_offset)?;
+        let mut arguments = Vec::new();
+        let mut next = function_node.next_node;
+        while next != 0xFFFFFFFF {
+            if arguments.len() > self.nodes.len() {
+                return Err("script node loop");
+            }
+            arguments.push(next);
+            next = self.node(next)?.next_node;
+        }
+        Ok(Some((name,arguments)))
+    }
+
+    fn expression(&self, node_id : u32, depth : usize) -> Result<String,&'static str> {
+        if depth > 256 {
+            return Err("script expression is too deep");
+        }
+        match self.function_call(node_id)? {
+            Some((name, arguments)) => {
+                let mut arguments_source = Vec::new();
+                for a in arguments {
+                    arguments_source.push(self.expression(a, depth + 1)?);
+                }
+                let one_line = format!("({}{}{})", name, if arguments_source.is_empty() { "" } else { " " }, arguments_source.join(" "));
+                if one_line.len() + depth * 4 <= 100 && !one_line.contains('\n') {
+                    return Ok(one_line);
+                }
+                let indent = "    ".repeat(depth + 1);
+                let mut source = format!("({}", name);
+                for a in arguments_source {
+                    source.push('\n');
+                    source.push_str(&indent);
+                    source.push_str(&a);
+                }
+                source.push(')');
+                Ok(source)
+            },
+            None => self.primitive(self.node(node_id)?)
+        }
+    }
+
+    fn primitive(&self, node : &ScriptNode) -> Result<String,&'static str> {
+        if node.flags & SCRIPT_NODE_FLAG_GLOBAL != 0 {
+            return self.string(node.string_offset);
+        }
+        match node.value_type {
+            // boolean
+            5 => Ok(if node.data & 0xFF != 0 { "true" } else { "false" }.to_owned()),
+            // real
+            6 => Ok(format!("{}", f32::from_bits(node.data))),
+            // short
+            7 => Ok(format!("{}", node.data as u16 as i16)),
+            // long
+            8 => Ok(format!("{}", node.data as i32)),
+            // string
+            9 => Ok(format!("\"{}\"", self.string(node.string_offset)?)),
+            n => {
+                if script_value_type_tag_class(n).is_some() {
+                    if node.data == 0xFFFFFFFF {
+                        return Ok("none".to_owned());
+                    }
+                    match self.tag_array.tags().get(node.data as usize & 0xFFFF) {
+                        Some(t) => Ok(format!("\"{}\"", t.tag_path)),
+                        None => Err("invalid script tag reference")
+                    }
+                }
+                else {
+                    self.string(node.string_offset)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,757 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use std::collections::HashMap;
+
+use super::{Tag, TagArray, ScriptNode};
+use super::script::*;
+use super::script_definitions::{SCRIPT_FUNCTION_TABLE, SCRIPT_GLOBAL_TABLE};
+use super::super::encode_latin1_string;
+
+const SCRIPT_TYPE_VOID : u16 = 4;
+const SCRIPT_TYPE_PASSTHROUGH : u16 = 3;
+const SCRIPT_TYPE_FUNCTION_NAME : u16 = 2;
+const SCRIPT_TYPE_BOOLEAN : u16 = 5;
+const SCRIPT_TYPE_REAL : u16 = 6;
+const SCRIPT_TYPE_SHORT : u16 = 7;
+const SCRIPT_TYPE_LONG : u16 = 8;
+const SCRIPT_TYPE_STRING : u16 = 9;
+const SCRIPT_TYPE_SCRIPT : u16 = 10;
+const SCRIPT_TYPE_GAME_DIFFICULTY : u16 = 32;
+const SCRIPT_TYPE_OBJECT : u16 = 37;
+const SCRIPT_TYPE_OBJECT_LIST : u16 = 23;
+const SCRIPT_TYPE_OBJECT_NAME : u16 = 43;
+
+/// The maximum number of script syntax nodes a scenario can have.
+pub const MAXIMUM_SCRIPT_NODES : usize = 19001;
+
+#[derive(Clone)]
+/// Script functions are functions provided by the engine that scripts can call.
+pub struct ScriptFunction {
+    /// This is the name of the function.
+    pub name : String,
+
+    /// This is the index of the function in the engine's function table.
+    pub index : u16,
+
+    /// This is the value type returned by the function. Passthrough functions return whatever
+    /// type is expected of them.
+    pub return_type : u16,
+
+    /// These are the value types of the parameters.
+    pub parameters : Vec<u16>,
+
+    /// This is how many of the parameters must be given. Any after these are optional.
+    pub minimum_parameters : usize,
+
+    /// If true, the last parameter can be repeated any number of times.
+    pub variadic : bool
+}
+
+#[derive(Clone)]
+/// Engine globals are globals provided by the engine that scripts can use.
+pub struct ScriptEngineGlobal {
+    /// This is the name of the global.
+    pub name : String,
+
+    /// This is the index of the global in the engine's global table.
+    pub index : u16,
+
+    /// This is the value type of the global.
+    pub value_type : u16
+}
+
+#[derive(Clone)]
+/// A script environment contains the engine's functions and globals used when compiling scripts.
+pub struct ScriptEnvironment {
+    /// These are the functions that can be called.
+    pub functions : Vec<ScriptFunction>,
+
+    /// These are the engine globals that can be used.
+    pub globals : Vec<ScriptEngineGlobal>,
+
+    /// These are literals whose values cannot be determined from the text alone, such as AI
+    /// encounters and trigger volumes. Each is a value type, the text, and the value.
+    pub literals : Vec<(u16,String,u32)>
+}
+impl ScriptEnvironment {
+    /// Create a script environment with the engine's functions and engine globals, indexed by
+    /// their position in the engine's tables.
+    ///
+    /// Use `from_scenario` to take the indices a map's scripts were compiled with, or add other
+    /// functions and globals with `with_function` and `with_global`.
+    pub fn new() -> ScriptEnvironment {
+        let value_type = |name : &str| script_value_type_from_name(name).unwrap();
+        ScriptEnvironment {
+            functions : SCRIPT_FUNCTION_TABLE.iter().enumerate().map(|(i, &(name, return_type, parameters, minimum_parameters, variadic))| ScriptFunction {
+                name : name.to_owned(),
+                index : i as u16,
+                return_type : value_type(return_type),
+                parameters : parameters.iter().map(|p| value_type(p)).collect(),
+                minimum_parameters : minimum_parameters,
+                variadic : variadic
+            }).collect(),
+            globals : SCRIPT_GLOBAL_TABLE.iter().enumerate().map(|(i, &(name, global_type))| ScriptEngineGlobal {
+                name : name.to_owned(),
+                index : i as u16,
+                value_type : value_type(global_type)
+            }).collect(),
+            literals : Vec::new()
+        }
+    }
+
+    /// Create a script environment with no functions, globals, or literals.
+    pub fn empty() -> ScriptEnvironment {
+        ScriptEnvironment {
+            functions : Vec::new(),
+            globals : Vec::new(),
+            literals : Vec::new()
+        }
+    }
+
+    /// Create a script environment from the engine's functions and engine globals and the
+    /// compiled scripts of a scnr tag.
+    ///
+    /// Functions and engine globals keep their types, but use the index from the scripts if it
+    /// differs. Literals used by the scripts, such as AI encounters, are added so they can be
+    /// compiled again.
+    pub fn from_scenario(tag : &Tag) -> ScriptEnvironment {
+        let mut environment = ScriptEnvironment::new();
+        let data = match tag.data.as_ref() {
+            Some(n) => n,
+            None => return environment
+        };
+        let strings = match tag.data_block_offset(SCRIPT_STRING_DATA_OFFSET) {
+            Some((offset,size)) => &data[offset .. offset + size],
+            None => return environment
+        };
+        let string = |offset : u32| -> Option<String> {
+            if offset as usize >= strings.len() {
+                return None;
+            }
+            super::super::string_from_slice(&strings[offset as usize..]).ok()
+        };
+        let nodes = tag.script_nodes();
+        let node = |id : u32| nodes.get(id as usize & 0xFFFF);
+
+        for n in &nodes {
+            if n.salt == 0 {
+                continue;
+            }
+            if n.flags & SCRIPT_NODE_FLAG_PRIMITIVE != 0 {
+                let text = match string(n.string_offset) {
+                    Some(t) => t,
+                    None => continue
+                };
+                if n.flags & SCRIPT_NODE_FLAG_GLOBAL != 0 {
+                    if n.data & 0x8000 != 0 {
+                        if let Some(g) = environment.globals.iter_mut().find(|g| g.name == text) {
+                            g.index = (n.data & 0x7FFF) as u16;
+                        }
+                    }
+                }
+                else if n.value_type != SCRIPT_TYPE_FUNCTION_NAME && !environment.literals.iter().any(|l| l.0 == n.value_type && l.1 == text) {
+                    environment.literals.push((n.value_type, text, n.data));
+                }
+                continue;
+            }
+            if n.flags & SCRIPT_NODE_FLAG_SCRIPT_INDEX != 0 {
+                continue;
+            }
+
+            let name = match node(n.data).and_then(|f| string(f.string_offset)) {
+                Some(t) => t,
+                None => continue
+            };
+            if let Some(f) = environment.functions.iter_mut().find(|f| f.name == name) {
+                f.index = n.index_union;
+            }
+        }
+
+        environment
+    }
+
+    /// Add a function to the environment, replacing any function with the same name.
+    pub fn with_function(mut self, function : ScriptFunction) -> ScriptEnvironment {
+        self.functions.retain(|f| f.name != function.name);
+        self.functions.push(function);
+        self
+    }
+
+    /// Add an engine global to the environment, replacing any global with the same name.
+    pub fn with_global(mut self, global : ScriptEngineGlobal) -> ScriptEnvironment {
+        self.globals.retain(|g| g.name != global.name);
+        self.globals.push(global);
+        self
+    }
+
+    /// Find a function by name.
+    pub fn find_function(&self, name : &str) -> Option<&ScriptFunction> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    /// Find an engine global by name.
+    pub fn find_global(&self, name : &str) -> Option<&ScriptEngineGlobal> {
+        self.globals.iter().find(|g| g.name == name)
+    }
+}
+
+/// Get the script value type with a name.
+pub fn script_value_type_from_name(name : &str) -> Option<u16> {
+    SCRIPT_VALUE_TYPE_NAMES.iter().position(|n| *n == name).map(|n| n as u16)
+}
+
+/// Determine whether or not a value of one script value type can be used where another type is
+/// expected.
+pub fn script_value_type_converts(from : u16, to : u16) -> bool {
+    let is_number = |t : u16| t == SCRIPT_TYPE_REAL || t == SCRIPT_TYPE_SHORT || t == SCRIPT_TYPE_LONG;
+    let is_object = |t : u16| t >= SCRIPT_TYPE_OBJECT && t < SCRIPT_TYPE_OBJECT_NAME;
+    let is_object_name = |t : u16| t >= SCRIPT_TYPE_OBJECT_NAME && t <= 48;
+    from == to
+        || to == SCRIPT_TYPE_VOID
+        || to == SCRIPT_TYPE_PASSTHROUGH
+        || from == SCRIPT_TYPE_PASSTHROUGH
+        || (is_number(from) && is_number(to))
+        || (is_object(from) && to == SCRIPT_TYPE_OBJECT)
+        || (is_object_name(from) && (to == SCRIPT_TYPE_OBJECT_NAME || to == SCRIPT_TYPE_OBJECT || to == SCRIPT_TYPE_OBJECT_LIST))
+        || (is_object(from) && to == SCRIPT_TYPE_OBJECT_LIST)
+        || (is_object_name(from) && is_object(to) && from - SCRIPT_TYPE_OBJECT_NAME == to - SCRIPT_TYPE_OBJECT)
+}
+
+// A parsed HaloScript expression.
+#[derive(Clone)]
+enum Expression {
+    // Text and whether or not it was quoted.
+    Atom(String,bool),
+    List(Vec<Expression>)
+}
+
+// Parse HaloScript source into expressions.
+fn parse_script_source(source : &str) -> Result<Vec<Expression>,&'static str> {
+    let chars : Vec<char> = source.chars().collect();
+    let mut stack : Vec<Vec<Expression>> = vec![Vec::new()];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ';' => {
+                // Block comments look like ;* this *;
+                if i + 1 < chars.len() && chars[i + 1] == '*' {
+                    i += 2;
+                    while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == ';') {
+                        i += 1;
+                    }
+                    i += 2;
+                }
+                else {
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                }
+                continue;
+            },
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                if stack.len() < 2 {
+                    return Err("unexpected closing parenthesis");
+                }
+                let list = stack.pop().unwrap();
+                stack.last_mut().unwrap().push(Expression::List(list));
+            },
+            '"' => {
+                let start = i + 1;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err("unterminated string");
+                }
+                stack.last_mut().unwrap().push(Expression::Atom(chars[start..i].iter().collect(), true));
+            },
+            n if n.is_whitespace() => (),
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' && chars[i] != ';' && chars[i] != '"' {
+                    i += 1;
+                }
+                stack.last_mut().unwrap().push(Expression::Atom(chars[start..i].iter().collect(), false));
+                continue;
+            }
+        }
+        i += 1;
+    }
+    if stack.len() != 1 {
+        return Err("missing closing parenthesis");
+    }
+    Ok(stack.pop().unwrap())
+}
+
+// Compiles expressions into script syntax nodes.
+struct ScriptCompiler<'a> {
+    environment : &'a ScriptEnvironment,
+    tag_array : &'a TagArray,
+
+    // Name, script type, return type
+    scripts : Vec<(String,u16,u16)>,
+
+    // Name, value type
+    globals : Vec<(String,u16)>,
+
+    nodes : Vec<ScriptNode>,
+    strings : Vec<u8>,
+    string_offsets : HashMap<String,u32>
+}
+impl<'a> ScriptCompiler<'a> {
+    fn string(&mut self, string : &str) -> Result<u32,&'static str> {
+        match self.string_offsets.get(string) {
+            Some(n) => return Ok(*n),
+            None => ()
+        }
+        let offset = self.strings.len() as u32;
+        self.strings.append(&mut encode_latin1_string(string)?);
+        self.strings.push(0);
+        self.string_offsets.insert(string.to_owned(), offset);
+        Ok(offset)
+    }
+
+    fn add_node(&mut self, value_type : u16, flags : u16, index_union : u16, string_offset : u32, data : u32) -> Result<u32,&'static str> {
+        let index = self.nodes.len();
+        if index >= MAXIMUM_SCRIPT_NODES {
+            return Err("too many script nodes");
+        }
+        let salt = script_node_salt(index);
+        self.nodes.push(ScriptNode {
+            salt : salt,
+            index_union : index_union,
+            value_type : value_type,
+            flags : flags,
+            next_node : 0xFFFFFFFF,
+            string_offset : string_offset,
+            data : data
+        });
+        Ok(((salt as u32) << 16) | index as u32)
+    }
+
+    // Link nodes together so each node's next node is the node after it.
+    fn link(&mut self, ids : &[u32]) {
+        for i in 1..ids.len() {
+            self.nodes[ids[i - 1] as usize & 0xFFFF].next_node = ids[i];
+        }
+    }
+
+    // Add a function call using the already-compiled argument nodes.
+    fn call(&mut self, name : &str, index : u16, flags : u16, value_type : u16, arguments : &[u32]) -> Result<u32,&'static str> {
+        let name_offset = self.string(name)?;
+        let function_name = self.add_node(SCRIPT_TYPE_FUNCTION_NAME, SCRIPT_NODE_FLAG_PRIMITIVE, index, name_offset, 0)?;
+        let mut ids = vec![function_name];
+        ids.extend_from_slice(arguments);
+        self.link(&ids);
+        self.add_node(value_type, flags, index, name_offset, function_name)
+    }
+
+    fn compile(&mut self, expression : &Expression, expected : u16) -> Result<(u32,u16),&'static str> {
+        match *expression {
+            Expression::Atom(ref text, quoted) => self.compile_atom(text, quoted, expected),
+            Expression::List(ref list) => self.compile_list(list, expected)
+        }
+    }
+
+    fn compile_list(&mut self, list : &[Expression], expected : u16) -> Result<(u32,u16),&'static str> {
+        let name = match list.first() {
+            Some(&Expression::Atom(ref n, false)) => n.to_owned(),
+            Some(_) => return Err("expected a function name"),
+            None => return Err("empty expression")
+        };
+        let arguments = &list[1..];
+
+        // cond is equivalent to a chain of if calls.
+        if name == "cond" {
+            let mut chain : Option<Expression> = None;
+            for clause in arguments.iter().rev() {
+                let clause = match *clause {
+                    Expression::List(ref n) if n.len() >= 2 => n,
+                    _ => return Err("invalid cond clause")
+                };
+                let mut body = vec![Expression::Atom("begin".to_owned(), false)];
+                body.extend_from_slice(&clause[1..]);
+                let mut call = vec![Expression::Atom("if".to_owned(), false), clause[0].to_owned(), Expression::List(body)];
+                match chain {
+                    Some(n) => call.push(n),
+                    None => ()
+                }
+                chain = Some(Expression::List(call));
+            }
+            return match chain {
+                Some(n) => self.compile(&n, expected),
+                None => Err("cond requires at least one clause")
+            };
+        }
+
+        // Calling a script
+        match self.scripts.iter().position(|s| s.0 == name) {
+            Some(n) => {
+                if !arguments.is_empty() {
+                    return Err("scripts do not take arguments");
+                }
+                let return_type = self.scripts[n].2;
+                if !script_value_type_converts(return_type, expected) {
+                    return Err("script return type mismatch");
+                }
+                let id = self.call(&name, n as u16, SCRIPT_NODE_FLAG_SCRIPT_INDEX, return_type, &[])?;
+                return Ok((id, return_type));
+            },
+            None => ()
+        }
+
+        let function = match self.environment.find_function(&name) {
+            Some(n) => n.to_owned(),
+            None => return Err("unknown function")
+        };
+
+        let mut ids = Vec::new();
+        let mut value_type = function.return_type;
+
+        match &name as &str {
+            "begin" | "begin_random" => {
+                if arguments.is_empty() {
+                    return Err("begin requires at least one expression");
+                }
+                for i in 0..arguments.len() {
+                    let last = i + 1 == arguments.len();
+                    let (id,t) = self.compile(&arguments[i], if last { expected } else { SCRIPT_TYPE_VOID })?;
+                    if last {
+                        value_type = t;
+                    }
+                    ids.push(id);
+                }
+            },
+            "if" => {
+                if arguments.len() < 2 || arguments.len() > 3 {
+                    return Err("if requires two or three arguments");
+                }
+                ids.push(self.compile(&arguments[0], SCRIPT_TYPE_BOOLEAN)?.0);
+                for a in &arguments[1..] {
+                    let (id,t) = self.compile(a, expected)?;
+                    value_type = t;
+                    ids.push(id);
+                }
+            },
+            "set" => {
+                if arguments.len() != 2 {
+                    return Err("set requires two arguments");
+                }
+                let (global,t) = match arguments[0] {
+                    Expression::Atom(ref n, false) => self.compile_global(n)?,
+                    _ => return Err("set requires a global")
+                };
+                ids.push(global);
+                ids.push(self.compile(&arguments[1], t)?.0);
+                value_type = t;
+            },
+            _ => {
+                if arguments.len() < function.minimum_parameters || (!function.variadic && arguments.len() > function.parameters.len()) {
+                    return Err("wrong number of arguments");
+                }
+                for i in 0..arguments.len() {
+                    let parameter = match function.parameters.get(i) {
+                        Some(n) => *n,
+                        None => match function.parameters.last() {
+                            Some(n) => *n,
+                            None => SCRIPT_TYPE_PASSTHROUGH
+                        }
+                    };
+                    let (id,_) = self.compile(&arguments[i], parameter)?;
+                    ids.push(id);
+                }
+            }
+        }
+
+        if value_type == SCRIPT_TYPE_PASSTHROUGH {
+            value_type = expected;
+        }
+        if !script_value_type_converts(value_type, expected) {
+            return Err("function return type mismatch");
+        }
+        let node_type = if expected == SCRIPT_TYPE_VOID || expected == SCRIPT_TYPE_PASSTHROUGH { value_type } else { expected };
+        let id = self.call(&name, function.index, 0, node_type, &ids)?;
+        Ok((id, value_type))
+    }
+
+    fn compile_global(&mut self, name : &str) -> Result<(u32,u16),&'static str> {
+        let (index, value_type) = match self.globals.iter().position(|g| g.0 == name) {
+            Some(n) => (n as u32, self.globals[n].1),
+            None => match self.environment.find_global(name) {
+                Some(g) => (g.index as u32 | 0x8000, g.value_type),
+                None => return Err("unknown global")
+            }
+        };
+        let name_offset = self.string(name)?;
+        let id = self.add_node(value_type, SCRIPT_NODE_FLAG_PRIMITIVE | SCRIPT_NODE_FLAG_GLOBAL, value_type, name_offset, index)?;
+        Ok((id, value_type))
+    }
+
+    fn compile_atom(&mut self, text : &str, quoted : bool, expected : u16) -> Result<(u32,u16),&'static str> {
+        if !quoted && (self.globals.iter().any(|g| g.0 == text) || self.environment.find_global(text).is_some()) {
+            let (id, value_type) = self.compile_global(text)?;
+            if !script_value_type_converts(value_type, expected) {
+                return Err("global type mismatch");
+            }
+            return Ok((id, value_type));
+        }
+
+        let value_type = if expected == SCRIPT_TYPE_VOID || expected == SCRIPT_TYPE_PASSTHROUGH {
+            if quoted {
+                SCRIPT_TYPE_STRING
+            }
+            else if text == "true" || text == "false" {
+                SCRIPT_TYPE_BOOLEAN
+            }
+            else if text.parse::<i16>().is_ok() {
+                SCRIPT_TYPE_SHORT
+            }
+            else if text.parse::<f32>().is_ok() {
+                SCRIPT_TYPE_REAL
+            }
+            else {
+                return Err("could not determine the type of a value")
+            }
+        }
+        else {
+            expected
+        };
+
+        let text_offset = self.string(text)?;
+        let data = self.literal(value_type, text, text_offset)?;
+        let id = self.add_node(value_type, SCRIPT_NODE_FLAG_PRIMITIVE, value_type, text_offset, data)?;
+        Ok((id, value_type))
+    }
+
+    fn literal(&self, value_type : u16, text : &str, text_offset : u32) -> Result<u32,&'static str> {
+        let is_tag = script_value_type_tag_class(value_type);
+        if text == "none" && (is_tag.is_some() || value_type >= SCRIPT_TYPE_OBJECT) {
+            return Ok(0xFFFFFFFF);
+        }
+        match value_type {
+            SCRIPT_TYPE_BOOLEAN => match text {
+                "true" | "on" | "1" => Ok(1),
+                "false" | "off" | "0" => Ok(0),
+                _ => Err("invalid boolean")
+            },
+            SCRIPT_TYPE_REAL => match text.parse::<f32>() {
+                Ok(n) => Ok(n.to_bits()),
+                Err(_) => Err("invalid real")
+            },
+            SCRIPT_TYPE_SHORT => match text.parse::<i16>() {
+                Ok(n) => Ok(n as u16 as u32),
+                Err(_) => Err("invalid short")
+            },
+            SCRIPT_TYPE_LONG => match text.parse::<i32>() {
+                Ok(n) => Ok(n as u32),
+                Err(_) => Err("invalid long")
+            },
+            SCRIPT_TYPE_STRING => Ok(text_offset),
+            SCRIPT_TYPE_SCRIPT => match self.scripts.iter().position(|s| s.0 == text) {
+                Some(n) => Ok(n as u32),
+                None => Err("unknown script")
+            },
+            SCRIPT_TYPE_GAME_DIFFICULTY => match ["easy", "normal", "hard", "impossible"].iter().position(|d| *d == text) {
+                Some(n) => Ok(n as u32),
+                None => Err("invalid game difficulty")
+            },
+            _ => {
+                match is_tag {
+                    Some(class) => {
+                        for (i,t) in self.tag_array.tags().iter().enumerate() {
+                            if t.tag_path != text {
+                                continue;
+                            }
+                            if t.tag_class.0 == class || t.tag_class.1 == class || t.tag_class.2 == class {
+                                return Ok(super::tag_index_to_tag_id(i));
+                            }
+                        }
+                        Err("script references a missing tag")
+                    },
+                    None => match self.environment.literals.iter().find(|l| l.0 == value_type && l.1 == text) {
+                        Some(n) => Ok(n.2),
+                        None => Err("unknown value")
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Get the salt of a script node.
+fn script_node_salt(index : usize) -> u16 {
+    ((0xE373 + index) & 0xFFFF) as u16 | 0x8000
+}
+
+// Write a fixed-length name into a script or global.
+fn write_script_name(destination : &mut [u8], name : &str) -> Result<(),&'static str> {
+    let name = encode_latin1_string(name)?;
+    if name.len() > 0x1F {
+        return Err("script and global names must not exceed 31 characters");
+    }
+    destination[..name.len()].copy_from_slice(&name);
+    Ok(())
+}
+
+impl Tag {
+    /// Compile HaloScript source into this scnr tag, replacing its scripts, globals, script syntax
+    /// data, and script string data.
+    ///
+    /// Function calls and engine globals are checked against `environment`. Tag values are
+    /// resolved using `tag_array`.
+    ///
+    /// If the source cannot be compiled, `Err` is returned without any changes to the tag.
+    pub fn compile_scripts(&mut self, source : &str, tag_array : &TagArray, environment : &ScriptEnvironment) -> Result<(),&'static str> {
+        if self.tag_class.0 != 0x73636E72 {
+            return Err("tag is not a scenario tag");
+        }
+        match self.data.as_ref() {
+            Some(n) => if n.len() < GLOBALS_OFFSET + 0xC {
+                return Err("scenario tag is too small");
+            },
+            None => return Err("scenario tag has no data")
+        }
+        if self.memory_address.is_none() {
+            return Err("scenario tag has no memory address");
+        }
+
+        let expressions = parse_script_source(source)?;
+
+        let mut compiler = ScriptCompiler {
+            environment : environment,
+            tag_array : tag_array,
+            scripts : Vec::new(),
+            globals : Vec::new(),
+            nodes : Vec::new(),
+            strings : Vec::new(),
+            string_offsets : HashMap::new()
+        };
+
+        // First pass: Find every script and global so they can be used before they are defined.
+        for expression in &expressions {
+            let list = match *expression {
+                Expression::List(ref n) => n,
+                _ => return Err("expected a script or global")
+            };
+            let atom = |i : usize| -> Result<&str,&'static str> {
+                match list.get(i) {
+                    Some(&Expression::Atom(ref n, false)) => Ok(n),
+                    _ => Err("expected a name or type")
+                }
+            };
+            let value_type = |i : usize| -> Result<u16,&'static str> {
+                match script_value_type_from_name(atom(i)?) {
+                    Some(n) => Ok(n),
+                    None => Err("unknown value type")
+                }
+            };
+            match atom(0)? {
+                "global" => {
+                    if list.len() != 4 {
+                        return Err("globals require a type, a name, and a value");
+                    }
+                    let name = atom(2)?.to_owned();
+                    if compiler.globals.iter().any(|g| g.0 == name) {
+                        return Err("duplicate global");
+                    }
+                    compiler.globals.push((name, value_type(1)?));
+                },
+                "script" => {
+                    let script_type = match SCRIPT_TYPE_NAMES.iter().position(|n| Ok(*n) == atom(1)) {
+                        Some(n) => n as u16,
+                        None => return Err("unknown script type")
+                    };
+                    let (return_type, name) = if script_type >= 3 {
+                        (value_type(2)?, atom(3)?)
+                    }
+                    else {
+                        (SCRIPT_TYPE_VOID, atom(2)?)
+                    };
+                    if compiler.scripts.iter().any(|s| s.0 == name) {
+                        return Err("duplicate script");
+                    }
+                    compiler.scripts.push((name.to_owned(), script_type, return_type));
+                },
+                _ => return Err("expected a script or global")
+            }
+        }
+
+        // Second pass: Compile everything.
+        let mut globals = Vec::new();
+        let mut scripts = Vec::new();
+        for expression in &expressions {
+            let list = match *expression {
+                Expression::List(ref n) => n,
+                _ => unreachable!()
+            };
+            let is_global = match list[0] {
+                Expression::Atom(ref n, _) => n == "global",
+                _ => unreachable!()
+            };
+            if is_global {
+                let index = globals.len() / GLOBAL_SIZE;
+                let (name, value_type) = compiler.globals[index].to_owned();
+                let (root, _) = compiler.compile(&list[3], value_type)?;
+                let mut global = [0u8 ; GLOBAL_SIZE];
+                write_script_name(&mut global[0x0..0x20], &name)?;
+                LittleEndian::write_u16(&mut global[0x20..], value_type);
+                LittleEndian::write_u32(&mut global[0x28..], root);
+                globals.extend_from_slice(&global);
+            }
+            else {
+                let index = scripts.len() / SCRIPT_SIZE;
+                let (name, script_type, return_type) = compiler.scripts[index].to_owned();
+                let body_start = if script_type >= 3 { 4 } else { 3 };
+                if list.len() <= body_start {
+                    return Err("scripts require at least one expression");
+                }
+                let mut body = vec![Expression::Atom("begin".to_owned(), false)];
+                body.extend_from_slice(&list[body_start..]);
+                let (root, _) = compiler.compile(&Expression::List(body), return_type)?;
+                let mut script = [0u8 ; SCRIPT_SIZE];
+                write_script_name(&mut script[0x0..0x20], &name)?;
+                LittleEndian::write_u16(&mut script[0x20..], script_type);
+                LittleEndian::write_u16(&mut script[0x22..], return_type);
+                LittleEndian::write_u32(&mut script[0x24..], root);
+                scripts.extend_from_slice(&script);
+            }
+        }
+
+        // Build the syntax data. The header of the current syntax data is kept if there is one, as
+        // only its node counts and next salt change.
+        let node_count = compiler.nodes.len();
+        let mut syntax_data = match self.data_block_offset(SCRIPT_SYNTAX_DATA_OFFSET) {
+            Some((offset,size)) if size >= SCRIPT_NODE_TABLE_HEADER_SIZE => self.data.as_ref().unwrap()[offset .. offset + SCRIPT_NODE_TABLE_HEADER_SIZE].to_owned(),
+            _ => {
+                let mut header = vec![0u8 ; SCRIPT_NODE_TABLE_HEADER_SIZE];
+                write_script_name(&mut header[0x0..0x20], "script node")?;
+                LittleEndian::write_u16(&mut header[0x20..], MAXIMUM_SCRIPT_NODES as u16);
+                LittleEndian::write_u16(&mut header[0x22..], SCRIPT_NODE_SIZE as u16);
+                header[0x24] = 1;
+                // "d@t@"
+                LittleEndian::write_u32(&mut header[0x28..], 0x64407440);
+                header
+            }
+        };
+        syntax_data.resize(SCRIPT_NODE_TABLE_HEADER_SIZE + node_count * SCRIPT_NODE_SIZE, 0);
+        LittleEndian::write_u16(&mut syntax_data[0x2E..], node_count as u16);
+        LittleEndian::write_u16(&mut syntax_data[0x30..], node_count as u16);
+        LittleEndian::write_u16(&mut syntax_data[0x32..], script_node_salt(node_count));
+        for i in 0..node_count {
+            let offset = SCRIPT_NODE_TABLE_HEADER_SIZE + i * SCRIPT_NODE_SIZE;
+            compiler.nodes[i].write_to_slice(&mut syntax_data[offset .. offset + SCRIPT_NODE_SIZE]);
+        }
+
+        let strings = compiler.strings;
+        self.replace_data_block(SCRIPT_SYNTAX_DATA_OFFSET, &syntax_data);
+        self.replace_data_block(SCRIPT_STRING_DATA_OFFSET, &strings);
+        self.replace_reflexive(SCRIPTS_OFFSET, SCRIPT_SIZE, &scripts);
+        self.replace_reflexive(GLOBALS_OFFSET, GLOBAL_SIZE, &globals);
+
+        Ok(())
+    }
+}
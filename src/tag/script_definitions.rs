@@ -0,0 +1,875 @@
+// The engine's function table, in the order of the engine's script documentation: Name, return
+// type, parameter types, minimum parameter count, and whether or not the last parameter repeats.
+// Each function's index is its opcode.
+pub const SCRIPT_FUNCTION_TABLE : [(&str,&str,&[&str],usize,bool) ; 502] = [
+    ("begin", "passthrough", &["passthrough"], 1, true),
+    ("begin_random", "passthrough", &["passthrough"], 1, true),
+    ("if", "passthrough", &["boolean", "passthrough", "passthrough"], 2, false),
+    ("cond", "passthrough", &["passthrough"], 1, true),
+    ("set", "passthrough", &["passthrough", "passthrough"], 2, false),
+    ("and", "boolean", &["boolean"], 1, true),
+    ("or", "boolean", &["boolean"], 1, true),
+    ("+", "real", &["real"], 1, true),
+    ("-", "real", &["real", "real"], 2, false),
+    ("*", "real", &["real"], 1, true),
+    ("/", "real", &["real", "real"], 2, false),
+    ("min", "real", &["real"], 1, true),
+    ("max", "real", &["real"], 1, true),
+    ("=", "boolean", &["passthrough", "passthrough"], 2, false),
+    ("!=", "boolean", &["passthrough", "passthrough"], 2, false),
+    (">", "boolean", &["real", "real"], 2, false),
+    ("<", "boolean", &["real", "real"], 2, false),
+    (">=", "boolean", &["real", "real"], 2, false),
+    ("<=", "boolean", &["real", "real"], 2, false),
+    ("sleep", "void", &["short", "script"], 1, false),
+    ("sleep_until", "void", &["boolean", "short", "short"], 1, false),
+    ("wake", "void", &["script"], 1, false),
+    ("inspect", "void", &["passthrough"], 1, false),
+    ("unit", "unit", &["object"], 1, false),
+    ("ai_debug_communication_suppress", "void", &["string"], 1, true),
+    ("ai_debug_communication_ignore", "void", &["string"], 1, true),
+    ("ai_debug_communication_focus", "void", &["string"], 1, true),
+    ("not", "boolean", &["boolean"], 1, false),
+    ("print", "void", &["string"], 1, false),
+    ("log_print", "void", &["string"], 1, false),
+    ("script_recompile", "void", &[], 0, false),
+    ("script_doc", "void", &[], 0, false),
+    ("help", "void", &["string"], 1, false),
+    ("random_range", "short", &["short", "short"], 2, false),
+    ("real_random_range", "real", &["real", "real"], 2, false),
+    ("numeric_countdown_timer_set", "void", &["long", "boolean"], 2, false),
+    ("numeric_countdown_timer_get", "short", &["short"], 1, false),
+    ("numeric_countdown_timer_stop", "void", &[], 0, false),
+    ("numeric_countdown_timer_restart", "void", &[], 0, false),
+    ("breakable_surfaces_enable", "void", &["boolean"], 1, false),
+    ("recording_play", "boolean", &["unit", "cutscene_recording"], 2, false),
+    ("recording_play_and_delete", "boolean", &["unit", "cutscene_recording"], 2, false),
+    ("recording_play_and_hover", "boolean", &["vehicle", "cutscene_recording"], 2, false),
+    ("recording_kill", "void", &["unit"], 1, false),
+    ("recording_time", "short", &["unit"], 1, false),
+    ("object_set_ranged_attack_inhibited", "void", &["object", "boolean"], 2, false),
+    ("object_set_melee_attack_inhibited", "void", &["object", "boolean"], 2, false),
+    ("objects_dump_memory", "void", &[], 0, false),
+    ("object_create", "void", &["object_name"], 1, false),
+    ("object_create_containing", "void", &["string"], 1, false),
+    ("object_create_anew", "void", &["object_name"], 1, false),
+    ("object_create_anew_containing", "void", &["string"], 1, false),
+    ("object_destroy", "void", &["object"], 1, false),
+    ("object_destroy_containing", "void", &["string"], 1, false),
+    ("object_destroy_all", "void", &[], 0, false),
+    ("list_get", "object", &["object_list", "short"], 2, false),
+    ("list_count", "short", &["object_list"], 1, false),
+    ("effect_new", "void", &["effect", "cutscene_flag"], 2, false),
+    ("effect_new_on_object_marker", "void", &["effect", "object", "string"], 3, false),
+    ("damage_new", "void", &["damage", "cutscene_flag"], 2, false),
+    ("damage_object", "void", &["damage", "object"], 2, false),
+    ("objects_can_see_object", "boolean", &["object_list", "object", "real"], 3, false),
+    ("objects_can_see_flag", "boolean", &["object_list", "cutscene_flag", "real"], 3, false),
+    ("objects_delete_by_definition", "void", &["object_definition"], 1, false),
+    ("sound_set_gain", "void", &["string", "real"], 2, false),
+    ("sound_get_gain", "real", &["string"], 1, false),
+    ("script_screen_effect_set_value", "void", &["short", "real"], 2, false),
+    ("cinematic_screen_effect_start", "void", &["boolean"], 1, false),
+    ("cinematic_screen_effect_set_convolution", "void", &["short", "short", "real", "real", "real"], 5, false),
+    ("cinematic_screen_effect_set_filter", "void", &["real", "real", "real", "real", "boolean", "real"], 6, false),
+    ("cinematic_screen_effect_set_filter_desaturation_tint", "void", &["real", "real", "real"], 3, false),
+    ("cinematic_screen_effect_set_video", "void", &["short", "real"], 2, false),
+    ("cinematic_screen_effect_stop", "void", &[], 0, false),
+    ("cinematic_set_near_clip_distance", "void", &["real"], 1, false),
+    ("player_effect_set_max_translation", "void", &["real", "real", "real"], 3, false),
+    ("player_effect_set_max_rotation", "void", &["real", "real", "real"], 3, false),
+    ("player_effect_set_max_vibrate", "void", &["real", "real"], 2, false),
+    ("player_effect_start", "void", &["real", "real"], 2, false),
+    ("player_effect_stop", "void", &["real"], 1, false),
+    ("hud_show_health", "void", &["boolean"], 1, false),
+    ("hud_blink_health", "void", &["boolean"], 1, false),
+    ("hud_show_shield", "void", &["boolean"], 1, false),
+    ("hud_blink_shield", "void", &["boolean"], 1, false),
+    ("hud_show_motion_sensor", "void", &["boolean"], 1, false),
+    ("hud_blink_motion_sensor", "void", &["boolean"], 1, false),
+    ("hud_show_crosshair", "void", &["boolean"], 1, false),
+    ("hud_clear_messages", "void", &[], 0, false),
+    ("hud_set_help_text", "void", &["hud_message"], 1, false),
+    ("hud_set_objective_text", "void", &["hud_message"], 1, false),
+    ("hud_set_timer_time", "void", &["short", "short"], 2, false),
+    ("hud_set_timer_warning_time", "void", &["short", "short"], 2, false),
+    ("hud_set_timer_position", "void", &["short", "short", "hud_corner"], 3, false),
+    ("show_hud_timer", "void", &["boolean"], 1, false),
+    ("pause_hud_timer", "void", &["boolean"], 1, false),
+    ("hud_get_timer_ticks", "short", &[], 0, false),
+    ("time_code_show", "void", &["boolean"], 1, false),
+    ("time_code_start", "void", &["boolean"], 1, false),
+    ("time_code_reset", "void", &[], 0, false),
+    ("show_hud_help_text", "void", &["boolean"], 1, false),
+    ("enable_hud_help_flash", "void", &["boolean"], 1, false),
+    ("hud_help_flash_restart", "void", &[], 0, false),
+    ("activate_nav_point_flag", "void", &["navpoint", "unit", "cutscene_flag", "real"], 4, false),
+    ("activate_nav_point_object", "void", &["navpoint", "unit", "object", "real"], 4, false),
+    ("activate_team_nav_point_flag", "void", &["navpoint", "team", "cutscene_flag", "real"], 4, false),
+    ("activate_team_nav_point_object", "void", &["navpoint", "team", "object", "real"], 4, false),
+    ("deactivate_nav_point_flag", "void", &["unit", "cutscene_flag"], 2, false),
+    ("deactivate_nav_point_object", "void", &["unit", "object"], 2, false),
+    ("deactivate_team_nav_point_flag", "void", &["team", "cutscene_flag"], 2, false),
+    ("deactivate_team_nav_point_object", "void", &["team", "object"], 2, false),
+    ("cls", "void", &[], 0, false),
+    ("error_overflow_suppression", "void", &["boolean"], 1, false),
+    ("player_enable_input", "void", &["boolean"], 1, false),
+    ("player_camera_control", "void", &["boolean"], 1, false),
+    ("player_action_test_reset", "void", &[], 0, false),
+    ("player_action_test_jump", "boolean", &[], 0, false),
+    ("player_action_test_primary_trigger", "boolean", &[], 0, false),
+    ("player_action_test_grenade_trigger", "boolean", &[], 0, false),
+    ("player_action_test_zoom", "boolean", &[], 0, false),
+    ("player_action_test_action", "boolean", &[], 0, false),
+    ("player_action_test_accept", "boolean", &[], 0, false),
+    ("player_action_test_back", "boolean", &[], 0, false),
+    ("player_action_test_look_relative_up", "boolean", &[], 0, false),
+    ("player_action_test_look_relative_down", "boolean", &[], 0, false),
+    ("player_action_test_look_relative_left", "boolean", &[], 0, false),
+    ("player_action_test_look_relative_right", "boolean", &[], 0, false),
+    ("player_action_test_look_relative_all_directions", "boolean", &[], 0, false),
+    ("player_action_test_move_relative_all_directions", "boolean", &[], 0, false),
+    ("debug_camera_load", "boolean", &[], 0, false),
+    ("debug_camera_save", "void", &[], 0, false),
+    ("game_won", "void", &[], 0, false),
+    ("game_lost", "void", &[], 0, false),
+    ("game_safe_to_save", "boolean", &[], 0, false),
+    ("game_all_quiet", "boolean", &[], 0, false),
+    ("game_safe_to_speak", "boolean", &[], 0, false),
+    ("game_is_cooperative", "boolean", &[], 0, false),
+    ("game_save", "void", &[], 0, false),
+    ("game_save_cancel", "void", &[], 0, false),
+    ("game_save_no_timeout", "void", &[], 0, false),
+    ("game_save_totally_unsafe", "void", &[], 0, false),
+    ("game_saving", "boolean", &[], 0, false),
+    ("game_revert", "void", &[], 0, false),
+    ("game_reverted", "boolean", &[], 0, false),
+    ("core_save", "boolean", &[], 0, false),
+    ("core_save_name", "boolean", &["string"], 1, false),
+    ("core_load", "boolean", &[], 0, false),
+    ("core_load_at_startup", "boolean", &[], 0, false),
+    ("core_load_name", "boolean", &["string"], 1, false),
+    ("core_load_name_at_startup", "boolean", &["string"], 1, false),
+    ("game_skip_ticks", "void", &["short"], 1, false),
+    ("sound_impulse_predict", "void", &["sound", "boolean"], 2, false),
+    ("sound_impulse_start", "void", &["sound", "object", "real"], 3, false),
+    ("sound_impulse_time", "long", &["sound"], 1, false),
+    ("sound_impulse_stop", "void", &["sound"], 1, false),
+    ("sound_looping_predict", "void", &["looping_sound"], 1, false),
+    ("sound_looping_start", "void", &["looping_sound", "object", "real"], 3, false),
+    ("sound_looping_stop", "void", &["looping_sound"], 1, false),
+    ("sound_looping_set_scale", "void", &["looping_sound", "real"], 2, false),
+    ("sound_looping_set_alternate", "void", &["looping_sound", "boolean"], 2, false),
+    ("debug_sounds_enable", "void", &["string", "boolean"], 2, false),
+    ("debug_sounds_distances", "void", &["string", "real", "real"], 3, false),
+    ("debug_sounds_wet", "void", &["string", "real"], 2, false),
+    ("sound_enable", "void", &["boolean"], 1, false),
+    ("sound_set_master_gain", "void", &["real"], 1, false),
+    ("sound_get_master_gain", "real", &[], 0, false),
+    ("sound_set_music_gain", "void", &["real"], 1, false),
+    ("sound_get_music_gain", "real", &[], 0, false),
+    ("sound_set_effects_gain", "void", &["real"], 1, false),
+    ("sound_get_effects_gain", "real", &[], 0, false),
+    ("sound_class_set_gain", "void", &["string", "real", "short"], 3, false),
+    ("vehicle_driver", "unit", &["unit"], 1, false),
+    ("vehicle_gunner", "unit", &["unit"], 1, false),
+    ("unit_get_health", "real", &["unit"], 1, false),
+    ("unit_get_shield", "real", &["unit"], 1, false),
+    ("unit_get_total_grenade_count", "short", &["unit"], 1, false),
+    ("unit_has_weapon", "boolean", &["unit", "object_definition"], 2, false),
+    ("unit_has_weapon_readied", "boolean", &["unit", "object_definition"], 2, false),
+    ("unit_doesnt_drop_items", "void", &["object_list"], 1, false),
+    ("unit_impervious", "void", &["object_list", "boolean"], 2, false),
+    ("unit_suspended", "void", &["unit", "boolean"], 2, false),
+    ("unit_solo_player_integrated_night_vision_is_active", "boolean", &[], 0, false),
+    ("units_set_desired_flashlight_state", "void", &["object_list", "boolean"], 2, false),
+    ("unit_set_desired_flashlight_state", "void", &["unit", "boolean"], 2, false),
+    ("unit_get_current_flashlight_state", "boolean", &["unit"], 1, false),
+    ("device_set_never_appears_locked", "void", &["device", "boolean"], 2, false),
+    ("device_get_power", "real", &["device"], 1, false),
+    ("device_set_power", "void", &["device", "real"], 2, false),
+    ("device_set_position", "boolean", &["device", "real"], 2, false),
+    ("device_get_position", "real", &["device"], 1, false),
+    ("device_set_position_immediate", "void", &["device", "real"], 2, false),
+    ("device_group_get", "real", &["device_group"], 1, false),
+    ("device_group_set", "boolean", &["device_group", "real"], 2, false),
+    ("device_group_set_immediate", "void", &["device_group", "real"], 2, false),
+    ("device_one_sided_set", "void", &["device", "boolean"], 2, false),
+    ("device_operates_automatically_set", "void", &["device", "boolean"], 2, false),
+    ("device_group_change_only_once_more_set", "void", &["device_group", "boolean"], 2, false),
+    ("breakable_surfaces_reset", "void", &[], 0, false),
+    ("cheat_all_powerups", "void", &[], 0, false),
+    ("cheat_all_weapons", "void", &[], 0, false),
+    ("cheat_spawn_warthog", "void", &[], 0, false),
+    ("cheat_all_vehicles", "void", &[], 0, false),
+    ("cheat_teleport_to_camera", "void", &[], 0, false),
+    ("cheat_active_camouflage", "void", &[], 0, false),
+    ("cheat_active_camouflage_local_player", "void", &["short"], 1, false),
+    ("cheats_load", "void", &[], 0, false),
+    ("ai_free", "void", &["ai"], 1, false),
+    ("ai_free_units", "void", &["object_list"], 1, false),
+    ("ai_attach", "void", &["unit", "ai"], 2, false),
+    ("ai_attach_free", "void", &["unit", "actor_variant"], 2, false),
+    ("ai_detach", "void", &["unit"], 1, false),
+    ("ai_place", "void", &["ai"], 1, false),
+    ("ai_kill", "void", &["ai"], 1, false),
+    ("ai_kill_silent", "void", &["ai"], 1, false),
+    ("ai_erase", "void", &["ai"], 1, false),
+    ("ai_erase_all", "void", &[], 0, false),
+    ("ai_select", "void", &["ai"], 1, false),
+    ("ai_deselect", "void", &[], 0, false),
+    ("ai_spawn_actor", "void", &["ai"], 1, false),
+    ("ai_set_respawn", "void", &["ai", "boolean"], 2, false),
+    ("ai_set_deaf", "void", &["ai", "boolean"], 2, false),
+    ("ai_set_blind", "void", &["ai", "boolean"], 2, false),
+    ("ai_magically_see_encounter", "void", &["ai", "ai"], 2, false),
+    ("ai_magically_see_players", "void", &["ai"], 1, false),
+    ("ai_magically_see_unit", "void", &["ai", "unit"], 2, false),
+    ("ai_timer_start", "void", &["ai"], 1, false),
+    ("ai_timer_expire", "void", &["ai"], 1, false),
+    ("ai_attack", "void", &["ai"], 1, false),
+    ("ai_defend", "void", &["ai"], 1, false),
+    ("ai_retreat", "void", &["ai"], 1, false),
+    ("ai_maneuver", "void", &["ai"], 1, false),
+    ("ai_maneuver_enable", "void", &["ai", "boolean"], 2, false),
+    ("ai_migrate", "void", &["ai", "ai"], 2, false),
+    ("ai_migrate_and_speak", "void", &["ai", "ai", "string"], 3, false),
+    ("ai_migrate_by_unit", "void", &["object_list", "ai"], 2, false),
+    ("ai_allegiance", "void", &["team", "team"], 2, false),
+    ("ai_allegiance_remove", "void", &["team", "team"], 2, false),
+    ("ai_allegiance_broken", "boolean", &["team", "team"], 2, false),
+    ("ai_living_count", "short", &["ai"], 1, false),
+    ("ai_living_fraction", "real", &["ai"], 1, false),
+    ("ai_strength", "real", &["ai"], 1, false),
+    ("ai_swarm_count", "short", &["ai"], 1, false),
+    ("ai_nonswarm_count", "short", &["ai"], 1, false),
+    ("ai_actors", "object_list", &["ai"], 1, false),
+    ("ai_go_to_vehicle", "boolean", &["ai", "unit", "string"], 3, false),
+    ("ai_go_to_vehicle_override", "boolean", &["ai", "unit", "string"], 3, false),
+    ("ai_going_to_vehicle", "short", &["unit"], 1, false),
+    ("ai_exit_vehicle", "void", &["ai"], 1, false),
+    ("ai_braindead", "void", &["ai", "boolean"], 2, false),
+    ("ai_braindead_by_unit", "void", &["object_list", "boolean"], 2, false),
+    ("ai_disregard", "void", &["object_list", "boolean"], 2, false),
+    ("ai_prefer_target", "void", &["object_list", "boolean"], 2, false),
+    ("ai_teleport_to_starting_location", "void", &["ai"], 1, false),
+    ("ai_teleport_to_starting_location_if_unsupported", "void", &["ai"], 1, false),
+    ("ai_renew", "void", &["ai"], 1, false),
+    ("ai_try_to_fight_nothing", "void", &["ai"], 1, false),
+    ("ai_try_to_fight", "void", &["ai", "ai"], 2, false),
+    ("ai_try_to_fight_player", "void", &["ai"], 1, false),
+    ("ai_command_list", "void", &["ai", "ai_command_list"], 2, false),
+    ("ai_command_list_by_unit", "void", &["unit", "ai_command_list"], 2, false),
+    ("ai_command_list_advance", "void", &["ai"], 1, false),
+    ("ai_command_list_advance_by_unit", "void", &["unit"], 1, false),
+    ("ai_command_list_status", "short", &["object_list"], 1, false),
+    ("ai_is_attacking", "boolean", &["ai"], 1, false),
+    ("ai_force_active", "void", &["ai", "boolean"], 2, false),
+    ("ai_force_active_by_unit", "void", &["unit", "boolean"], 2, false),
+    ("ai_set_return_state", "void", &["ai", "ai_default_state"], 2, false),
+    ("ai_set_current_state", "void", &["ai", "ai_default_state"], 2, false),
+    ("ai_playfight", "void", &["ai", "boolean"], 2, false),
+    ("ai_status", "short", &["ai"], 1, false),
+    ("ai_reconnect", "void", &[], 0, false),
+    ("ai_vehicle_encounter", "void", &["unit", "ai"], 2, false),
+    ("ai_vehicle_enterable_distance", "void", &["unit", "real"], 2, false),
+    ("ai_vehicle_enterable_team", "void", &["unit", "team"], 2, false),
+    ("ai_vehicle_enterable_actor_type", "void", &["unit", "actor_type"], 2, false),
+    ("ai_vehicle_enterable_actors", "void", &["unit", "ai"], 2, false),
+    ("ai_vehicle_enterable_disable", "void", &["unit"], 1, false),
+    ("ai_look_at_object", "void", &["unit", "object"], 2, false),
+    ("ai_stop_looking", "void", &["unit"], 1, false),
+    ("ai_automatic_migration_target", "void", &["ai", "boolean"], 2, false),
+    ("ai_follow_target_disable", "void", &["ai"], 1, false),
+    ("ai_follow_target_players", "void", &["ai"], 1, false),
+    ("ai_follow_target_unit", "void", &["ai", "unit"], 2, false),
+    ("ai_follow_target_ai", "void", &["ai", "ai"], 2, false),
+    ("ai_follow_distance", "void", &["ai", "real"], 2, false),
+    ("ai_conversation", "void", &["conversation"], 1, false),
+    ("ai_conversation_stop", "void", &["conversation"], 1, false),
+    ("ai_conversation_advance", "void", &["conversation"], 1, false),
+    ("ai_conversation_line", "short", &["conversation"], 1, false),
+    ("ai_conversation_status", "short", &["conversation"], 1, false),
+    ("ai_link_activation", "void", &["ai", "ai"], 2, false),
+    ("ai_berserk", "void", &["ai", "boolean"], 2, false),
+    ("ai_set_team", "void", &["ai", "team"], 2, false),
+    ("ai_allow_charge", "void", &["ai", "boolean"], 2, false),
+    ("ai_allow_dormant", "void", &["ai", "boolean"], 2, false),
+    ("ai_grenades", "void", &["boolean"], 1, false),
+    ("ai_dialogue_triggers", "void", &["boolean"], 1, false),
+    ("ai_debug_sound_point_set", "void", &[], 0, false),
+    ("ai_debug_vocalize", "void", &["string", "string"], 2, false),
+    ("ai_debug_teleport_to", "void", &["ai"], 1, false),
+    ("ai_debug_speak", "void", &["string"], 1, false),
+    ("ai_debug_speak_list", "void", &["string"], 1, false),
+    ("ai_lines", "void", &[], 0, false),
+    ("ai_select_next_actor", "void", &[], 0, false),
+    ("ai_select_previous_actor", "void", &[], 0, false),
+    ("ai_select_next_encounter", "void", &[], 0, false),
+    ("ai_select_previous_encounter", "void", &[], 0, false),
+    ("camera_control", "void", &["boolean"], 1, false),
+    ("camera_set", "void", &["cutscene_camera_point", "short"], 2, false),
+    ("camera_set_relative", "void", &["cutscene_camera_point", "short", "object"], 3, false),
+    ("camera_set_animation", "void", &["animation_graph", "string"], 2, false),
+    ("camera_set_first_person", "void", &["unit"], 1, false),
+    ("camera_set_dead", "void", &["unit"], 1, false),
+    ("camera_time", "short", &[], 0, false),
+    ("debug_camera_load_map", "void", &[], 0, false),
+    ("debug_camera_load_solo", "void", &[], 0, false),
+    ("debug_camera_load_text", "void", &["string"], 1, false),
+    ("debug_camera_save_map", "void", &[], 0, false),
+    ("debug_camera_save_solo", "void", &[], 0, false),
+    ("debug_camera_save_name", "void", &["string"], 1, false),
+    ("game_speed", "void", &["real"], 1, false),
+    ("game_time", "long", &[], 0, false),
+    ("game_variant", "void", &["string"], 1, false),
+    ("game_difficulty_get", "game_difficulty", &[], 0, false),
+    ("game_difficulty_get_real", "game_difficulty", &[], 0, false),
+    ("game_difficulty_set", "void", &["game_difficulty"], 1, false),
+    ("map_reset", "void", &[], 0, false),
+    ("map_name", "void", &["string"], 1, false),
+    ("multiplayer_map_name", "void", &["string"], 1, false),
+    ("switch_bsp", "void", &["short"], 1, false),
+    ("structure_bsp_index", "short", &[], 0, false),
+    ("structure_lens_flares_place", "void", &[], 0, false),
+    ("crash", "void", &["string"], 1, false),
+    ("version", "void", &[], 0, false),
+    ("playback", "void", &[], 0, false),
+    ("quit", "void", &[], 0, false),
+    ("texture_cache_flush", "void", &[], 0, false),
+    ("sound_cache_flush", "void", &[], 0, false),
+    ("sound_cache_dump_to_file", "void", &[], 0, false),
+    ("debug_memory", "void", &[], 0, false),
+    ("debug_memory_by_file", "void", &[], 0, false),
+    ("debug_memory_for_file", "void", &["string"], 1, false),
+    ("debug_tags", "void", &[], 0, false),
+    ("profile_reset", "void", &[], 0, false),
+    ("profile_dump", "void", &["string"], 1, false),
+    ("profile_activate", "void", &["string"], 1, false),
+    ("profile_deactivate", "void", &["string"], 1, false),
+    ("profile_graph_toggle", "void", &["string"], 1, false),
+    ("profile_service_clear_timers", "void", &[], 0, false),
+    ("profile_service_dump_timers", "void", &[], 0, false),
+    ("debug_pvs", "void", &["boolean"], 1, false),
+    ("radiosity_start", "void", &[], 0, false),
+    ("radiosity_save", "void", &[], 0, false),
+    ("radiosity_debug_point", "void", &[], 0, false),
+    ("cinematic_start", "void", &[], 0, false),
+    ("cinematic_stop", "void", &[], 0, false),
+    ("cinematic_abort", "void", &[], 0, false),
+    ("cinematic_skip_start_internal", "void", &[], 0, false),
+    ("cinematic_skip_stop_internal", "void", &[], 0, false),
+    ("cinematic_show_letterbox", "void", &["boolean"], 1, false),
+    ("cinematic_set_title", "void", &["cutscene_title"], 1, false),
+    ("cinematic_set_title_delayed", "void", &["cutscene_title", "real"], 2, false),
+    ("cinematic_suppress_bsp_object_creation", "void", &["boolean"], 1, false),
+    ("attract_mode_start", "void", &[], 0, false),
+    ("attract_mode_set_seconds", "void", &["real"], 1, false),
+    ("fade_in", "void", &["real", "real", "real", "short"], 4, false),
+    ("fade_out", "void", &["real", "real", "real", "short"], 4, false),
+    ("object_teleport", "void", &["object", "cutscene_flag"], 2, false),
+    ("object_set_facing", "void", &["object", "cutscene_flag"], 2, false),
+    ("object_set_shield", "void", &["object", "real"], 2, false),
+    ("object_set_permutation", "void", &["object", "string", "string"], 3, false),
+    ("object_set_scale", "void", &["object", "real", "short"], 3, false),
+    ("object_pvs_activate", "void", &["object"], 1, false),
+    ("object_pvs_set_object", "void", &["object"], 1, false),
+    ("object_pvs_set_camera", "void", &["cutscene_camera_point"], 1, false),
+    ("object_pvs_clear", "void", &[], 0, false),
+    ("render_lights", "void", &["boolean"], 1, false),
+    ("scenery_animation_start", "void", &["scenery", "animation_graph", "string"], 3, false),
+    ("scenery_animation_start_at_frame", "void", &["scenery", "animation_graph", "string", "short"], 4, false),
+    ("scenery_get_animation_time", "short", &["scenery"], 1, false),
+    ("unit_can_see_flag", "boolean", &["unit", "cutscene_flag", "real"], 3, false),
+    ("unit_can_see_object", "boolean", &["unit", "object", "real"], 3, false),
+    ("object_beautify", "void", &["object", "boolean"], 2, false),
+    ("objects_attach", "void", &["object", "string", "object", "string"], 4, false),
+    ("objects_detach", "void", &["object", "object"], 2, false),
+    ("garbage_collect_now", "void", &[], 0, false),
+    ("object_cannot_take_damage", "void", &["object_list"], 1, false),
+    ("object_can_take_damage", "void", &["object_list"], 1, false),
+    ("objects_predict", "void", &["object_list"], 1, false),
+    ("object_type_predict", "void", &["object_definition"], 1, false),
+    ("custom_animation", "boolean", &["unit", "animation_graph", "string", "boolean"], 4, false),
+    ("custom_animation_list", "boolean", &["object_list", "animation_graph", "string", "boolean"], 4, false),
+    ("unit_custom_animation_at_frame", "boolean", &["unit", "animation_graph", "string", "boolean", "short"], 5, false),
+    ("unit_stop_custom_animation", "void", &["unit"], 1, false),
+    ("unit_is_playing_custom_animation", "boolean", &["unit"], 1, false),
+    ("unit_get_custom_animation_time", "short", &["unit"], 1, false),
+    ("unit_aim_without_turning", "void", &["unit", "boolean"], 2, false),
+    ("unit_set_enterable_by_player", "void", &["unit", "boolean"], 2, false),
+    ("unit_close", "void", &["unit"], 1, false),
+    ("unit_open", "void", &["unit"], 1, false),
+    ("unit_set_maximum_vitality", "void", &["unit", "real", "real"], 3, false),
+    ("unit_set_current_vitality", "void", &["unit", "real", "real"], 3, false),
+    ("vehicle_load_magic", "void", &["object", "string", "object_list"], 3, false),
+    ("vehicle_unload", "void", &["object", "string"], 2, false),
+    ("magic_seat_name", "void", &["string"], 1, false),
+    ("unit_set_seat", "void", &["unit", "string"], 2, false),
+    ("magic_melee_attack", "void", &[], 0, false),
+    ("vehicle_riders", "object_list", &["unit"], 1, false),
+    ("unit_set_emotion", "void", &["unit", "short"], 2, false),
+    ("unit_enable_eye_tracking", "void", &["unit", "boolean"], 2, false),
+    ("unit_set_integrated_flashlight", "void", &["unit", "boolean"], 2, false),
+    ("unit_set_voice", "void", &["unit", "string"], 2, false),
+    ("unit_enter_vehicle", "void", &["unit", "vehicle", "string"], 3, false),
+    ("vehicle_test_seat_list", "boolean", &["vehicle", "string", "object_list"], 3, false),
+    ("vehicle_test_seat", "boolean", &["vehicle", "string", "unit"], 3, false),
+    ("unit_set_emotion_animation", "void", &["unit", "string"], 2, false),
+    ("unit_exit_vehicle", "void", &["unit"], 1, false),
+    ("unit_kill", "void", &["unit"], 1, false),
+    ("unit_kill_silent", "void", &["unit"], 1, false),
+    ("unit_add_equipment", "void", &["unit", "starting_profile", "boolean", "boolean"], 4, false),
+    ("weapon_hold_trigger", "void", &["weapon", "long", "boolean"], 3, false),
+    ("weapon_enable_warthog_chaingun_light", "void", &["boolean"], 1, false),
+    ("player_add_equipment", "void", &["unit", "starting_profile", "boolean"], 3, false),
+    ("players", "object_list", &[], 0, false),
+    ("volume_teleport_players_not_inside", "void", &["trigger_volume", "cutscene_flag"], 2, false),
+    ("volume_test_object", "boolean", &["trigger_volume", "object"], 2, false),
+    ("volume_test_objects", "boolean", &["trigger_volume", "object_list"], 2, false),
+    ("volume_test_objects_all", "boolean", &["trigger_volume", "object_list"], 2, false),
+    ("rasterizer_reload_effects", "void", &[], 0, false),
+    ("rasterizer_fixed_function_ambient", "void", &["long"], 1, false),
+    ("rasterizer_decals_flush", "void", &[], 0, false),
+    ("rasterizer_fps_accumulate", "void", &[], 0, false),
+    ("rasterizer_model_ambient_reflection_tint", "void", &["real", "real", "real", "real"], 4, false),
+    ("rasterizer_lights_reset_for_new_map", "void", &[], 0, false),
+    ("hud_team_icon_set_pos", "void", &["long", "long"], 2, false),
+    ("hud_team_icon_set_scale", "void", &["real", "real"], 2, false),
+    ("hud_team_background_set_pos", "void", &["long", "long"], 2, false),
+    ("hud_team_background_set_scale", "void", &["real", "real"], 2, false),
+    ("reload_shader_transparent_chicago", "void", &[], 0, false),
+    ("ui_widget_show_path", "void", &["boolean"], 1, false),
+    ("display_scenario_help", "void", &["short"], 1, false),
+    ("bind", "void", &["string", "string", "string"], 3, false),
+    ("unbind", "void", &["string", "string"], 2, false),
+    ("print_binds", "void", &[], 0, false),
+    ("mouse_acceleration", "void", &["real", "long"], 2, false),
+    ("set_gamma", "void", &["long"], 1, false),
+    ("sound_enable_eax", "void", &["boolean"], 1, false),
+    ("sound_eax_enabled", "boolean", &[], 0, false),
+    ("sound_set_env", "void", &["short"], 1, false),
+    ("sound_enable_hardware", "void", &["boolean", "boolean"], 2, false),
+    ("sound_set_supplementary_buffers", "void", &["short", "boolean"], 2, false),
+    ("sound_get_supplementary_buffers", "short", &[], 0, false),
+    ("sound_set_rolloff", "void", &["real"], 1, false),
+    ("sound_set_factor", "void", &["real"], 1, false),
+    ("checkpoint_save", "void", &[], 0, false),
+    ("checkpoint_load", "void", &["string"], 1, false),
+    ("profile_load", "void", &["string"], 1, false),
+    ("connect", "void", &["string", "string"], 2, false),
+    ("disconnect", "void", &[], 0, false),
+    ("rcon", "void", &["string", "string"], 2, false),
+    ("net_graph_clear", "void", &[], 0, false),
+    ("net_graph_show", "void", &["string", "string"], 2, false),
+    ("play_update_history", "void", &["long", "boolean"], 2, false),
+    ("show_player_update_stats", "void", &[], 0, false),
+    ("message_metrics_clear", "void", &[], 0, false),
+    ("message_metrics_dump", "void", &["string"], 1, false),
+    ("sv_end_game", "void", &[], 0, false),
+    ("sv_ban", "void", &["string", "string"], 0, false),
+    ("sv_banlist", "void", &[], 0, false),
+    ("sv_banlist_file", "void", &["string"], 0, false),
+    ("sv_ban_penalty", "void", &["string", "string", "string", "string"], 0, false),
+    ("sv_friendly_fire", "void", &["string"], 0, false),
+    ("sv_gamelist", "void", &["string"], 0, false),
+    ("sv_get_player_action_queue_length", "void", &["string"], 1, false),
+    ("sv_kick", "void", &["string"], 1, false),
+    ("sv_log_echo_chat", "void", &["string"], 0, false),
+    ("sv_log_enabled", "void", &["string"], 0, false),
+    ("sv_log_file", "void", &["string"], 0, false),
+    ("sv_log_note", "void", &["string"], 1, false),
+    ("sv_log_rotation_threshold", "void", &["string"], 0, false),
+    ("sv_map", "void", &["string", "string"], 2, false),
+    ("sv_map_next", "void", &[], 0, false),
+    ("sv_map_reset", "void", &[], 0, false),
+    ("sv_mapcycle", "void", &[], 0, false),
+    ("sv_mapcycle_add", "void", &["string", "string"], 2, false),
+    ("sv_mapcycle_begin", "void", &[], 0, false),
+    ("sv_mapcycle_del", "void", &["long"], 1, false),
+    ("sv_mapcycle_timeout", "void", &["string"], 0, false),
+    ("sv_maxplayers", "void", &["string"], 0, false),
+    ("sv_motd", "void", &["string"], 0, false),
+    ("sv_name", "void", &["string"], 0, false),
+    ("sv_parameters_dump", "void", &[], 0, false),
+    ("sv_parameters_reload", "void", &[], 0, false),
+    ("sv_password", "void", &["string"], 0, false),
+    ("sv_players", "void", &[], 0, false),
+    ("sv_rcon_password", "void", &["string"], 0, false),
+    ("sv_say", "void", &["string"], 1, false),
+    ("sv_single_flag_force_reset", "void", &["string"], 0, false),
+    ("sv_status", "void", &[], 0, false),
+    ("sv_timelimit", "void", &["string"], 0, false),
+    ("sv_tk_ban", "void", &["string"], 0, false),
+    ("sv_tk_cooldown", "void", &["string"], 0, false),
+    ("sv_tk_grace", "void", &["string"], 0, false),
+    ("sv_unban", "void", &["string"], 1, false)
+];
+
+// The engine's global table: Name and value type. Each global's index is its position.
+pub const SCRIPT_GLOBAL_TABLE : [(&str,&str) ; 344] = [
+    ("debug_no_frustum_clip", "boolean"),
+    ("debug_no_drawing", "boolean"),
+    ("debug_render_freeze", "boolean"),
+    ("debug_inactive_objects", "boolean"),
+    ("debug_portals", "boolean"),
+    ("debug_leaf_index", "boolean"),
+    ("debug_leaf_portals", "boolean"),
+    ("debug_structure", "boolean"),
+    ("debug_structure_automatic", "boolean"),
+    ("debug_bsp", "boolean"),
+    ("debug_fog_planes", "boolean"),
+    ("debug_lights", "boolean"),
+    ("debug_point_physics", "boolean"),
+    ("debug_motion_sensor_draw_all_units", "boolean"),
+    ("debug_detail_objects", "boolean"),
+    ("debug_sprites", "boolean"),
+    ("debug_decals", "boolean"),
+    ("debug_permanent_decals", "boolean"),
+    ("debug_recording", "boolean"),
+    ("debug_recording_newlines", "boolean"),
+    ("debug_scripting", "boolean"),
+    ("debug_trigger_volumes", "boolean"),
+    ("debug_game_save", "boolean"),
+    ("debug_framerate", "boolean"),
+    ("debug_frustum", "boolean"),
+    ("debug_player", "boolean"),
+    ("debug_camera", "boolean"),
+    ("debug_damage", "boolean"),
+    ("debug_damage_taken", "boolean"),
+    ("debug_sound", "boolean"),
+    ("debug_sound_cache", "boolean"),
+    ("debug_sound_cache_graph", "boolean"),
+    ("debug_sound_channels", "boolean"),
+    ("debug_sound_channels_detail", "boolean"),
+    ("debug_sound_hardware", "boolean"),
+    ("debug_looping_sound", "boolean"),
+    ("debug_texture_cache", "boolean"),
+    ("debug_objects", "boolean"),
+    ("debug_objects_names", "boolean"),
+    ("debug_objects_root_node", "boolean"),
+    ("debug_objects_bounding_spheres", "boolean"),
+    ("debug_objects_collision_models", "boolean"),
+    ("debug_objects_physics", "boolean"),
+    ("debug_objects_pathfinding_spheres", "boolean"),
+    ("debug_objects_unit_vectors", "boolean"),
+    ("debug_objects_unit_seats", "boolean"),
+    ("debug_objects_unit_mouth_apeture", "boolean"),
+    ("debug_objects_biped_autoaim_pills", "boolean"),
+    ("debug_objects_vehicle_powered_mass_points", "boolean"),
+    ("debug_objects_devices", "boolean"),
+    ("debug_objects_position_velocity", "boolean"),
+    ("debug_biped_physics", "boolean"),
+    ("debug_biped_skip_update", "boolean"),
+    ("debug_biped_skip_collision", "boolean"),
+    ("debug_biped_limp_body_disable", "boolean"),
+    ("debug_unit_all_animations", "boolean"),
+    ("debug_unit_animations", "boolean"),
+    ("debug_unit_illumination", "boolean"),
+    ("debug_obstacle_path", "boolean"),
+    ("debug_obstacle_path_on_failure", "boolean"),
+    ("debug_collision_skip_objects", "boolean"),
+    ("debug_collision_skip_vectors", "boolean"),
+    ("debug_collision_skip_instanced_geometry", "boolean"),
+    ("debug_object_garbage_collection", "boolean"),
+    ("debug_lights_sprites", "boolean"),
+    ("debug_score", "boolean"),
+    ("debug_input", "boolean"),
+    ("debug_input_target", "boolean"),
+    ("debug_player_teleport", "boolean"),
+    ("debug_physics_disable_penetration_freeze", "boolean"),
+    ("cheat_deathless_player", "boolean"),
+    ("cheat_jetpack", "boolean"),
+    ("cheat_infinite_ammo", "boolean"),
+    ("cheat_bottomless_clip", "boolean"),
+    ("cheat_bump_possession", "boolean"),
+    ("cheat_super_jump", "boolean"),
+    ("cheat_reflexive_damage_effects", "boolean"),
+    ("cheat_medusa", "boolean"),
+    ("cheat_omnipotent", "boolean"),
+    ("cheat_controller", "boolean"),
+    ("player_autoaim", "boolean"),
+    ("player_magnetism", "boolean"),
+    ("player_spawn_count", "short"),
+    ("game_speed", "real"),
+    ("effects_corpse_nonviolent", "boolean"),
+    ("breakable_surfaces", "boolean"),
+    ("decals", "boolean"),
+    ("weather", "boolean"),
+    ("temporary_hud", "boolean"),
+    ("terminal_render", "boolean"),
+    ("console_dump_to_file", "boolean"),
+    ("framerate_throttle", "boolean"),
+    ("framerate_lock", "boolean"),
+    ("speed", "boolean"),
+    ("stun_enable", "boolean"),
+    ("allow_client_side_weapon_projectiles", "boolean"),
+    ("multiplayer_draw_teammates_names", "boolean"),
+    ("multiplayer_hit_sound_volume", "real"),
+    ("director_camera_switching", "boolean"),
+    ("director_camera_switch_fast", "boolean"),
+    ("model_animation_compression", "boolean"),
+    ("model_animation_bullshit0", "boolean"),
+    ("model_animation_bullshit1", "boolean"),
+    ("model_animation_bullshit2", "boolean"),
+    ("model_animation_bullshit3", "boolean"),
+    ("object_light_ambient_base", "real"),
+    ("object_light_ambient_scale", "real"),
+    ("object_light_secondary_scale", "real"),
+    ("object_light_interpolate", "boolean"),
+    ("sound_obstruction_ratio", "real"),
+    ("sound_gain_under_dialog", "real"),
+    ("collision_debug", "boolean"),
+    ("collision_debug_spray", "boolean"),
+    ("collision_debug_features", "boolean"),
+    ("collision_debug_repeat", "boolean"),
+    ("collision_debug_point_x", "real"),
+    ("collision_debug_point_y", "real"),
+    ("collision_debug_point_z", "real"),
+    ("collision_debug_vector_i", "real"),
+    ("collision_debug_vector_j", "real"),
+    ("collision_debug_vector_k", "real"),
+    ("collision_debug_length", "real"),
+    ("collision_debug_width", "real"),
+    ("collision_debug_height", "real"),
+    ("collision_debug_flag_front_facing_surfaces", "boolean"),
+    ("collision_debug_flag_back_facing_surfaces", "boolean"),
+    ("collision_debug_flag_ignore_two_sided_surfaces", "boolean"),
+    ("collision_debug_flag_ignore_invisible_surfaces", "boolean"),
+    ("collision_debug_flag_ignore_breakable_surfaces", "boolean"),
+    ("collision_debug_flag_structure", "boolean"),
+    ("collision_debug_flag_media", "boolean"),
+    ("collision_debug_flag_objects", "boolean"),
+    ("collision_debug_flag_objects_bipeds", "boolean"),
+    ("collision_debug_flag_objects_vehicles", "boolean"),
+    ("collision_debug_flag_objects_weapons", "boolean"),
+    ("collision_debug_flag_objects_equipment", "boolean"),
+    ("collision_debug_flag_objects_projectiles", "boolean"),
+    ("collision_debug_flag_objects_scenery", "boolean"),
+    ("collision_debug_flag_objects_machines", "boolean"),
+    ("collision_debug_flag_objects_controls", "boolean"),
+    ("collision_debug_flag_objects_light_fixtures", "boolean"),
+    ("collision_debug_flag_objects_placeholders", "boolean"),
+    ("collision_debug_flag_try_to_keep_location_valid", "boolean"),
+    ("collision_debug_flag_skip_passthrough_bipeds", "boolean"),
+    ("collision_debug_flag_use_vehicle_physics", "boolean"),
+    ("collision_log_render", "boolean"),
+    ("collision_log_detailed", "boolean"),
+    ("collision_log_extended", "boolean"),
+    ("collision_log_totals_only", "boolean"),
+    ("collision_log_time", "boolean"),
+    ("radiosity_quality", "short"),
+    ("radiosity_step_count", "short"),
+    ("radiosity_lines", "boolean"),
+    ("radiosity_normals", "boolean"),
+    ("ai_render", "boolean"),
+    ("ai_render_all_actors", "boolean"),
+    ("ai_render_inactive_actors", "boolean"),
+    ("ai_render_lineoffire_crouching", "boolean"),
+    ("ai_render_lineoffire", "boolean"),
+    ("ai_render_lineofsight", "boolean"),
+    ("ai_render_ballistic_lineoffire", "boolean"),
+    ("ai_render_encounter_activeness", "boolean"),
+    ("ai_render_vision_cones", "boolean"),
+    ("ai_render_current_state", "boolean"),
+    ("ai_render_detailed_state", "boolean"),
+    ("ai_render_emotions", "boolean"),
+    ("ai_render_grenades", "boolean"),
+    ("ai_render_danger_zones", "boolean"),
+    ("ai_render_control", "boolean"),
+    ("ai_render_activation", "boolean"),
+    ("ai_render_paths", "boolean"),
+    ("ai_render_paths_text", "boolean"),
+    ("ai_render_paths_failed", "boolean"),
+    ("ai_render_paths_smoothed", "boolean"),
+    ("ai_render_paths_avoided", "boolean"),
+    ("ai_render_paths_nodes", "boolean"),
+    ("ai_render_paths_nodes_all", "boolean"),
+    ("ai_render_paths_nodes_polygons", "boolean"),
+    ("ai_render_paths_nodes_costs", "boolean"),
+    ("ai_render_paths_nodes_closest", "boolean"),
+    ("ai_render_paths_distance", "boolean"),
+    ("ai_render_paths_selected_only", "boolean"),
+    ("ai_render_paths_destination", "boolean"),
+    ("ai_render_paths_raw", "boolean"),
+    ("ai_render_paths_current", "boolean"),
+    ("ai_render_spatial_effects", "boolean"),
+    ("ai_render_firing_positions", "boolean"),
+    ("ai_render_gun_positions", "boolean"),
+    ("ai_render_aiming_vectors", "boolean"),
+    ("ai_render_aiming_validity", "boolean"),
+    ("ai_render_teams", "boolean"),
+    ("ai_render_player_ratings", "boolean"),
+    ("ai_render_behavior_failures", "boolean"),
+    ("ai_render_vehicle_avoidance", "boolean"),
+    ("ai_render_vehicles_enterable", "boolean"),
+    ("ai_render_idle_look", "boolean"),
+    ("ai_render_shooting", "boolean"),
+    ("ai_render_charge_decisions", "boolean"),
+    ("ai_render_postcombat", "boolean"),
+    ("ai_render_recent_damage", "boolean"),
+    ("ai_render_threats", "boolean"),
+    ("ai_render_targets", "boolean"),
+    ("ai_render_targets_last_visible", "boolean"),
+    ("ai_render_states", "boolean"),
+    ("ai_render_vitality", "boolean"),
+    ("ai_render_evaluations", "short"),
+    ("ai_render_evaluations_detailed", "boolean"),
+    ("ai_render_evaluations_text", "boolean"),
+    ("ai_render_evaluations_shading", "boolean"),
+    ("ai_render_evaluations_shading_type", "short"),
+    ("ai_render_pursuit", "boolean"),
+    ("ai_render_projectile_aiming", "boolean"),
+    ("ai_render_melee_check", "boolean"),
+    ("ai_render_dialogue", "boolean"),
+    ("ai_render_dialogue_queue", "boolean"),
+    ("ai_render_dialogue_player_weights", "boolean"),
+    ("ai_render_speech", "boolean"),
+    ("ai_render_player_aiming_blocked", "boolean"),
+    ("ai_render_sectors", "boolean"),
+    ("ai_render_sector_bsps", "boolean"),
+    ("ai_render_intersection_links", "boolean"),
+    ("ai_render_non_walkable_sectors", "boolean"),
+    ("ai_render_threshold_links", "boolean"),
+    ("ai_render_object_properties", "boolean"),
+    ("ai_render_holding", "boolean"),
+    ("ai_print_communication", "boolean"),
+    ("ai_print_communication_player", "boolean"),
+    ("ai_print_vocalizations", "boolean"),
+    ("ai_print_unit_vocalizations", "boolean"),
+    ("ai_print_major_upgrade", "boolean"),
+    ("ai_print_oversteer", "boolean"),
+    ("ai_print_evaluation_statistics", "boolean"),
+    ("ai_print_respawn", "boolean"),
+    ("ai_print_bsp_transition", "boolean"),
+    ("ai_print_killing_sprees", "boolean"),
+    ("ai_print_scripting", "boolean"),
+    ("ai_print_surprise", "boolean"),
+    ("ai_print_automatic_migration", "boolean"),
+    ("ai_print_placement", "boolean"),
+    ("ai_print_speech", "boolean"),
+    ("ai_print_conversations", "boolean"),
+    ("ai_print_uncovering", "boolean"),
+    ("ai_print_acknowledgement", "boolean"),
+    ("ai_print_lost_speech", "boolean"),
+    ("ai_debug_fast_los", "boolean"),
+    ("ai_debug_ballistic_lineoffire_freeze", "boolean"),
+    ("ai_debug_oversteer_disable", "boolean"),
+    ("ai_debug_path", "boolean"),
+    ("ai_debug_path_end_freeze", "boolean"),
+    ("ai_debug_path_flood", "boolean"),
+    ("ai_debug_path_maximum_radius", "boolean"),
+    ("ai_debug_path_attractor", "boolean"),
+    ("ai_debug_path_attractor_radius", "real"),
+    ("ai_debug_path_attractor_weight", "real"),
+    ("ai_debug_path_accept_radius", "real"),
+    ("ai_debug_communication_random_disabled", "boolean"),
+    ("ai_debug_communication_timeout_disabled", "boolean"),
+    ("ai_debug_communication_unit_repeat_disabled", "boolean"),
+    ("ai_debug_communication_focus_enable", "boolean"),
+    ("ai_debug_blind", "boolean"),
+    ("ai_debug_deaf", "boolean"),
+    ("ai_debug_invisible_player", "boolean"),
+    ("ai_debug_ignore_player", "boolean"),
+    ("ai_debug_flee_always", "boolean"),
+    ("ai_debug_evaluate_all_positions", "boolean"),
+    ("ai_debug_force_all_active", "boolean"),
+    ("ai_debug_disable_wounded_sounds", "boolean"),
+    ("ai_debug_force_crouch", "boolean"),
+    ("ai_debug_force_vocalizations", "boolean"),
+    ("rasterizer_stats", "short"),
+    ("rasterizer_mode", "short"),
+    ("rasterizer_wireframe", "boolean"),
+    ("rasterizer_smart", "boolean"),
+    ("rasterizer_debug_model_vertices", "short"),
+    ("rasterizer_debug_model_lod", "short"),
+    ("rasterizer_debug_transparents", "boolean"),
+    ("rasterizer_debug_meter_shader", "boolean"),
+    ("rasterizer_debug_geometry", "boolean"),
+    ("rasterizer_debug_geometry_multipass", "boolean"),
+    ("rasterizer_models", "boolean"),
+    ("rasterizer_model_transparents", "boolean"),
+    ("rasterizer_draw_first_person_weapon_first", "boolean"),
+    ("rasterizer_stencil_mask", "boolean"),
+    ("rasterizer_environment", "boolean"),
+    ("rasterizer_environment_lightmaps", "boolean"),
+    ("rasterizer_environment_shadows", "boolean"),
+    ("rasterizer_environment_diffuse_lights", "boolean"),
+    ("rasterizer_environment_diffuse_textures", "boolean"),
+    ("rasterizer_environment_decals", "boolean"),
+    ("rasterizer_environment_specular_lights", "boolean"),
+    ("rasterizer_environment_specular_lightmaps", "boolean"),
+    ("rasterizer_environment_reflection_lightmap_mask", "boolean"),
+    ("rasterizer_environment_reflection_mirrors", "boolean"),
+    ("rasterizer_environment_reflections", "boolean"),
+    ("rasterizer_environment_transparents", "boolean"),
+    ("rasterizer_environment_fog", "boolean"),
+    ("rasterizer_environment_fog_screen", "boolean"),
+    ("rasterizer_environment_alpha_testing", "boolean"),
+    ("rasterizer_environment_specular_mask", "boolean"),
+    ("rasterizer_water", "boolean"),
+    ("rasterizer_water_mipmapping", "boolean"),
+    ("rasterizer_lens_flares", "boolean"),
+    ("rasterizer_lens_flares_occlusion", "boolean"),
+    ("rasterizer_lens_flares_occlusion_debug", "boolean"),
+    ("rasterizer_dynamic_unlit_geometry", "boolean"),
+    ("rasterizer_dynamic_lit_geometry", "boolean"),
+    ("rasterizer_dynamic_screen_geometry", "boolean"),
+    ("rasterizer_hud_motion_sensor", "boolean"),
+    ("rasterizer_detail_objects", "boolean"),
+    ("rasterizer_fog_atmosphere", "boolean"),
+    ("rasterizer_fog_plane", "boolean"),
+    ("rasterizer_bump_mapping", "boolean"),
+    ("rasterizer_lightmap_ambient", "real"),
+    ("rasterizer_lightmap_mode", "short"),
+    ("rasterizer_lightmaps_incident_radiance", "boolean"),
+    ("rasterizer_lightmaps_filtering", "boolean"),
+    ("rasterizer_model_lighting_ambient", "real"),
+    ("rasterizer_shadows_convolution", "boolean"),
+    ("rasterizer_shadows_debug", "boolean"),
+    ("rasterizer_active_camouflage", "boolean"),
+    ("rasterizer_active_camouflage_multipass", "boolean"),
+    ("rasterizer_plasma_energy", "boolean"),
+    ("rasterizer_ray_of_buddha", "boolean"),
+    ("rasterizer_screen_flashes", "boolean"),
+    ("rasterizer_screen_effects", "boolean"),
+    ("rasterizer_DXTC_noise", "real"),
+    ("rasterizer_soft_filter", "boolean"),
+    ("rasterizer_secondary_render_target_debug", "boolean"),
+    ("rasterizer_profile_log", "boolean"),
+    ("rasterizer_filthy_decal_fog_hack", "boolean"),
+    ("rasterizer_zsprites", "boolean"),
+    ("rasterizer_zoffset", "real"),
+    ("rasterizer_refraction_amount", "real"),
+    ("rasterizer_effects_level", "short"),
+    ("rasterizer_near_clip_distance", "real"),
+    ("rasterizer_far_clip_distance", "real"),
+    ("rasterizer_floating_point_zbuffer", "boolean"),
+    ("rasterizer_fps", "boolean"),
+    ("rasterizer_framerate_throttle", "boolean"),
+    ("rasterizer_framerate_stabilization", "boolean"),
+    ("rasterizer_frame_bounds_left", "long"),
+    ("rasterizer_frame_bounds_right", "long"),
+    ("rasterizer_frame_bounds_top", "long"),
+    ("rasterizer_frame_bounds_bottom", "long")
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::script_value_type_from_name;
+
+    #[test]
+    fn table_types_and_names_are_valid() {
+        for &(name, return_type, parameters, minimum_parameters, _) in SCRIPT_FUNCTION_TABLE.iter() {
+            assert!(script_value_type_from_name(return_type).is_some(), "{}", name);
+            assert!(parameters.iter().all(|p| script_value_type_from_name(p).is_some()), "{}", name);
+            assert!(minimum_parameters <= parameters.len(), "{}", name);
+            assert_eq!(SCRIPT_FUNCTION_TABLE.iter().filter(|f| f.0 == name).count(), 1, "{}", name);
+        }
+        for &(name, value_type) in SCRIPT_GLOBAL_TABLE.iter() {
+            assert!(script_value_type_from_name(value_type).is_some(), "{}", name);
+            assert_eq!(SCRIPT_GLOBAL_TABLE.iter().filter(|g| g.0 == name).count(), 1, "{}", name);
+        }
+    }
+}
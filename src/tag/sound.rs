@@ -0,0 +1,509 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian,BigEndian};
+
+use super::{Tag, SND};
+use super::wav::{encode_wav, decode_wav, decode_xbox_adpcm, encode_xbox_adpcm, XBOX_ADPCM_BLOCK_SIZE};
+use super::super::{string_from_slice, encode_latin1_string};
+
+/// Size of a pitch range in a snd! tag.
+pub const SOUND_PITCH_RANGE_SIZE : usize = 0x48;
+
+/// Size of a permutation in a snd! tag.
+pub const SOUND_PERMUTATION_SIZE : usize = 0x7C;
+
+/// Maximum size of the samples of a single permutation. Longer sounds are split into chained
+/// permutations when imported.
+pub const SOUND_MAXIMUM_PERMUTATION_SIZE : usize = 0x38E00;
+
+#[derive(PartialEq,Clone,Copy)]
+/// Sound formats determine how the samples of a permutation are stored.
+pub enum SoundFormat {
+    /// Samples are 16-bit big endian PCM.
+    PCM16,
+
+    /// Samples are compressed with Xbox ADPCM.
+    XboxADPCM,
+
+    /// Samples are compressed with IMA ADPCM.
+    IMAADPCM,
+
+    /// Samples are an Ogg Vorbis stream.
+    OggVorbis,
+
+    /// The format isn't known.
+    Unknown(u16)
+}
+impl SoundFormat {
+    /// Convert a 16-bit unsigned integer into a SoundFormat.
+    pub fn from_u16(format : u16) -> SoundFormat {
+        match format {
+            0 => SoundFormat::PCM16,
+            1 => SoundFormat::XboxADPCM,
+            2 => SoundFormat::IMAADPCM,
+            3 => SoundFormat::OggVorbis,
+            n => SoundFormat::Unknown(n)
+        }
+    }
+
+    /// Convert a SoundFormat to its equivalent 16-bit integer.
+    pub fn as_u16(&self) -> u16 {
+        match *self {
+            SoundFormat::PCM16 => 0,
+            SoundFormat::XboxADPCM => 1,
+            SoundFormat::IMAADPCM => 2,
+            SoundFormat::OggVorbis => 3,
+            SoundFormat::Unknown(n) => n
+        }
+    }
+}
+
+#[derive(Clone)]
+/// A permutation of a pitch range in a snd! tag. Long sounds are split into several
+/// permutations which are chained together using the next permutation index.
+pub struct SoundPermutation {
+    /// This is the offset of the permutation in the tag data.
+    pub offset : usize,
+    pub name : String,
+    pub format : SoundFormat,
+
+    /// This is the index of the next permutation in the chain within the same pitch range.
+    pub next_permutation : Option<usize>,
+
+    /// This is the offset of the samples. For internalized sounds, this is an offset in the tag's
+    /// asset data.
+    pub samples_offset : u32,
+
+    /// This is the size of the samples.
+    pub samples_size : u32,
+
+    /// Samples of external permutations are stored in sounds.map rather than in the map file.
+    pub external : bool
+}
+
+#[derive(Clone)]
+/// A pitch range in a snd! tag.
+pub struct SoundPitchRange {
+    /// This is the offset of the pitch range in the tag data.
+    pub offset : usize,
+    pub name : String,
+    pub natural_pitch : f32,
+
+    /// This is the number of permutations that start a chain. The remaining permutations are
+    /// continuations of these.
+    pub actual_permutation_count : u16,
+    pub permutations : Vec<SoundPermutation>
+}
+impl SoundPitchRange {
+    /// Get the indices of the permutations that start a chain.
+    pub fn actual_permutations(&self) -> Vec<usize> {
+        let count = self.actual_permutation_count as usize;
+        if count > 0 && count <= self.permutations.len() {
+            return (0..count).collect();
+        }
+
+        // If the count is not set, any permutation that no other permutation leads to starts a chain.
+        (0..self.permutations.len()).filter(|i| !self.permutations.iter().any(|p| p.next_permutation == Some(*i))).collect()
+    }
+
+    /// Get the indices of every permutation in a chain, starting with `permutation`.
+    ///
+    /// Returns `Err` if the chain is broken or loops.
+    pub fn permutation_chain(&self, permutation : usize) -> Result<Vec<usize>,&'static str> {
+        let mut chain = Vec::new();
+        let mut next = Some(permutation);
+        while let Some(n) = next {
+            if n >= self.permutations.len() {
+                return Err("invalid next permutation index on sound permutation");
+            }
+            if chain.contains(&n) {
+                return Err("sound permutation chain loops");
+            }
+            chain.push(n);
+            next = self.permutations[n].next_permutation;
+        }
+        Ok(chain)
+    }
+}
+
+#[derive(Clone)]
+/// An exported sound permutation, including every permutation chained to it.
+pub struct SoundExport {
+    pub pitch_range : usize,
+    pub pitch_range_name : String,
+    pub permutation : usize,
+    pub permutation_name : String,
+
+    /// This is the file extension for the data, either `wav` or `ogg`.
+    pub extension : &'static str,
+    pub data : Vec<u8>
+}
+
+impl Tag {
+    /// Get the number of channels of this snd! tag.
+    pub fn sound_channel_count(&self) -> Result<usize,&'static str> {
+        let data = self.p_sound_data()?;
+        match LittleEndian::read_u16(&data[0x6C..]) {
+            0 => Ok(1),
+            1 => Ok(2),
+            _ => Err("unknown channel count on sound tag")
+        }
+    }
+
+    /// Get the sample rate of this snd! tag.
+    pub fn sound_sample_rate(&self) -> Result<u32,&'static str> {
+        let data = self.p_sound_data()?;
+        match LittleEndian::read_u16(&data[0x6..]) {
+            0 => Ok(22050),
+            1 => Ok(44100),
+            _ => Err("unknown sample rate on sound tag")
+        }
+    }
+
+    /// Get the format of the samples of this snd! tag.
+    pub fn sound_format(&self) -> Result<SoundFormat,&'static str> {
+        let data = self.p_sound_data()?;
+        Ok(SoundFormat::from_u16(LittleEndian::read_u16(&data[0x6E..])))
+    }
+
+    /// Get the pitch ranges and permutations of this snd! tag.
+    pub fn sound_pitch_ranges(&self) -> Result<Vec<SoundPitchRange>,&'static str> {
+        let data = self.p_sound_data()?;
+        let range_count = LittleEndian::read_u32(&data[0x98..]) as usize;
+        if range_count == 0 {
+            return Ok(Vec::new());
+        }
+        let ranges_offset = match self.offset_from_memory_address(LittleEndian::read_u32(&data[0x98 + 4..])) {
+            Some(n) if n + range_count * SOUND_PITCH_RANGE_SIZE <= data.len() => n,
+            _ => return Err("invalid address on sound range reflexive")
+        };
+
+        let mut ranges = Vec::with_capacity(range_count);
+        for r in 0..range_count {
+            let range_offset = ranges_offset + r * SOUND_PITCH_RANGE_SIZE;
+            let range = &data[range_offset .. range_offset + SOUND_PITCH_RANGE_SIZE];
+            let permutation_count = LittleEndian::read_u32(&range[0x3C..]) as usize;
+            let mut permutations = Vec::with_capacity(permutation_count);
+            if permutation_count > 0 {
+                let permutations_offset = match self.offset_from_memory_address(LittleEndian::read_u32(&range[0x3C + 4..])) {
+                    Some(n) if n + permutation_count * SOUND_PERMUTATION_SIZE <= data.len() => n,
+                    _ => return Err("invalid address on sound permutation reflexive")
+                };
+                for p in 0..permutation_count {
+                    let permutation_offset = permutations_offset + p * SOUND_PERMUTATION_SIZE;
+                    let permutation = &data[permutation_offset .. permutation_offset + SOUND_PERMUTATION_SIZE];
+                    let next_permutation = LittleEndian::read_u16(&permutation[0x2A..]);
+                    permutations.push(SoundPermutation {
+                        offset : permutation_offset,
+                        name : string_from_slice(&permutation[0..0x20])?,
+                        format : SoundFormat::from_u16(LittleEndian::read_u16(&permutation[0x28..])),
+                        next_permutation : if next_permutation == 0xFFFF { None } else { Some(next_permutation as usize) },
+                        samples_offset : LittleEndian::read_u32(&permutation[0x48..]),
+                        samples_size : LittleEndian::read_u32(&permutation[0x40..]),
+                        external : permutation[0x44] & 1 != 0
+                    });
+                }
+            }
+            ranges.push(SoundPitchRange {
+                offset : range_offset,
+                name : string_from_slice(&range[0..0x20])?,
+                natural_pitch : LittleEndian::read_f32(&range[0x20..]),
+                actual_permutation_count : LittleEndian::read_u16(&range[0x2C..]),
+                permutations : permutations
+            });
+        }
+        Ok(ranges)
+    }
+
+    /// Get the samples of a permutation in this snd! tag from its asset data.
+    ///
+    /// Returns `Err` if the permutation is stored in sounds.map or if the samples are invalid.
+    pub fn sound_permutation_samples(&self, permutation : &SoundPermutation) -> Result<&[u8],&'static str> {
+        if permutation.external {
+            return Err("sound samples are not stored in the map");
+        }
+        let asset_data = match self.asset_data.as_ref() {
+            Some(n) => n,
+            None => return Err("sound tag has no asset data")
+        };
+        let start = permutation.samples_offset as usize;
+        let end = start + permutation.samples_size as usize;
+        if end > asset_data.len() {
+            return Err("sound points to invalid data");
+        }
+        Ok(&asset_data[start..end])
+    }
+
+    /// Export a permutation of a pitch range in this snd! tag, joining every permutation chained
+    /// to it.
+    ///
+    /// 16-bit PCM and Xbox ADPCM permutations are exported as 16-bit PCM WAV files, and Ogg Vorbis
+    /// permutations are exported as they are stored.
+    pub fn export_sound_permutation(&self, pitch_range : usize, permutation : usize) -> Result<SoundExport,&'static str> {
+        let ranges = self.sound_pitch_ranges()?;
+        let range = match ranges.get(pitch_range) {
+            Some(n) => n,
+            None => return Err("pitch range index out of bounds")
+        };
+        let chain = range.permutation_chain(permutation)?;
+        let format = range.permutations[permutation].format;
+        let channels = self.sound_channel_count()?;
+
+        let mut samples = Vec::new();
+        let mut stream = Vec::new();
+        for p in chain {
+            let permutation = &range.permutations[p];
+            if permutation.format != format {
+                return Err("sound permutation chain mixes formats");
+            }
+            let data = self.sound_permutation_samples(permutation)?;
+            match format {
+                SoundFormat::PCM16 => {
+                    if data.len() % (2 * channels) != 0 {
+                        return Err("PCM data is not made of whole samples");
+                    }
+                    samples.extend(data.chunks(2).map(BigEndian::read_i16));
+                },
+                SoundFormat::XboxADPCM => samples.append(&mut decode_xbox_adpcm(data, channels)?),
+                SoundFormat::OggVorbis => stream.extend_from_slice(data),
+                _ => return Err("unsupported sound format")
+            }
+        }
+
+        let (extension, data) = match format {
+            SoundFormat::OggVorbis => ("ogg", stream),
+            _ => ("wav", encode_wav(channels, self.sound_sample_rate()?, &samples))
+        };
+
+        Ok(SoundExport {
+            pitch_range : pitch_range,
+            pitch_range_name : range.name.clone(),
+            permutation : permutation,
+            permutation_name : range.permutations[permutation].name.clone(),
+            extension : extension,
+            data : data
+        })
+    }
+
+    /// Export every actual permutation of every pitch range in this snd! tag. See
+    /// `export_sound_permutation` for more information.
+    pub fn export_sounds(&self) -> Result<Vec<SoundExport>,&'static str> {
+        let mut exports = Vec::new();
+        for (r,range) in self.sound_pitch_ranges()?.iter().enumerate() {
+            for p in range.actual_permutations() {
+                exports.push(self.export_sound_permutation(r, p)?);
+            }
+        }
+        Ok(exports)
+    }
+
+    /// Import a WAV file into a pitch range of this snd! tag, encoding it as 16-bit PCM or Xbox
+    /// ADPCM.
+    ///
+    /// If `permutation` is the index of an actual permutation, its chain is replaced. Otherwise, a
+    /// new permutation is added. If `pitch_range` is the number of pitch ranges, a new pitch range
+    /// is added. Sounds larger than `SOUND_MAXIMUM_PERMUTATION_SIZE` are split into chained
+    /// permutations. The tag's sample rate, channel count, and format are updated, and the samples
+    /// are stored in the tag's asset data.
+    ///
+    /// Returns the index of the imported permutation, or `Err` without changing the tag if the
+    /// WAV file could not be imported. The sample rate must be 22050 Hz or 44100 Hz, and the
+    /// sample rate, channel count, and format must match any other permutations in the tag.
+    pub fn import_sound_permutation(&mut self, pitch_range : usize, permutation : Option<usize>, name : &str, wav : &[u8], format : SoundFormat) -> Result<usize,&'static str> {
+        let ranges = self.sound_pitch_ranges()?;
+        if pitch_range > ranges.len() {
+            return Err("pitch range index out of bounds");
+        }
+        let new_name = encode_latin1_string(name)?;
+        if new_name.len() >= 0x20 {
+            return Err("permutation name is too long");
+        }
+
+        let (channels, sample_rate, samples) = decode_wav(wav)?;
+        let (sample_rate_index, channel_count_index) = match (sample_rate, channels) {
+            (22050, 1) => (0, 0),
+            (22050, 2) => (0, 1),
+            (44100, 1) => (1, 0),
+            (44100, 2) => (1, 1),
+            (22050, _) | (44100, _) => return Err("only mono and stereo sounds are supported"),
+            _ => return Err("only 22050 Hz and 44100 Hz sounds are supported")
+        };
+
+        // Encode the samples and split them into whole blocks or frames.
+        let (encoded, chunk_alignment) = match format {
+            SoundFormat::PCM16 => {
+                let mut encoded = vec![0u8 ; samples.len() * 2];
+                for (s,sample) in samples.iter().enumerate() {
+                    BigEndian::write_i16(&mut encoded[s * 2..], *sample);
+                }
+                (encoded, 2 * channels)
+            },
+            SoundFormat::XboxADPCM => (encode_xbox_adpcm(&samples, channels), XBOX_ADPCM_BLOCK_SIZE * channels),
+            _ => return Err("sounds can only be imported as 16-bit PCM or Xbox ADPCM")
+        };
+        let chunk_size = SOUND_MAXIMUM_PERMUTATION_SIZE / chunk_alignment * chunk_alignment;
+        let mut chunks : Vec<&[u8]> = encoded.chunks(chunk_size).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+
+        // The sample rate, channel count, and format apply to the whole tag, so every other
+        // permutation has to match.
+        let replaced = match (ranges.get(pitch_range), permutation) {
+            (Some(r), Some(p)) if r.actual_permutations().contains(&p) => Some(p),
+            (_, Some(_)) => return Err("permutation index is not an actual permutation"),
+            _ => None
+        };
+        let has_other_permutations = ranges.iter().enumerate().any(|(r,range)| {
+            range.actual_permutations().iter().any(|p| r != pitch_range || Some(*p) != replaced)
+        });
+        if has_other_permutations && (self.sound_sample_rate()? != sample_rate || self.sound_channel_count()? != channels) {
+            return Err("sample rate and channel count must match the other permutations");
+        }
+        if has_other_permutations && self.sound_format()? != format {
+            return Err("format must match the other permutations");
+        }
+
+        // Rebuild the asset data, putting the permutations of the pitch range in chain order.
+        let mut asset_data = Vec::new();
+        let mut sample_offsets = Vec::new();
+        let tag_data = self.data.as_ref().unwrap();
+        let mut template = [0u8 ; SOUND_PERMUTATION_SIZE];
+        LittleEndian::write_f32(&mut template[0x24..], 1.0);
+        LittleEndian::write_u32(&mut template[0x34..], 0xFFFFFFFF);
+        LittleEndian::write_u32(&mut template[0x3C..], 0xFFFFFFFF);
+        if let Some(p) = ranges.iter().flat_map(|r| r.permutations.iter()).next() {
+            template[0x34..0x40].copy_from_slice(&tag_data[p.offset + 0x34 .. p.offset + 0x40]);
+        }
+
+        for (r,range) in ranges.iter().enumerate() {
+            if r == pitch_range {
+                continue;
+            }
+            for p in &range.permutations {
+                if !p.external {
+                    sample_offsets.push((p.offset, asset_data.len()));
+                    asset_data.extend_from_slice(self.sound_permutation_samples(p)?);
+                }
+            }
+        }
+
+        // Each chain is a list of permutation entries and their samples. The new samples are added
+        // as a chain of their own unless they replace one.
+        let mut chains = Vec::new();
+        if let Some(range) = ranges.get(pitch_range) {
+            for head in range.actual_permutations() {
+                let mut chain = Vec::new();
+                if Some(head) == replaced {
+                    let offset = range.permutations[head].offset;
+                    let mut entry = tag_data[offset .. offset + SOUND_PERMUTATION_SIZE].to_owned();
+                    entry[0x54..].iter_mut().for_each(|b| *b = 0);
+                    for chunk in &chunks {
+                        chain.push((entry.clone(), *chunk, false));
+                    }
+                }
+                else {
+                    for p in range.permutation_chain(head)? {
+                        let permutation = &range.permutations[p];
+                        let samples : &[u8] = if permutation.external { &[] } else { self.sound_permutation_samples(permutation)? };
+                        chain.push((tag_data[permutation.offset .. permutation.offset + SOUND_PERMUTATION_SIZE].to_owned(), samples, permutation.external));
+                    }
+                }
+                chains.push(chain);
+            }
+        }
+        if replaced.is_none() {
+            chains.push(chunks.iter().map(|c| (template.to_vec(), *c, false)).collect());
+        }
+
+        // Actual permutations go first, followed by the rest of each chain.
+        let head_count = chains.len();
+        let imported = replaced.unwrap_or(head_count - 1);
+        let mut next_index = head_count;
+        let mut heads = Vec::with_capacity(head_count);
+        let mut continuations = Vec::new();
+        for (c,chain) in chains.iter().enumerate() {
+            for (i,&(ref entry, samples, external)) in chain.iter().enumerate() {
+                let mut entry = entry.clone();
+                if c == imported {
+                    entry[0..0x20].iter_mut().for_each(|b| *b = 0);
+                    entry[0..new_name.len()].copy_from_slice(&new_name);
+                    LittleEndian::write_u16(&mut entry[0x28..], format.as_u16());
+                    LittleEndian::write_u32(&mut entry[0x40..], samples.len() as u32);
+                    LittleEndian::write_u32(&mut entry[0x44..], 0);
+                }
+                LittleEndian::write_u16(&mut entry[0x2A..], if i + 1 < chain.len() { (next_index + i) as u16 } else { 0xFFFF });
+                if !external {
+                    LittleEndian::write_u32(&mut entry[0x48..], asset_data.len() as u32);
+                    asset_data.extend_from_slice(samples);
+                }
+                if i == 0 {
+                    heads.push(entry);
+                }
+                else {
+                    continuations.push(entry);
+                }
+            }
+            next_index += chain.len() - 1;
+        }
+        if next_index > 0xFFFF {
+            return Err("too many sound permutations");
+        }
+        let mut new_permutations = Vec::with_capacity(next_index * SOUND_PERMUTATION_SIZE);
+        for entry in heads.iter().chain(continuations.iter()) {
+            new_permutations.extend_from_slice(entry);
+        }
+
+        // Everything is known to work at this point, so the tag can be changed.
+        {
+            let tag_data = self.data.as_mut().unwrap();
+            for (offset,samples_offset) in sample_offsets {
+                LittleEndian::write_u32(&mut tag_data[offset + 0x48..], samples_offset as u32);
+            }
+            LittleEndian::write_u16(&mut tag_data[0x6..], sample_rate_index);
+            LittleEndian::write_u16(&mut tag_data[0x6C..], channel_count_index);
+            LittleEndian::write_u16(&mut tag_data[0x6E..], format.as_u16());
+        }
+        if pitch_range == ranges.len() {
+            let mut new_ranges = Vec::with_capacity((ranges.len() + 1) * SOUND_PITCH_RANGE_SIZE);
+            if let Some(r) = ranges.first() {
+                new_ranges.extend_from_slice(&self.data.as_ref().unwrap()[r.offset .. r.offset + ranges.len() * SOUND_PITCH_RANGE_SIZE]);
+            }
+            let mut range = [0u8 ; SOUND_PITCH_RANGE_SIZE];
+            range[0..7].copy_from_slice(b"default");
+            LittleEndian::write_f32(&mut range[0x20..], 1.0);
+            LittleEndian::write_f32(&mut range[0x24..], 1.0);
+            LittleEndian::write_f32(&mut range[0x28..], 1.0);
+            new_ranges.extend_from_slice(&range);
+            self.replace_reflexive(0x98, SOUND_PITCH_RANGE_SIZE, &new_ranges);
+        }
+        let range_offset = self.sound_pitch_ranges()?[pitch_range].offset;
+        self.replace_reflexive(range_offset + 0x3C, SOUND_PERMUTATION_SIZE, &new_permutations);
+        let range_offset = self.sound_pitch_ranges()?[pitch_range].offset;
+        LittleEndian::write_u16(&mut self.data.as_mut().unwrap()[range_offset + 0x2C..], head_count as u16);
+        self.asset_data = Some(asset_data);
+
+        Ok(imported)
+    }
+
+    /// Import a WAV file into this snd! tag, replacing a permutation. See
+    /// `import_sound_permutation` for more information.
+    pub fn replace_sound_permutation(&mut self, pitch_range : usize, permutation : usize, wav : &[u8], format : SoundFormat) -> Result<(),&'static str> {
+        let name = match self.sound_pitch_ranges()?.get(pitch_range).and_then(|r| r.permutations.get(permutation)) {
+            Some(n) => n.name.clone(),
+            None => return Err("permutation index out of bounds")
+        };
+        self.import_sound_permutation(pitch_range, Some(permutation), &name, wav, format).map(|_| ())
+    }
+
+    // Get the tag data of a snd! tag, checking that the header is there.
+    fn p_sound_data(&self) -> Result<&Vec<u8>,&'static str> {
+        if self.tag_class.0 != SND {
+            return Err("tag is not a sound tag");
+        }
+        match self.data.as_ref() {
+            Some(n) if n.len() >= 0x98 + 0xC => Ok(n),
+            Some(_) => Err("sound tag is too small"),
+            None => Err("sound tag has no data")
+        }
+    }
+}
@@ -1,4 +1,28 @@
-use super::Tag;
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+use super::{Tag, RootSet, BITM, SND, OBJE, SBSP, SCNR};
+
+/// Size of a predicted resource.
+pub const PREDICTED_RESOURCE_SIZE : usize = 8;
+
+#[derive(Clone)]
+/// This describes what removing dead tags would do to a tag array.
+pub struct DeadTagReport {
+    /// These are the indices of the tags that would be removed, in ascending order.
+    pub dead_tags : Vec<usize>,
+
+    /// This is the total size of the tag data of the dead tags.
+    pub data_size : usize,
+
+    /// This is the total size of the asset data of the dead tags.
+    pub asset_data_size : usize,
+
+    /// These are the indices of the tags that would be kept, each paired with a chain of tag
+    /// indices that starts at a root and ends at the kept tag. Each tag in the chain references
+    /// the next one.
+    pub kept_tags : Vec<(usize,Vec<usize>)>
+}
 
 #[derive(Clone)]
 /// A tag array contains the tags that make up a Halo map.
@@ -124,6 +148,107 @@ impl TagArray {
         Ok(self.p_insert_recursive(origin_tag_array,origin_tag_index,&mut Vec::new()))
     }
 
+    /// Find a tag in this tag array with a path and a class. If it is not here, it is inserted
+    /// recursively from the origin tag array, if one is given.
+    ///
+    /// The index of the tag is returned, or `Err` if the tag could not be found in either.
+    ///
+    /// This function will panic if the tag array exceeds 65535 objects.
+    pub fn find_or_insert_recursive(&mut self, tag_path : &str, tag_class : u32, origin_tag_array : Option<&TagArray>) -> Result<usize,&'static str> {
+        if let Some(n) = self.find_tag(tag_path, tag_class) {
+            return Ok(n);
+        }
+        match origin_tag_array.and_then(|o| o.find_tag(tag_path, tag_class).map(|i| (o, i))) {
+            Some((origin_tag_array, origin_tag_index)) => self.insert_recursive(origin_tag_array, origin_tag_index),
+            None => Err("tag not found")
+        }
+    }
+
+    /// Duplicate a tag under a new path, returning the index of the copy.
+    ///
+    /// References the tag makes to itself are changed to point to the copy. All other references
+    /// remain pointed at the original tags. If a tag with the new path and class already exists,
+    /// `Err` is returned without any changes to the array.
+    ///
+    /// This function will panic if the tag array exceeds 65535 objects.
+    pub fn duplicate(&mut self, tag_index : usize, new_path : &str) -> Result<usize,&'static str> {
+        self.duplicate_recursive_with(tag_index, new_path, "", |_,_| false)
+    }
+
+    /// Duplicate a tag under a new path, also duplicating any dependencies with one of the given
+    /// classes. See `duplicate_recursive_with` for more information.
+    pub fn duplicate_recursive(&mut self, tag_index : usize, new_path : &str, path_prefix : &str, tag_classes : &[u32]) -> Result<usize,&'static str> {
+        self.duplicate_recursive_with(tag_index, new_path, path_prefix, |_,tag| tag_classes.contains(&tag.tag_class.0))
+    }
+
+    /// Duplicate a tag under a new path, also duplicating any dependencies that `filter` returns
+    /// true for. The filter is given the index of the dependency and the dependency itself.
+    ///
+    /// Duplicated dependencies use the original tag path with `path_prefix` prepended to it.
+    /// Dependencies are only followed through tags that are duplicated, and every reference
+    /// between duplicated tags is changed to point to the copies.
+    ///
+    /// If any of the new tags would conflict with an existing tag's path and class, `Err` is
+    /// returned without any changes to the array. Otherwise, the index of the copy is returned.
+    ///
+    /// This function will panic if the tag array exceeds 65535 objects.
+    pub fn duplicate_recursive_with<F>(&mut self, tag_index : usize, new_path : &str, path_prefix : &str, filter : F) -> Result<usize,&'static str> where F : Fn(usize, &Tag) -> bool {
+        assert!(tag_index < self.tags.len(),"tag out of bounds");
+
+        // Origin tag index, new tag path
+        let mut copies : Vec<(usize,String)> = vec![(tag_index, new_path.to_owned())];
+        let mut i = 0;
+        while i < copies.len() {
+            let origin_index = copies[i].0;
+            for r in self.tags[origin_index].references(&self) {
+                if r.tag_index == 0xFFFFFFFF || copies.iter().any(|c| c.0 == r.tag_index) {
+                    continue;
+                }
+                let tag = &self.tags[r.tag_index];
+                if filter(r.tag_index, tag) {
+                    copies.push((r.tag_index, format!("{}{}", path_prefix, tag.tag_path)));
+                }
+            }
+            i += 1;
+        }
+
+        for c in &copies {
+            let class = self.tags[c.0].tag_class.0;
+            if self.find_tag(&c.1, class).is_some() {
+                return Err("tag already exists")
+            }
+            if copies.iter().filter(|d| d.1 == c.1 && self.tags[d.0].tag_class.0 == class).count() > 1 {
+                return Err("tag already exists")
+            }
+        }
+
+        let first_index = self.tags.len();
+        if first_index + copies.len() - 1 > 65535 {
+            panic!("tag array exceeds 65535 objects")
+        }
+
+        for c in &copies {
+            let mut tag = self.tags[c.0].to_owned();
+            tag.tag_path = c.1.to_owned();
+            self.tags.push(tag);
+        }
+
+        // Point references between the copies to the copies.
+        for n in first_index .. self.tags.len() {
+            for mut r in self.tags[n].references(&self) {
+                match copies.iter().position(|c| c.0 == r.tag_index) {
+                    Some(p) => {
+                        r.tag_index = first_index + p;
+                        self.tags[n].set_reference(&r);
+                    },
+                    None => ()
+                }
+            }
+        }
+
+        Ok(first_index)
+    }
+
     /// Remove a specific tag from the tag array and returns it.
     ///
     /// This function will panic if the tag does not already exist.
@@ -145,87 +270,202 @@ impl TagArray {
                 self.tags[t].set_reference(&r);
             }
         }
+        self.principal_tag = match self.principal_tag {
+            Some(n) if n == tag => None,
+            Some(n) if n > tag => Some(n - 1),
+            n => n
+        };
         self.tags.remove(tag)
     }
 
-    /// Remove all tags not referenced (recursively) by tagc tags, matg tags, and the principal scenario tag, as well as essential tags.
-    pub fn remove_dead_tags(&mut self) {
-        let mut keep_list = Vec::new();
+    /// Regenerate the predicted resources of every object, scenario, and sbsp tag from the
+    /// bitmaps and sounds they depend on.
+    ///
+    /// Dependencies are followed recursively, but not into other objects, scenarios, or sbsps,
+    /// since those predict their own resources. Every cluster of an sbsp tag predicts the
+    /// resources of the whole sbsp tag.
+    ///
+    /// If a tag's predicted resources cannot be found, `Err` is returned and no tags are changed.
+    pub fn regenerate_predicted_resources(&mut self) -> Result<(),&'static str> {
+        // Build every new tag before changing any of them so an error leaves the tags unchanged.
+        let mut new_tags = Vec::new();
+        for t in 0..self.tags.len() {
+            if !has_predicted_resources(&self.tags[t]) {
+                continue;
+            }
+            let resources = self.p_predicted_resources(t);
+            let mut tag = self.tags[t].clone();
+            let count = predicted_resource_offsets(&tag)?.len();
+            for i in 0..count {
+                let offset = predicted_resource_offsets(&tag)?[i];
+                tag.replace_reflexive(offset, PREDICTED_RESOURCE_SIZE, &resources);
+            }
+            new_tags.push((t, tag));
+        }
+
+        for (t, tag) in new_tags {
+            self.tags[t] = tag;
+        }
+        Ok(())
+    }
+
+    // Get the class to write in a dependency on a tag, checking that the tag is or inherits from
+    // a class. Dependencies use the tag's own class, as maps do.
+    pub(super) fn p_dependency_class(&self, reference : Option<usize>, tag_class : u32) -> Result<u32,&'static str> {
+        match reference {
+            Some(n) => match self.tags.get(n) {
+                Some(t) if t.tag_class.0 == tag_class || t.tag_class.1 == tag_class || t.tag_class.2 == tag_class => Ok(t.tag_class.0),
+                Some(_) => Err("referenced tag is the wrong class"),
+                None => Err("referenced tag does not exist")
+            },
+            None => Ok(tag_class)
+        }
+    }
+
+    // Build the predicted resources of a tag from its dependencies.
+    fn p_predicted_resources(&self, tag_index : usize) -> Vec<u8> {
         let tag_count = self.tags.len();
-        keep_list.resize(tag_count, false);
+        let mut visited = vec![false ; tag_count];
+        let mut stack = vec![tag_index];
+        let mut resources = Vec::new();
+        while let Some(t) = stack.pop() {
+            if visited[t] {
+                continue;
+            }
+            visited[t] = true;
+            let tag = &self.tags[t];
+            if tag.tag_class.0 == BITM || tag.tag_class.0 == SND {
+                resources.push(t);
+                continue;
+            }
+            if t != tag_index && has_predicted_resources(tag) {
+                continue;
+            }
 
-        for i in 0..tag_count {
-            {
-                let tag = unsafe { self.tags.get_unchecked(i) };
-                let class = tag.tag_class.0;
-                let is_principal_tag = {
-                    match self.principal_tag {
-                        Some(n) => i == n,
-                        None => false
-                    }
-                };
-                if match class {
-                    0x6269746D => {
-                        match &tag.tag_path as &str {
-                            "ui\\shell\\bitmaps\\background" => false,
-                            "ui\\shell\\bitmaps\\trouble_brewing" => false,
-                            _ => true
-                        }
-                    },
-                    0x736E6421 => {
-                        match &tag.tag_path as &str {
-                            "sound\\sfx\\ui\\cursor" => false,
-                            "sound\\sfx\\ui\\forward" => false,
-                            "sound\\sfx\\ui\\back" => false,
-                            _ => true
-                        }
-                    },
-                    0x75737472 => {
-                        match &tag.tag_path as &str {
-                            "ui\\shell\\strings\\loading" => false,
-                            "ui\\shell\\main_menu\\mp_map_list" => false,
-                            _ => true
-                        }
-                    },
-                    0x6D617467 => {
-                        match &tag.tag_path as &str {
-                            "globals\\globals" => false,
-                            _ => true
-                        }
-                    },
-                    0x74616763 => false,
-                    _ => !is_principal_tag
-                } {
-                    if !is_principal_tag {
-                        continue;
-                    }
+            // The old predicted resources are not followed, as they are being replaced.
+            let old_resources = predicted_resource_ranges(tag);
+            for r in tag.references(self) {
+                if r.tag_index < tag_count && !old_resources.iter().any(|&(o, s)| r.offset >= o && r.offset < o + s) {
+                    stack.push(r.tag_index);
                 }
             }
-
-            self.p_save_tag_recursive(i, &mut keep_list);
         }
+        resources.sort();
 
-        for i in (0..tag_count).rev() {
-            if keep_list[i] {
-                continue;
+        // Bitmaps predict each of their bitmaps. Sounds are predicted as a whole.
+        let mut data = Vec::new();
+        for r in resources {
+            let tag = &self.tags[r];
+            let (resource_type, count) = if tag.tag_class.0 == BITM {
+                (0, match tag.data.as_ref() {
+                    Some(n) if n.len() >= 0x64 => LittleEndian::read_u32(&n[0x60..]).max(1) as usize,
+                    _ => 1
+                })
             }
+            else {
+                (1, 1)
+            };
+            for i in 0..count {
+                let mut resource = [0u8 ; PREDICTED_RESOURCE_SIZE];
+                LittleEndian::write_u16(&mut resource[0x0..], resource_type);
+                LittleEndian::write_u16(&mut resource[0x2..], i as u16);
+                LittleEndian::write_u32(&mut resource[0x4..], tag_index_to_tag_id(r));
+                data.extend_from_slice(&resource);
+            }
+        }
+        data
+    }
+
+    /// Remove all tags not referenced (recursively) by tagc tags, matg tags, and the principal scenario tag, as well as essential tags.
+    pub fn remove_dead_tags(&mut self) {
+        self.remove_dead_tags_with(&RootSet::essential(), |_,_| false)
+    }
+
+    /// Remove all tags not referenced (recursively) by a root. A tag is a root if it is in
+    /// `root_set` or if `is_root` returns true for it. `is_root` is given the index of the tag and
+    /// the tag itself.
+    pub fn remove_dead_tags_with<F>(&mut self, root_set : &RootSet, is_root : F) where F : Fn(usize, &Tag) -> bool {
+        let report = self.analyze_dead_tags(root_set, is_root);
+        for i in report.dead_tags.into_iter().rev() {
             self.remove(i);
         }
     }
 
-    fn p_save_tag_recursive(&mut self, tag_index : usize, keep_list : &mut [bool]) {
-        if keep_list[tag_index] {
-            return;
+    /// Determine what `remove_dead_tags_with` would remove without changing the tag array.
+    pub fn analyze_dead_tags<F>(&self, root_set : &RootSet, is_root : F) -> DeadTagReport where F : Fn(usize, &Tag) -> bool {
+        let tag_count = self.tags.len();
+
+        // This is the tag that first referenced each kept tag, or itself if it is a root.
+        let mut parents : Vec<Option<usize>> = Vec::new();
+        parents.resize(tag_count, None);
+
+        let mut queue = Vec::new();
+        for i in 0..tag_count {
+            let tag = &self.tags[i];
+            let is_principal_tag = {
+                match self.principal_tag {
+                    Some(n) => i == n,
+                    None => false
+                }
+            };
+            if root_set.contains(tag, is_principal_tag) || is_root(i, tag) {
+                parents[i] = Some(i);
+                queue.push(i);
+            }
         }
-        keep_list[tag_index] = true;
 
-        let references = self.tags[tag_index].references(&self);
-        for i in references {
-            if tag_index == i.tag_index {
-                continue;
+        // Go breadth-first so each chain is as short as possible.
+        let mut q = 0;
+        while q < queue.len() {
+            let tag_index = queue[q];
+            for r in self.tags[tag_index].references(&self) {
+                if r.tag_index >= tag_count || parents[r.tag_index].is_some() {
+                    continue;
+                }
+                parents[r.tag_index] = Some(tag_index);
+                queue.push(r.tag_index);
             }
-            self.p_save_tag_recursive(i.tag_index, keep_list);
+            q += 1;
         }
+
+        let mut report = DeadTagReport {
+            dead_tags : Vec::new(),
+            data_size : 0,
+            asset_data_size : 0,
+            kept_tags : Vec::new()
+        };
+
+        for i in 0..tag_count {
+            match parents[i] {
+                Some(_) => {
+                    let mut chain = vec![i];
+                    let mut current = i;
+                    while let Some(n) = parents[current] {
+                        if n == current {
+                            break;
+                        }
+                        chain.push(n);
+                        current = n;
+                    }
+                    chain.reverse();
+                    report.kept_tags.push((i, chain));
+                },
+                None => {
+                    let tag = &self.tags[i];
+                    report.data_size += match tag.data.as_ref() {
+                        Some(n) => n.len(),
+                        None => 0
+                    };
+                    report.asset_data_size += match tag.asset_data.as_ref() {
+                        Some(n) => n.len(),
+                        None => 0
+                    };
+                    report.dead_tags.push(i);
+                }
+            }
+        }
+
+        report
     }
 
     fn p_insert_recursive(&mut self, origin_tag_array : &TagArray, origin_tag_index : usize, tags_to_be_imported : &mut Vec<usize>) -> usize {
@@ -278,3 +518,33 @@ pub fn tag_index_to_tag_id(index : usize) -> u32 {
     let secondary_index = (index + 0xE174) * 0x10000;
     (tag_index + secondary_index) as u32
 }
+
+// Check if a tag has its own predicted resources.
+fn has_predicted_resources(tag : &Tag) -> bool {
+    tag.data.is_some() && (tag.tag_class.0 == OBJE || tag.tag_class.1 == OBJE || tag.tag_class.2 == OBJE || tag.tag_class.0 == SCNR || tag.tag_class.0 == SBSP)
+}
+
+// Get the offsets of the predicted resource reflexives of a tag.
+fn predicted_resource_offsets(tag : &Tag) -> Result<Vec<usize>,&'static str> {
+    if !has_predicted_resources(tag) {
+        return Ok(Vec::new());
+    }
+    if tag.tag_class.0 == SCNR {
+        Ok(vec![0xEC])
+    }
+    else if tag.tag_class.0 == SBSP {
+        Ok(tag.reflexive_elements(0x14C, 104)?.into_iter().map(|c| c + 0x28).collect())
+    }
+    else {
+        Ok(vec![0x170])
+    }
+}
+
+// Get the offsets and sizes of the predicted resources of a tag in its tag data.
+fn predicted_resource_ranges(tag : &Tag) -> Vec<(usize,usize)> {
+    let offsets = predicted_resource_offsets(tag).unwrap_or_default();
+    offsets.into_iter().filter_map(|o| match tag.reflexive_elements(o, PREDICTED_RESOURCE_SIZE) {
+        Ok(ref n) if !n.is_empty() => Some((n[0], n.len() * PREDICTED_RESOURCE_SIZE)),
+        _ => None
+    }).collect()
+}
@@ -0,0 +1,272 @@
+extern crate byteorder;
+use self::byteorder::{ByteOrder,LittleEndian};
+
+// Each channel of an Xbox ADPCM block is 36 bytes and holds 65 samples: the initial sample in the
+// header and 64 nibbles.
+pub const XBOX_ADPCM_BLOCK_SIZE : usize = 36;
+pub const XBOX_ADPCM_BLOCK_SAMPLES : usize = 65;
+
+const IMA_INDEX_TABLE : [i32 ; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const IMA_STEP_TABLE : [i32 ; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493, 10442,
+    11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767
+];
+
+/// Encode interleaved 16-bit samples as a PCM WAV file.
+pub fn encode_wav(channels : usize, sample_rate : u32, samples : &[i16]) -> Vec<u8> {
+    let data_size = samples.len() * 2;
+    let block_align = channels * 2;
+    let mut wav = vec![0u8 ; 44];
+    wav[0..4].copy_from_slice(b"RIFF");
+    LittleEndian::write_u32(&mut wav[4..], (36 + data_size) as u32);
+    wav[8..12].copy_from_slice(b"WAVE");
+    wav[12..16].copy_from_slice(b"fmt ");
+    LittleEndian::write_u32(&mut wav[16..], 16);
+    LittleEndian::write_u16(&mut wav[20..], 1);
+    LittleEndian::write_u16(&mut wav[22..], channels as u16);
+    LittleEndian::write_u32(&mut wav[24..], sample_rate);
+    LittleEndian::write_u32(&mut wav[28..], sample_rate * block_align as u32);
+    LittleEndian::write_u16(&mut wav[32..], block_align as u16);
+    LittleEndian::write_u16(&mut wav[34..], 16);
+    wav[36..40].copy_from_slice(b"data");
+    LittleEndian::write_u32(&mut wav[40..], data_size as u32);
+    wav.reserve_exact(data_size);
+    for s in samples {
+        let mut sample = [0u8 ; 2];
+        LittleEndian::write_i16(&mut sample, *s);
+        wav.extend_from_slice(&sample);
+    }
+    wav
+}
+
+// Decode a single 4-bit IMA ADPCM nibble, updating the predictor and step index.
+fn decode_ima_nibble(nibble : u8, predictor : &mut i32, step_index : &mut i32) -> i16 {
+    let step = IMA_STEP_TABLE[*step_index as usize];
+    let mut difference = step >> 3;
+    if nibble & 1 != 0 {
+        difference += step >> 2;
+    }
+    if nibble & 2 != 0 {
+        difference += step >> 1;
+    }
+    if nibble & 4 != 0 {
+        difference += step;
+    }
+    if nibble & 8 != 0 {
+        *predictor -= difference;
+    }
+    else {
+        *predictor += difference;
+    }
+    *predictor = (*predictor).max(-32768).min(32767);
+    *step_index = (*step_index + IMA_INDEX_TABLE[nibble as usize]).max(0).min(88);
+    *predictor as i16
+}
+
+/// Decode Xbox ADPCM data into interleaved 16-bit samples.
+///
+/// Each block starts with a four byte header for each channel (the initial sample and step
+/// index), followed by four bytes of nibbles for each channel at a time. The initial sample is the
+/// first sample of the block.
+///
+/// Returns `Err` if the data is not made of whole blocks.
+pub fn decode_xbox_adpcm(data : &[u8], channels : usize) -> Result<Vec<i16>,&'static str> {
+    if channels == 0 {
+        return Err("invalid channel count");
+    }
+    let block_size = XBOX_ADPCM_BLOCK_SIZE * channels;
+    if data.len() % block_size != 0 {
+        return Err("ADPCM data is not made of whole blocks");
+    }
+
+    let block_count = data.len() / block_size;
+    let mut samples = vec![0i16 ; block_count * XBOX_ADPCM_BLOCK_SAMPLES * channels];
+    for b in 0..block_count {
+        let block = &data[b * block_size .. (b + 1) * block_size];
+        let output = &mut samples[b * XBOX_ADPCM_BLOCK_SAMPLES * channels .. (b + 1) * XBOX_ADPCM_BLOCK_SAMPLES * channels];
+        for c in 0..channels {
+            let header = &block[c * 4 .. c * 4 + 4];
+            let mut predictor = LittleEndian::read_i16(header) as i32;
+            let mut step_index = (header[2] as i32).min(88);
+            output[c] = predictor as i16;
+
+            // Nibbles are stored in groups of four bytes (eight samples) per channel.
+            for s in 1..XBOX_ADPCM_BLOCK_SAMPLES {
+                let n = s - 1;
+                let group = n / 8;
+                let byte = block[channels * 4 + (group * channels + c) * 4 + (n % 8) / 2];
+                let nibble = if n % 2 == 0 { byte & 0xF } else { byte >> 4 };
+                output[s * channels + c] = decode_ima_nibble(nibble, &mut predictor, &mut step_index);
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// Decode a WAV file into its channel count, sample rate, and interleaved 16-bit samples.
+///
+/// 8-bit, 16-bit, 24-bit, and 32-bit PCM as well as 32-bit floating point samples are supported.
+pub fn decode_wav(wav : &[u8]) -> Result<(usize,u32,Vec<i16>),&'static str> {
+    if wav.len() < 12 || &wav[0..4] != b"RIFF" || &wav[8..12] != b"WAVE" {
+        return Err("not a WAV file");
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut offset = 12;
+    while offset + 8 <= wav.len() {
+        let chunk_size = LittleEndian::read_u32(&wav[offset + 4..]) as usize;
+        let start = offset + 8;
+        let end = start + chunk_size;
+        if end > wav.len() {
+            return Err("WAV chunk is out of bounds");
+        }
+        match &wav[offset .. offset + 4] {
+            b"fmt " => format = Some(&wav[start..end]),
+            b"data" => data = Some(&wav[start..end]),
+            _ => ()
+        }
+        offset = end + chunk_size % 2;
+    }
+
+    let format = match format {
+        Some(n) if n.len() >= 16 => n,
+        _ => return Err("WAV file has no format chunk")
+    };
+    let data = match data {
+        Some(n) => n,
+        None => return Err("WAV file has no data chunk")
+    };
+
+    let mut format_tag = LittleEndian::read_u16(&format[0..]);
+    let channels = LittleEndian::read_u16(&format[2..]) as usize;
+    let sample_rate = LittleEndian::read_u32(&format[4..]);
+    let bits_per_sample = LittleEndian::read_u16(&format[14..]);
+    if format_tag == 0xFFFE {
+        if format.len() < 26 {
+            return Err("invalid WAV format chunk");
+        }
+        format_tag = LittleEndian::read_u16(&format[24..]);
+    }
+    if channels == 0 {
+        return Err("invalid channel count");
+    }
+
+    let mut samples : Vec<i16> = match (format_tag, bits_per_sample) {
+        (1, 8) => data.iter().map(|s| ((*s as i16) - 128) << 8).collect(),
+        (1, 16) => data.chunks(2).filter(|s| s.len() == 2).map(LittleEndian::read_i16).collect(),
+        (1, 24) => data.chunks(3).filter(|s| s.len() == 3).map(|s| LittleEndian::read_i16(&s[1..])).collect(),
+        (1, 32) => data.chunks(4).filter(|s| s.len() == 4).map(|s| (LittleEndian::read_i32(s) >> 16) as i16).collect(),
+        (3, 32) => data.chunks(4).filter(|s| s.len() == 4).map(|s| (LittleEndian::read_f32(s).max(-1.0).min(1.0) * 32767.0) as i16).collect(),
+        _ => return Err("unsupported WAV sample format")
+    };
+
+    let whole_frames = samples.len() / channels * channels;
+    samples.truncate(whole_frames);
+    Ok((channels, sample_rate, samples))
+}
+
+// Encode a single sample as a 4-bit IMA ADPCM nibble, updating the predictor and step index the
+// same way the decoder will.
+fn encode_ima_nibble(sample : i16, predictor : &mut i32, step_index : &mut i32) -> u8 {
+    let step = IMA_STEP_TABLE[*step_index as usize];
+    let mut difference = sample as i32 - *predictor;
+    let mut nibble = 0;
+    if difference < 0 {
+        nibble = 8;
+        difference = -difference;
+    }
+    if difference >= step {
+        nibble |= 4;
+        difference -= step;
+    }
+    if difference >= step >> 1 {
+        nibble |= 2;
+        difference -= step >> 1;
+    }
+    if difference >= step >> 2 {
+        nibble |= 1;
+    }
+    decode_ima_nibble(nibble, predictor, step_index);
+    nibble
+}
+
+/// Encode interleaved 16-bit samples as Xbox ADPCM. The last block is padded with silence.
+///
+/// This function will panic if `channels` is zero.
+pub fn encode_xbox_adpcm(samples : &[i16], channels : usize) -> Vec<u8> {
+    assert!(channels > 0, "invalid channel count");
+    let frames = samples.len() / channels;
+    let block_count = (frames + XBOX_ADPCM_BLOCK_SAMPLES - 1) / XBOX_ADPCM_BLOCK_SAMPLES;
+    let block_size = XBOX_ADPCM_BLOCK_SIZE * channels;
+    let mut data = vec![0u8 ; block_count * block_size];
+    let mut step_indices = vec![0i32 ; channels];
+
+    for b in 0..block_count {
+        let block = &mut data[b * block_size .. (b + 1) * block_size];
+        let sample = |s : usize, c : usize| -> i16 {
+            let frame = b * XBOX_ADPCM_BLOCK_SAMPLES + s;
+            if frame < frames { samples[frame * channels + c] } else { 0 }
+        };
+        for (c, step_index) in step_indices.iter_mut().enumerate() {
+            let mut predictor = sample(0, c) as i32;
+            LittleEndian::write_i16(&mut block[c * 4..], predictor as i16);
+            block[c * 4 + 2] = *step_index as u8;
+            block[c * 4 + 3] = 0;
+
+            for s in 1..XBOX_ADPCM_BLOCK_SAMPLES {
+                let n = s - 1;
+                let group = n / 8;
+                let byte = &mut block[channels * 4 + (group * channels + c) * 4 + (n % 8) / 2];
+                let nibble = encode_ima_nibble(sample(s, c), &mut predictor, step_index);
+                if n % 2 == 0 {
+                    *byte |= nibble;
+                }
+                else {
+                    *byte |= nibble << 4;
+                }
+            }
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adpcm_block_holds_every_sample() {
+        // Every nibble of a block decodes to a sample, so 36 bytes per channel is 65 samples. With
+        // every nibble increasing the sample, the last one must be larger than the one before it.
+        let mut data = vec![0x11u8 ; XBOX_ADPCM_BLOCK_SIZE * 2];
+        for header in data[0..8].chunks_mut(4) {
+            header.copy_from_slice(&[0, 0, 0, 0]);
+        }
+        let samples = decode_xbox_adpcm(&data, 2).unwrap();
+        assert_eq!(samples.len(), 65 * 2);
+        assert!(samples[64 * 2] > samples[63 * 2] && samples[64 * 2 + 1] > samples[63 * 2 + 1]);
+    }
+
+    #[test]
+    fn adpcm_encode_then_decode() {
+        // A slow stereo sine wave is tracked closely, including at the end of each block, once the
+        // step size has adapted to it.
+        let frames = XBOX_ADPCM_BLOCK_SAMPLES * 3;
+        let samples : Vec<i16> = (0..frames).flat_map(|f| {
+            let v = ((f as f32 / 20.0).sin() * 8000.0) as i16;
+            vec![v, -v]
+        }).collect();
+        let encoded = encode_xbox_adpcm(&samples, 2);
+        assert_eq!(encoded.len(), XBOX_ADPCM_BLOCK_SIZE * 2 * 3);
+        let decoded = decode_xbox_adpcm(&encoded, 2).unwrap();
+        assert_eq!(decoded.len(), samples.len());
+        for (a, b) in samples.iter().zip(decoded.iter()).skip(16 * 2) {
+            assert!((*a as i32 - *b as i32).abs() < 400, "{} decoded as {}", a, b);
+        }
+    }
+}